@@ -1,27 +1,20 @@
+#![recursion_limit = "1024"]
+
 #[macro_use]
 extern crate pipeline;
 
-pub use pipeline::shared::{
-    bind_float, bind_vec, bind_vec3, is_gl_builtin, ready_to_run, update_bind_context,
-};
-pub use pipeline::wgpu_compute_header::{compile, read_fvec3, run, ComputeShader};
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{compile, read_fvec3, ComputeShader, PingPong};
 
-pub use static_assertions::const_assert;
+pub use wgpu_macros::{generic_bindings, init};
 
 async fn execute_gpu() {
-    // qualifiers
-    // buffer: is a buffer?
-    // in: this parameter must be bound to before the program runs
-    //     thus it can be read inside of the program scope
-    // out: if this parameter is bound, it must be rebound after each iteration
-    //      Only out variables can be mutated
-    //      Only out variables can be read as a result of the program
-    //      If out has been unassigned then an error is raised when it is read
-    // loop: one or more of these loop annotations are required per program. Atm, the values bound is assumed to be of equal length and this gives the number of iterations(gl_GlobalInvocationID.x)
-    //      the size of any out buffers that need to be created
+    init!();
 
     // TODO how to set a work group larger than 1?
-    const BOIDS: (ComputeShader, [&str; 32], [&str; 32]) = compute_shader! {
+    my_shader! {boids = {
         [[uniform in] float] deltaT;
         [[uniform in] float] rule1Distance;
         [[uniform in] float] rule2Distance;
@@ -35,14 +28,6 @@ async fn execute_gpu() {
         [[buffer out] vec3[]] dstParticlePos;
         [[buffer out] vec3[]] dstParticleVel;
 
-
-            /* layout(std140, set = 0, binding = 1) buffer SrcParticles {
-                Particle particles[NUM_PARTICLES];
-            } srcParticles;
-            layout(std140, set = 0, binding = 2) buffer DstParticles {
-                Particle particles[NUM_PARTICLES];
-            } dstParticles; */
-
         {{
             // TODO This would be nice
             /* struct Particle {
@@ -104,126 +89,60 @@ async fn execute_gpu() {
                 dstParticleVel[index] = vec3(vVel, 0.0);
             }
         }}
-    };
-
-    let mut srcParticlePos: Vec<[f32; 3]> = vec![[0.0, 0.0, 0.0], [0.3, 0.2, 0.0]];
-    let mut srcParticleVel: Vec<[f32; 3]> = vec![[0.01, -0.02, 0.0], [-0.05, -0.03, 0.0]];
-    let deltaT: f32 = 0.04;
-    let rule1Distance: f32 = 0.1;
-    let rule2Distance: f32 = 0.25;
-    let rule3Distance: f32 = 0.25;
-    let rule1Scale: f32 = 0.02;
-    let rule2Scale: f32 = 0.05;
-    let rule3Scale: f32 = 0.005;
-
-    /*     let texture : wgpu::Buffer = create_texture(".."); */
-    loop {
-        const S: ComputeShader = BOIDS.0;
-        const STARTING_BIND_CONTEXT: [&str; 32] = BOIDS.1;
-
-        let (program, mut bindings, mut out_bindings) = compile(&S).await;
-
-        const BIND_CONTEXT_1: [&str; 32] = update_bind_context(&STARTING_BIND_CONTEXT, "deltaT");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &deltaT,
-            "deltaT".to_string(),
-        );
-
-        const BIND_CONTEXT_2: [&str; 32] = update_bind_context(&BIND_CONTEXT_1, "rule1Distance");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &rule1Distance,
-            "rule1Distance".to_string(),
-        );
-
-        const BIND_CONTEXT_3: [&str; 32] = update_bind_context(&BIND_CONTEXT_2, "rule2Distance");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &rule2Distance,
-            "rule2Distance".to_string(),
-        );
-
-        const BIND_CONTEXT_4: [&str; 32] = update_bind_context(&BIND_CONTEXT_3, "rule3Distance");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &rule3Distance,
-            "rule3Distance".to_string(),
-        );
-
-        const BIND_CONTEXT_5: [&str; 32] = update_bind_context(&BIND_CONTEXT_4, "rule1Scale");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &rule1Scale,
-            "rule1Scale".to_string(),
-        );
-
-        const BIND_CONTEXT_6: [&str; 32] = update_bind_context(&BIND_CONTEXT_5, "rule2Scale");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &rule2Scale,
-            "rule2Scale".to_string(),
-        );
-
-        const BIND_CONTEXT_7: [&str; 32] = update_bind_context(&BIND_CONTEXT_6, "rule3Scale");
-        bind_float(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &rule3Scale,
-            "rule3Scale".to_string(),
-        );
-
-        const BIND_CONTEXT_8: [&str; 32] = update_bind_context(&BIND_CONTEXT_7, "srcParticlePos");
-        bind_vec3(
-            &program,
-            &mut bindings,
-            &mut out_bindings,
-            &srcParticlePos,
-            "srcParticlePos".to_string(),
-        );
-
-        const BIND_CONTEXT_9: [&str; 32] = update_bind_context(&BIND_CONTEXT_8, "srcParticleVel");
-        bind_vec3(
+    }}
+
+    const S: ComputeShader = eager_compute_shader! {boids!()};
+    eager_binding! {context = boids!()};
+
+    let srcParticlePos: Vec<[f32; 3]> = vec![[0.0, 0.0, 0.0], [0.3, 0.2, 0.0]];
+    let srcParticleVel: Vec<[f32; 3]> = vec![[0.01, -0.02, 0.0], [-0.05, -0.03, 0.0]];
+    let deltaT: Vec<f32> = vec![0.04];
+    let rule1Distance: Vec<f32> = vec![0.1];
+    let rule2Distance: Vec<f32> = vec![0.25];
+    let rule3Distance: Vec<f32> = vec![0.25];
+    let rule1Scale: Vec<f32> = vec![0.02];
+    let rule2Scale: Vec<f32> = vec![0.05];
+    let rule3Scale: Vec<f32> = vec![0.005];
+
+    let (program, mut bindings, mut out_bindings) = compile(&S).await;
+
+    let context1 = context.bind_deltaT(&deltaT, &program, &mut bindings, &mut out_bindings);
+    let context2 =
+        context1.bind_rule1Distance(&rule1Distance, &program, &mut bindings, &mut out_bindings);
+    let context3 =
+        context2.bind_rule2Distance(&rule2Distance, &program, &mut bindings, &mut out_bindings);
+    let context4 =
+        context3.bind_rule3Distance(&rule3Distance, &program, &mut bindings, &mut out_bindings);
+    let context5 =
+        context4.bind_rule1Scale(&rule1Scale, &program, &mut bindings, &mut out_bindings);
+    let context6 =
+        context5.bind_rule2Scale(&rule2Scale, &program, &mut bindings, &mut out_bindings);
+    let context7 =
+        context6.bind_rule3Scale(&rule3Scale, &program, &mut bindings, &mut out_bindings);
+    let context8 =
+        context7.bind_srcParticlePos(&srcParticlePos, &program, &mut bindings, &mut out_bindings);
+    let context9 =
+        context8.bind_srcParticleVel(&srcParticleVel, &program, &mut bindings, &mut out_bindings);
+
+    // The positions and velocities written by one step are read by the next without leaving the gpu
+    let mut simulation = context9.runable(|| {
+        PingPong::new(
             &program,
-            &mut bindings,
-            &mut out_bindings,
-            &srcParticleVel,
-            "srcParticleVel".to_string(),
-        );
-
-        {
-            ready_to_run(BIND_CONTEXT_9);
-            let result = run(&program, &mut bindings, out_bindings);
-            let dstParticlePos = read_fvec3(&program, &result, "dstParticlePos").await;
-            let dstParticleVel = read_fvec3(&program, &result, "dstParticleVel").await;
-            println!("Current values");
-            println!("{:?}", dstParticlePos);
-            println!("{:?}", dstParticleVel);
-            let mut dstParticlePos1: [f32; 3] = Default::default();
-            let mut dstParticlePos2: [f32; 3] = Default::default();
-            let mut dstParticleVel1: [f32; 3] = Default::default();
-            let mut dstParticleVel2: [f32; 3] = Default::default();
-
-            dstParticlePos1.copy_from_slice(&dstParticlePos[0..3]);
-            dstParticlePos2.copy_from_slice(&dstParticlePos[3..6]);
-            dstParticleVel1.copy_from_slice(&dstParticleVel[0..3]);
-            dstParticleVel2.copy_from_slice(&dstParticleVel[3..6]);
-            srcParticlePos = vec![dstParticlePos1, dstParticlePos2];
-            srcParticleVel = vec![dstParticleVel1, dstParticleVel2];
-        }
+            bindings,
+            out_bindings,
+            &[
+                ("srcParticlePos", "dstParticlePos"),
+                ("srcParticleVel", "dstParticleVel"),
+            ],
+        )
+    });
+
+    for _ in 0..10 {
+        simulation.run_steps(100);
+        let result = simulation.results();
+        println!("Current values");
+        println!("{:?}", read_fvec3(&result, "dstParticlePos").await);
+        println!("{:?}", read_fvec3(&result, "dstParticleVel").await);
     }
 }
 
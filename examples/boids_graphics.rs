@@ -23,9 +23,9 @@ pub use pipeline::wgpu_compute_header::{
     compile, read_fvec3, run, ComputeBindings, ComputeProgram, ComputeShader, OutComputeBindings,
 };
 pub use pipeline::wgpu_graphics_header::{
-    compile_buffer, default_bind_group, graphics_compile, graphics_pipe, graphics_run,
-    setup_render_pass, valid_fragment_shader, valid_vertex_shader, GraphicsBindings,
-    GraphicsProgram, GraphicsShader, OutGraphicsBindings,
+    compile_buffer, graphics_compile, graphics_pipe, graphics_run, setup_render_pass,
+    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsProgram, GraphicsShader,
+    OutGraphicsBindings,
 };
 
 pub use static_assertions::const_assert;
@@ -116,8 +116,8 @@ fn execute_gpu(event_loop: EventLoop<()>, window: Window) {
     };
 
     const VERTEX: (GraphicsShader, [&str; 32], [&str; 32]) = graphics_shader! {
-        [[loop in] vec3] dstParticlePos;
-        [[loop in] vec3] dstParticleVel;
+        [[instance in] vec3] dstParticlePos;
+        [[instance in] vec3] dstParticleVel;
         [[vertex in] vec3] trianglePos;
         [[out] vec4] gl_Position;
 
@@ -155,7 +155,13 @@ fn execute_gpu(event_loop: EventLoop<()>, window: Window) {
     static_assertions::const_assert!(valid_vertex_shader(&V));
     static_assertions::const_assert!(valid_fragment_shader(&F));
     let (graphics_program, template_graphics_bindings, template_graphics_out_bindings) =
-        futures::executor::block_on(graphics_compile(&mut compile_buffer, &window, &V, &F));
+        futures::executor::block_on(graphics_compile(
+            &mut compile_buffer,
+            &window,
+            &V,
+            &F,
+            false,
+        ));
 
     // For drawing to window
     let sc_desc = wgpu::SwapChainDescriptor {
@@ -208,7 +214,6 @@ fn execute_gpu(event_loop: EventLoop<()>, window: Window) {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         let mut rpass = setup_render_pass(&graphics_program, &mut init_encoder, &frame);
-        let mut bind_group = default_bind_group(&graphics_program);
 
         let mut bindings: ComputeBindings = template_bindings.clone();
         let mut out_bindings: OutComputeBindings = template_out_bindings.clone();
@@ -309,9 +314,9 @@ fn execute_gpu(event_loop: EventLoop<()>, window: Window) {
         let result = run(program, &mut bindings, out_bindings);
 
         // Need to finagle async too allow reading here
-        let dstParticlePos = read_fvec3(program, &result, "dstParticlePos").await;
-        let dstParticleVel = read_fvec3(program, &result, "dstParticleVel").await;
-        let trianglePos = read_fvec3(program, &result, "trianglePos").await;
+        let dstParticlePos = read_fvec3(&result, "dstParticlePos").await;
+        let dstParticleVel = read_fvec3(&result, "dstParticleVel").await;
+        let trianglePos = read_fvec3(&result, "trianglePos").await;
         println!("Current values");
         println!("{:?}", dstParticlePos);
         println!("{:?}", dstParticleVel);
@@ -319,7 +324,6 @@ fn execute_gpu(event_loop: EventLoop<()>, window: Window) {
         graphics_pipe(
             &graphics_program,
             rpass,
-            &mut bind_group,
             graphics_bindings,
             &mut graphics_out_bindings,
             result,
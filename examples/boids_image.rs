@@ -23,8 +23,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     let size = window.inner_size();
 
     const VERTEXT: (GraphicsShader, [&str; 32], [&str; 32]) = graphics_shader! {
-        [[loop in] vec3] dstParticlePos;
-        [[loop in] vec3] dstParticleVel;
+        [[instance in] vec3] dstParticlePos;
+        [[instance in] vec3] dstParticleVel;
         [[vertex in] vec3] trianglePos;
         [[out] vec4] gl_Position;
 
@@ -58,7 +58,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     static_assertions::const_assert!(valid_vertex_shader(&S_v));
     static_assertions::const_assert!(valid_fragment_shader(&S_f));
     let (program, mut template_bindings, mut template_out_bindings) =
-        wgpu_graphics_header::graphics_compile(&mut compile_buffer, &window, &S_v, &S_f).await;
+        wgpu_graphics_header::graphics_compile(&mut compile_buffer, &window, &S_v, &S_f, false)
+            .await;
 
     let mut srcParticlePos = vec![vec![0.5, 0.0, 0.0], vec![0.3, 0.2, 0.0]];
     let mut srcParticleVel = vec![vec![0.01, -0.02, 0.0], vec![-0.05, -0.03, 0.0]];
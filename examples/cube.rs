@@ -11,9 +11,8 @@ pub use static_assertions::const_assert;
 
 pub use pipeline::wgpu_graphics_header;
 pub use pipeline::wgpu_graphics_header::{
-    compile_buffer, default_bind_group, generate_swap_chain, setup_render_pass,
-    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
-    OutGraphicsBindings,
+    compile_buffer, generate_swap_chain, setup_render_pass, valid_fragment_shader,
+    valid_vertex_shader, GraphicsBindings, GraphicsShader, OutGraphicsBindings,
 };
 
 pub use pipeline::shared;
@@ -145,7 +144,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .expect("Timeout when acquiring next swap chain texture");
 
                 let mut rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-                let mut bind_group = default_bind_group(&program);
 
                 let mut bindings: GraphicsBindings = template_bindings.clone();
                 let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
@@ -216,7 +214,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                         wgpu_graphics_header::graphics_run_indicies(
                                             &program,
                                             rpass,
-                                            &mut bind_group,
                                             &mut bindings,
                                             &mut out_bindings,
                                             &index_data,
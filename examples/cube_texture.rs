@@ -11,9 +11,9 @@ pub use static_assertions::const_assert;
 
 pub use pipeline::wgpu_graphics_header;
 pub use pipeline::wgpu_graphics_header::{
-    bind_sampler, bind_texture, compile_buffer, default_bind_group, graphics_starting_context,
-    setup_render_pass, valid_fragment_shader, valid_vertex_shader, GraphicsBindings,
-    GraphicsShader, OutGraphicsBindings,
+    bind_sampler, bind_texture, compile_buffer, graphics_starting_context, setup_render_pass,
+    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
+    OutGraphicsBindings,
 };
 
 pub use pipeline::shared;
@@ -211,7 +211,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 );
 
                 let mut rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-                let mut bind_group = default_bind_group(&program);
 
                 let mut bindings: GraphicsBindings = template_bindings.clone();
                 let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
@@ -270,7 +269,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                     wgpu_graphics_header::graphics_run_indicies(
                                         &program,
                                         rpass,
-                                        &mut bind_group,
                                         &mut bindings,
                                         &out_bindings,
                                         &index_data,
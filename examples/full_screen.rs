@@ -0,0 +1,113 @@
+#![recursion_limit = "512"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+pub use pipeline::wgpu_graphics_header::{
+    compile_buffer, generate_swap_chain, graphics_run_draws, setup_render_pass,
+    valid_fragment_shader, valid_vertex_shader, Draw, GraphicsBindings, GraphicsShader,
+    OutGraphicsBindings,
+};
+
+pub use pipeline::bind::Bindings;
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn run(event_loop: EventLoop<()>, window: Window) {
+    init!();
+
+    // Nothing is bound to the vertices, their positions come from gl_VertexID
+    my_shader! {vertex = {
+        [[] int] gl_VertexID;
+        [[out] vec2] uv;
+        [[out] vec4] gl_Position;
+        {{
+            void main() {
+                uv = vec2(gl_VertexID == 1 ? 2.0 : 0.0, gl_VertexID == 2 ? 2.0 : 0.0);
+                gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+            }
+        }}
+    }}
+
+    my_shader! {fragment = {
+        [[in] vec2] uv;
+        [[out] vec4] color;
+        {{
+            void main() {
+                color = vec4(uv, 0.5, 1.0);
+            }
+        }}
+    }}
+
+    const VERTEXT: GraphicsShader = eager_graphics_shader! {vertex!()};
+
+    const FRAGMENT: GraphicsShader = eager_graphics_shader! {fragment!()};
+
+    eager_binding! {context = vertex!(), fragment!()};
+
+    const S_V: GraphicsShader = VERTEXT;
+    const S_F: GraphicsShader = FRAGMENT;
+
+    let (program, template_bindings, template_out_bindings, _) =
+        compile_valid_graphics_program!(window, S_V, S_F);
+
+    // A "chain" of buffers that we render on to the display
+    let mut swap_chain = generate_swap_chain(&program, &window);
+
+    let mut bindings: GraphicsBindings = template_bindings.clone();
+    let out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
+    event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            // Everything that can be processed has been so we can now redraw the image on our window
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let mut init_encoder = program
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                let frame = swap_chain
+                    .get_next_texture()
+                    .expect("Timeout when acquiring next swap chain texture");
+                let rpass = setup_render_pass(&program, &mut init_encoder, &frame);
+
+                context.runable(|| {
+                    graphics_run_draws(
+                        &program,
+                        rpass,
+                        &mut bindings,
+                        &out_bindings,
+                        &[Draw::full_screen_triangle()],
+                    )
+                });
+                program.queue.submit(&[init_encoder.finish()]);
+            }
+            // When the window closes we are done. Change the status
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            // Ignore any other types of events
+            _ => {}
+        }
+    });
+}
+
+fn main() {
+    // From examples of wgpu-rs, set up a window we can use to view our stuff
+    let event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .build(&event_loop)
+        .unwrap();
+
+    // Why do we need to be async? Because of event_loop?
+    futures::executor::block_on(run(event_loop, window));
+}
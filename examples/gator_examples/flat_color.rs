@@ -1,86 +1,96 @@
+#![recursion_limit = "512"]
 #[macro_use]
 extern crate pipeline;
 
+#[macro_use]
+extern crate eager;
+
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
 
-pub use static_assertions::const_assert;
-
-pub use pipeline::wgpu_graphics_header;
 pub use pipeline::wgpu_graphics_header::{
-    compile_buffer, default_bind_group, generate_swap_chain, graphics_run_indicies,
-    setup_render_pass, valid_fragment_shader, valid_vertex_shader, GraphicsBindings,
-    GraphicsShader, OutGraphicsBindings,
+    bind_mesh_indicies, compile_buffer, generate_swap_chain, graphics_run, setup_render_pass,
+    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
+    OutGraphicsBindings,
 };
 
-pub use pipeline::shared;
-pub use pipeline::shared::{
-    bind_fvec, bind_mat4, bind_vec3, is_gl_builtin, ready_to_run, update_bind_context, Bindings,
-};
+pub use pipeline::bind::Bindings;
+
+pub use pipeline::mesh::Mesh;
 
 pub use pipeline::helper::{
-    generate_identity_matrix, generate_projection_matrix, generate_view_matrix, load_model,
-    rotation_x, rotation_y, rotation_z, scale, translate,
+    generate_identity_matrix, generate_projection_transform, generate_view_transform, load_model,
+    model_to_world, rotation_y, Clip, Model, Transform, View, World,
 };
 
+pub use wgpu_macros::{generic_bindings, init};
+
 async fn run(event_loop: EventLoop<()>, window: Window) {
     let size = window.inner_size();
 
-    const VERTEXT: (GraphicsShader, [&str; 32], [&str; 32]) = graphics_shader! {
+    init!();
+
+    // The frames of the matrices and the varying are checked against what is bound and what the fragment shader reads
+    my_shader! {vertex = {
         [[vertex in] vec3] a_position;
-        [[uniform in] mat4] u_view;
-        [[uniform in] mat4] u_proj;
-        [[uniform in] mat4] u_model;
+        [[uniform in] mat4<world, view>] u_view;
+        [[uniform in] mat4<view, clip>] u_proj;
+        [[uniform in] mat4<model, world>] u_model;
 
+        [[out] vec3<world>] v_position;
         [[out] vec4] gl_Position;
         {{
             void main() {
-                gl_Position = u_proj * u_view * u_model * vec4( a_position, 1.0);
+                vec4 world_position = u_model * vec4(a_position, 1.0);
+                v_position = world_position.xyz;
+                gl_Position = u_proj * u_view * world_position;
             }
         }}
-    };
+    }}
 
-    const FRAGMENT: (GraphicsShader, [&str; 32], [&str; 32]) = graphics_shader! {
+    my_shader! {fragment = {
+        [[in] vec3<world>] v_position;
         [[out] vec4] color;
         {{
             void main() {
-                color = vec4(1.0, 0.4, 0.25, 1.0);
+                // Lighter towards the top of the teapot
+                color = vec4(1.0, 0.4, 0.25, 1.0) * (0.75 + 0.25 * clamp(v_position.z, -1.0, 1.0));
             }
         }}
-    };
-
-    const S_V: GraphicsShader = VERTEXT.0;
-    const STARTING_BIND_CONTEXT: [&str; 32] = VERTEXT.1;
-    const S_F: GraphicsShader = FRAGMENT.0;
+    }}
 
-    let (program, template_bindings, template_out_bindings, _) =
-        compile_valid_graphics_program!(window, S_V, S_F);
+    const VERTEXT: GraphicsShader = eager_graphics_shader! {vertex!()};
 
-    let (positions, normals, indices) = load_model("src/models/teapot.obj");
+    const FRAGMENT: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    /*     println!("{:?}", positions); */
+    eager_binding! {context = vertex!(), fragment!()};
 
-    let mut view_mat = generate_view_matrix();
-    /* view_mat = translate(view_mat, 0.0, -1.0, 0.1); */
+    const S_V: GraphicsShader = VERTEXT;
+    const S_F: GraphicsShader = FRAGMENT;
 
-    /*     println!("{:?}", generate_view_matrix()); */
-
-    println!("View mat: {:?}", view_mat);
-
-    let mut proj_mat = generate_projection_matrix(size.width as f32 / size.height as f32);
+    let (program, template_bindings, template_out_bindings, _) =
+        compile_valid_graphics_program!(window, S_V, S_F);
 
-    /*     proj_mat = rotation_x(proj_mat, 34.0);
-    proj_mat = rotation_y(proj_mat, 3.0); */
+    let teapot = Mesh::from_model(&program, load_model("src/models/teapot.obj"));
 
-    let mut model_mat = generate_identity_matrix();
-    /*     model_mat = scale(model_mat, 0.5); */
+    // Each matrix is tagged with the frames it goes between so multiplying them in the wrong order
+    // or binding one to a parameter declared with other frames doesn't compile
+    let view: Transform<World, View> = generate_view_transform();
+    let proj: Transform<View, Clip> =
+        generate_projection_transform(size.width as f32 / size.height as f32);
+    let mut model: Transform<Model, World> = model_to_world(generate_identity_matrix());
 
     // A "chain" of buffers that we render on to the display
     let mut swap_chain = generate_swap_chain(&program, &window);
 
+    let mut bindings: GraphicsBindings = template_bindings.clone();
+    let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
+    bind_mesh_indicies(&mut bindings, &teapot);
+
     event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
         *control_flow = ControlFlow::Poll;
         match event {
@@ -90,73 +100,29 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 let mut init_encoder = program
                     .device
                     .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-                let mut frame = swap_chain
+                let frame = swap_chain
                     .get_next_texture()
                     .expect("Timeout when acquiring next swap chain texture");
+                let rpass = setup_render_pass(&program, &mut init_encoder, &frame);
 
-                let mut rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-                let mut bind_group = default_bind_group(&program);
-
-                let mut bindings: GraphicsBindings = template_bindings.clone();
-                let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
-
-                model_mat = rotation_y(model_mat, 0.05);
+                model = model_to_world(rotation_y(model.matrix, 0.05));
 
-                const BIND_CONTEXT_1: [&str; 32] =
-                    update_bind_context(&STARTING_BIND_CONTEXT, "a_position");
-                bind_vec3(
-                    &program,
-                    &mut bindings,
-                    &mut out_bindings,
-                    &positions,
-                    "a_position".to_string(),
-                );
                 {
-                    const BIND_CONTEXT_2: [&str; 32] =
-                        update_bind_context(&BIND_CONTEXT_1, "u_view");
-                    bind_mat4(
+                    let context1 = (&context).bind_a_position(
+                        &teapot,
                         &program,
                         &mut bindings,
                         &mut out_bindings,
-                        view_mat,
-                        "u_view".to_string(),
                     );
-                    {
-                        {
-                            const BIND_CONTEXT_3: [&str; 32] =
-                                update_bind_context(&BIND_CONTEXT_2, "u_proj");
-                            bind_mat4(
-                                &program,
-                                &mut bindings,
-                                &mut out_bindings,
-                                proj_mat,
-                                "u_proj".to_string(),
-                            );
-                            {
-                                const BIND_CONTEXT_4: [&str; 32] =
-                                    update_bind_context(&BIND_CONTEXT_3, "u_model");
-                                bind_mat4(
-                                    &program,
-                                    &mut bindings,
-                                    &mut out_bindings,
-                                    model_mat,
-                                    "u_model".to_string(),
-                                );
-                                {
-                                    ready_to_run(BIND_CONTEXT_4);
-                                    graphics_run_indicies(
-                                        &program,
-                                        rpass,
-                                        &mut bind_group,
-                                        &mut bindings,
-                                        &out_bindings,
-                                        &indices,
-                                    );
-                                }
-                            }
-                        }
-                    }
+                    let context2 =
+                        context1.bind_u_view(&view, &program, &mut bindings, &mut out_bindings);
+                    let context3 =
+                        context2.bind_u_proj(&proj, &program, &mut bindings, &mut out_bindings);
+                    let context4 =
+                        context3.bind_u_model(&model, &program, &mut bindings, &mut out_bindings);
+                    context4.runable(|| {
+                        graphics_run(&program, rpass, &mut bindings, &mut out_bindings)
+                    });
                 }
                 program.queue.submit(&[init_encoder.finish()]);
             }
@@ -177,7 +143,6 @@ fn main() {
     let window = winit::window::WindowBuilder::new()
         .build(&event_loop)
         .unwrap();
-    /* let window = winit::window::Window::new(&event_loop).unwrap(); */
 
     // Why do we need to be async? Because of event_loop?
     futures::executor::block_on(run(event_loop, window));
@@ -12,9 +12,8 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    compile_buffer, default_bind_group, generate_swap_chain, graphics_run, setup_render_pass,
-    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
-    OutGraphicsBindings,
+    compile_buffer, generate_swap_chain, graphics_run, setup_render_pass, valid_fragment_shader,
+    valid_vertex_shader, GraphicsBindings, GraphicsShader, OutGraphicsBindings,
 };
 
 pub use pipeline::bind::Bindings;
@@ -72,6 +71,10 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     // A "chain" of buffers that we render on to the display
     let mut swap_chain = generate_swap_chain(&program, &window);
 
+    // The bindings are kept between frames so binding again writes into the same buffers
+    let mut bindings: GraphicsBindings = template_bindings.clone();
+    let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
     event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
         *control_flow = ControlFlow::Poll;
         match event {
@@ -85,10 +88,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .get_next_texture()
                     .expect("Timeout when acquiring next swap chain texture");
                 let mut rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-                let mut bind_group = default_bind_group(&program);
-
-                let mut bindings: GraphicsBindings = template_bindings.clone();
-                let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
 
                 {
                     let context1 = (&context).bind_in_brightness(
@@ -107,13 +106,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                         );
                         {
                             context2.runable(|| {
-                                graphics_run(
-                                    &program,
-                                    rpass,
-                                    &mut bind_group,
-                                    &bindings,
-                                    &out_bindings,
-                                )
+                                graphics_run(&program, rpass, &mut bindings, &out_bindings)
                             });
                         }
                     }
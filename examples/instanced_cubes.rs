@@ -0,0 +1,159 @@
+#![recursion_limit = "512"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+pub use pipeline::wgpu_graphics_header::{
+    bind_indicies, compile_buffer, generate_swap_chain, graphics_run_instanced, setup_render_pass,
+    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
+    OutGraphicsBindings,
+};
+
+pub use pipeline::bind::Bindings;
+
+pub use pipeline::helper::{
+    generate_identity_matrix, generate_projection_matrix, generate_view_matrix, load_cube,
+    translate,
+};
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn run(event_loop: EventLoop<()>, window: Window) {
+    let size = window.inner_size();
+
+    init!();
+
+    my_shader! {vertex = {
+        [[vertex in] vec3] a_position;
+        [[vertex in] vec3] vertexColor;
+        [[uniform in] mat4] u_view;
+        [[uniform in] mat4] u_proj;
+        [[instance in] mat4] i_model;
+        [[out] vec3] fragmentColor;
+        [[out] vec4] gl_Position;
+        {{
+            void main() {
+                fragmentColor = vertexColor;
+                gl_Position = u_proj * u_view * i_model * vec4(0.5 * a_position, 1.0);
+            }
+        }}
+    }}
+
+    my_shader! {fragment = {
+        [[in] vec3] fragmentColor;
+        [[out] vec4] color;
+        {{
+            void main() {
+                color = vec4(fragmentColor, 1.0);
+            }
+        }}
+    }}
+
+    const VERTEXT: GraphicsShader = eager_graphics_shader! {vertex!()};
+
+    const FRAGMENT: GraphicsShader = eager_graphics_shader! {fragment!()};
+
+    eager_binding! {context = vertex!(), fragment!()};
+
+    const S_V: GraphicsShader = VERTEXT;
+    const S_F: GraphicsShader = FRAGMENT;
+
+    let (program, template_bindings, template_out_bindings, _) =
+        compile_valid_graphics_program!(window, S_V, S_F);
+
+    let (positions, _, index_data) = load_cube();
+
+    // One color for each corner of every face
+    let color_data: Vec<[f32; 3]> = positions
+        .iter()
+        .map(|p| [(p[0] + 1.0) / 2.0, (p[1] + 1.0) / 2.0, (p[2] + 1.0) / 2.0])
+        .collect();
+
+    let view_mat = generate_view_matrix();
+
+    let proj_mat = generate_projection_matrix(size.width as f32 / size.height as f32);
+
+    let model_mat = generate_identity_matrix();
+
+    // Every instance of the cube is drawn with its own model matrix
+    let models: Vec<cgmath::Matrix4<f32>> = (0..5)
+        .map(|i| translate(model_mat, 2.0 * (i as f32 - 2.0), 0.0, 0.0))
+        .collect();
+
+    // A "chain" of buffers that we render on to the display
+    let mut swap_chain = generate_swap_chain(&program, &window);
+
+    let mut bindings: GraphicsBindings = template_bindings.clone();
+    let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
+    bind_indicies(&program, &mut bindings, &index_data);
+
+    event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            // Everything that can be processed has been so we can now redraw the image on our window
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let mut init_encoder = program
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                let frame = swap_chain
+                    .get_next_texture()
+                    .expect("Timeout when acquiring next swap chain texture");
+                let rpass = setup_render_pass(&program, &mut init_encoder, &frame);
+
+                {
+                    let context1 = (&context).bind_a_position(
+                        &positions,
+                        &program,
+                        &mut bindings,
+                        &mut out_bindings,
+                    );
+                    let context2 = context1.bind_vertexColor(
+                        &color_data,
+                        &program,
+                        &mut bindings,
+                        &mut out_bindings,
+                    );
+                    let context3 =
+                        context2.bind_u_view(&view_mat, &program, &mut bindings, &mut out_bindings);
+                    let context4 =
+                        context3.bind_u_proj(&proj_mat, &program, &mut bindings, &mut out_bindings);
+                    let context5 =
+                        context4.bind_i_model(&models, &program, &mut bindings, &mut out_bindings);
+                    // Skip the first and last cube
+                    context5.runable(|| {
+                        graphics_run_instanced(&program, rpass, &mut bindings, &out_bindings, 1..4)
+                    });
+                }
+                program.queue.submit(&[init_encoder.finish()]);
+            }
+            // When the window closes we are done. Change the status
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            // Ignore any other types of events
+            _ => {}
+        }
+    });
+}
+
+fn main() {
+    // From examples of wgpu-rs, set up a window we can use to view our stuff
+    let event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .build(&event_loop)
+        .unwrap();
+
+    // Why do we need to be async? Because of event_loop?
+    futures::executor::block_on(run(event_loop, window));
+}
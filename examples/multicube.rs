@@ -1,42 +1,45 @@
+#![recursion_limit = "512"]
 #[macro_use]
 extern crate pipeline;
 
+#[macro_use]
+extern crate eager;
+
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::Window,
 };
 
-pub use static_assertions::const_assert;
-
-pub use pipeline::wgpu_graphics_header;
 pub use pipeline::wgpu_graphics_header::{
-    compile_buffer, default_bind_group, generate_swap_chain, setup_render_pass,
+    bind_mesh_indicies, compile_buffer, draw_each, generate_swap_chain, setup_render_pass,
     valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
     OutGraphicsBindings,
 };
 
-pub use pipeline::shared;
-pub use pipeline::shared::{bind_fvec, bind_mat4, bind_vec3, is_gl_builtin, Bindings};
+pub use pipeline::bind::Bindings;
 
-pub use pipeline::context::{ready_to_run, update_bind_context};
+pub use pipeline::mesh::Mesh;
 
-pub use pipeline::helper::{
-    generate_identity_matrix, generate_projection_matrix, generate_view_matrix, load_cube,
-    translate,
-};
+pub use pipeline::camera::{Camera, OrbitController};
+
+pub use pipeline::helper::load_cube;
+
+pub use pipeline::scene::{SceneGraph, Trs};
+
+pub use wgpu_macros::{generic_bindings, init};
 
 async fn run(event_loop: EventLoop<()>, window: Window) {
     let size = window.inner_size();
 
-    const VERTEXT: (GraphicsShader, [&str; 32], [&str; 32]) = graphics_shader! {
+    init!();
+
+    my_shader! {vertex = {
         [[vertex in] vec3] a_position;
         [[vertex in] vec3] vertexColor;
         [[uniform in] mat4] u_view;
         [[uniform in] mat4] u_proj;
-        [[uniform in] mat4] u_model;
-
-
+        [[uniform in dynamic] mat4] u_model;
         [[out] vec3] fragmentColor;
         [[out] vec4] gl_Position;
         {{
@@ -45,9 +48,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 gl_Position = u_proj * u_view * u_model * vec4(0.5 * a_position, 1.0);
             }
         }}
-    };
+    }}
 
-    const FRAGMENT: (GraphicsShader, [&str; 32], [&str; 32]) = graphics_shader! {
+    my_shader! {fragment = {
         [[in] vec3] fragmentColor;
         [[out] vec4] color;
         {{
@@ -55,67 +58,61 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 color = vec4(fragmentColor, 1.0);
             }
         }}
-    };
+    }}
+
+    const VERTEXT: GraphicsShader = eager_graphics_shader! {vertex!()};
 
-    const S_V: GraphicsShader = VERTEXT.0;
-    const STARTING_BIND_CONTEXT: [&str; 32] = VERTEXT.1;
-    const S_F: GraphicsShader = FRAGMENT.0;
+    const FRAGMENT: GraphicsShader = eager_graphics_shader! {fragment!()};
+
+    // u_model is dynamic so it is given a value for each cube by draw_each instead of being bound
+    eager_binding! {context = vertex!(), fragment!()};
+
+    const S_V: GraphicsShader = VERTEXT;
+    const S_F: GraphicsShader = FRAGMENT;
 
     let (program, template_bindings, template_out_bindings, _) =
         compile_valid_graphics_program!(window, S_V, S_F);
 
     let (positions, _, index_data) = load_cube();
 
-    let color_data = vec![
-        [0.583, 0.771, 0.014],
-        [0.609, 0.115, 0.436],
-        [0.327, 0.483, 0.844],
-        [0.822, 0.569, 0.201],
-        [0.435, 0.602, 0.223],
-        [0.310, 0.747, 0.185],
-        [0.597, 0.770, 0.761],
-        [0.559, 0.436, 0.730],
-        [0.359, 0.583, 0.152],
-        [0.483, 0.596, 0.789],
-        [0.559, 0.861, 0.639],
-        [0.195, 0.548, 0.859],
-        [0.014, 0.184, 0.576],
-        [0.771, 0.328, 0.970],
-        [0.406, 0.615, 0.116],
-        [0.676, 0.977, 0.133],
-        [0.971, 0.572, 0.833],
-        [0.140, 0.616, 0.489],
-        [0.997, 0.513, 0.064],
-        [0.945, 0.719, 0.592],
-        [0.543, 0.021, 0.978],
-        [0.279, 0.317, 0.505],
-        [0.167, 0.620, 0.077],
-        [0.347, 0.857, 0.137],
-        [0.055, 0.953, 0.042],
-        [0.714, 0.505, 0.345],
-        [0.783, 0.290, 0.734],
-        [0.722, 0.645, 0.174],
-        [0.302, 0.455, 0.848],
-        [0.225, 0.587, 0.040],
-        [0.517, 0.713, 0.338],
-        [0.053, 0.959, 0.120],
-        [0.393, 0.621, 0.362],
-        [0.673, 0.211, 0.457],
-        [0.820, 0.883, 0.371],
-        [0.982, 0.099, 0.879],
-    ];
-
-    let view_mat = generate_view_matrix();
-
-    let proj_mat = generate_projection_matrix(size.width as f32 / size.height as f32);
-
-    let model_mat = generate_identity_matrix();
-
-    let model_mat2 = translate(model_mat, 2.0, 0.0, 0.0);
+    // One color for each corner of every face
+    let color_data: Vec<[f32; 3]> = positions
+        .iter()
+        .map(|p| [(p[0] + 1.0) / 2.0, (p[1] + 1.0) / 2.0, (p[2] + 1.0) / 2.0])
+        .collect();
+
+    // The cube is uploaded once and every frame binds the same buffers
+    let mut cube = Mesh::new();
+    cube.add_vec3(&program, "a_position", &positions);
+    cube.add_vec3(&program, "vertexColor", &color_data);
+    cube.set_indicies(&program, &index_data);
+
+    // Drag with the left mouse button to look around the cubes and scroll to zoom
+    let mut camera = Camera::default();
+    camera.resize(size.width, size.height);
+    let mut controller = OrbitController::new(&camera);
+
+    // Every node draws the same cube so there is nothing to store for its mesh
+    // The other cubes are children of the middle one so they turn around it as it spins
+    let mut scene: SceneGraph<()> = SceneGraph::new();
+    let middle = scene.add(None, Trs::default(), Some(()));
+    for i in &[-2.0, -1.0, 1.0, 2.0] {
+        scene.add(
+            Some(middle),
+            Trs::from_translation(2.0 * i, 0.0, 0.0),
+            Some(()),
+        );
+    }
+    let mut angle = 0.0;
 
     // A "chain" of buffers that we render on to the display
     let mut swap_chain = generate_swap_chain(&program, &window);
 
+    let mut bindings: GraphicsBindings = template_bindings.clone();
+    let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
+    bind_mesh_indicies(&mut bindings, &cube);
+
     event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
         *control_flow = ControlFlow::Poll;
         match event {
@@ -125,152 +122,49 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 let mut init_encoder = program
                     .device
                     .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-                let mut frame = swap_chain
+                let frame = swap_chain
                     .get_next_texture()
                     .expect("Timeout when acquiring next swap chain texture");
+                let rpass = setup_render_pass(&program, &mut init_encoder, &frame);
+
+                controller.update_camera(&mut camera);
+                let view_mat = camera.view_matrix();
+                let proj_mat = camera.projection_matrix();
+
+                angle += 0.01;
+                scene.transform_mut(middle).rotation = cgmath::Quaternion::from(
+                    cgmath::Euler::new(cgmath::Rad(0.0), cgmath::Rad(0.0), cgmath::Rad(angle)),
+                );
+                let draws = scene.draws();
+
                 {
-                    let mut bindings: GraphicsBindings = template_bindings.clone();
-                    let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
-                    let mut bind_group = default_bind_group(&program);
-                    let mut bindings2: GraphicsBindings = template_bindings.clone();
-                    let mut out_bindings2: OutGraphicsBindings = template_out_bindings.clone();
-                    let mut bind_group2 = default_bind_group(&program);
-
-                    let mut rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-
-                    const BIND_CONTEXT_1: [&str; 32] =
-                        update_bind_context(&STARTING_BIND_CONTEXT, "a_position");
-                    bind_vec3(
+                    let context1 = (&context).bind_a_position(
+                        &cube,
                         &program,
                         &mut bindings,
                         &mut out_bindings,
-                        &positions,
-                        "a_position".to_string(),
                     );
-                    {
-                        const BIND_CONTEXT_2: [&str; 32] =
-                            update_bind_context(&BIND_CONTEXT_1, "u_view");
-                        bind_mat4(
-                            &program,
-                            &mut bindings,
-                            &mut out_bindings,
-                            view_mat,
-                            "u_view".to_string(),
-                        );
-                        {
-                            const BIND_CONTEXT_3: [&str; 32] =
-                                update_bind_context(&BIND_CONTEXT_2, "vertexColor");
-                            bind_vec3(
-                                &program,
-                                &mut bindings,
-                                &mut out_bindings,
-                                &color_data,
-                                "vertexColor".to_string(),
-                            );
-
-                            {
-                                const BIND_CONTEXT_4: [&str; 32] =
-                                    update_bind_context(&BIND_CONTEXT_3, "u_proj");
-                                bind_mat4(
-                                    &program,
-                                    &mut bindings,
-                                    &mut out_bindings,
-                                    proj_mat,
-                                    "u_proj".to_string(),
-                                );
-                                {
-                                    const BIND_CONTEXT_5: [&str; 32] =
-                                        update_bind_context(&BIND_CONTEXT_4, "u_model");
-                                    bind_mat4(
-                                        &program,
-                                        &mut bindings,
-                                        &mut out_bindings,
-                                        model_mat,
-                                        "u_model".to_string(),
-                                    );
-
-                                    {
-                                        ready_to_run(BIND_CONTEXT_5);
-                                        rpass = wgpu_graphics_header::graphics_run_indicies(
-                                            &program,
-                                            rpass,
-                                            &mut bind_group,
-                                            &mut bindings,
-                                            &out_bindings,
-                                            &index_data,
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    const BIND_CONTEXT_1_1: [&str; 32] =
-                        update_bind_context(&STARTING_BIND_CONTEXT, "a_position");
-                    bind_vec3(
+                    let context2 = context1.bind_vertexColor(
+                        &cube,
                         &program,
-                        &mut bindings2,
-                        &mut out_bindings2,
-                        &positions,
-                        "a_position".to_string(),
+                        &mut bindings,
+                        &mut out_bindings,
                     );
-                    {
-                        const BIND_CONTEXT_2_1: [&str; 32] =
-                            update_bind_context(&BIND_CONTEXT_1_1, "u_view");
-                        bind_mat4(
+                    let context3 =
+                        context2.bind_u_view(&view_mat, &program, &mut bindings, &mut out_bindings);
+                    let context4 =
+                        context3.bind_u_proj(&proj_mat, &program, &mut bindings, &mut out_bindings);
+                    context4.runable(|| {
+                        draw_each(
                             &program,
-                            &mut bindings2,
-                            &mut out_bindings2,
-                            view_mat,
-                            "u_view".to_string(),
-                        );
-                        {
-                            const BIND_CONTEXT_3_1: [&str; 32] =
-                                update_bind_context(&BIND_CONTEXT_2_1, "vertexColor");
-                            bind_vec3(
-                                &program,
-                                &mut bindings2,
-                                &mut out_bindings2,
-                                &color_data,
-                                "vertexColor".to_string(),
-                            );
-
-                            {
-                                const BIND_CONTEXT_4_1: [&str; 32] =
-                                    update_bind_context(&BIND_CONTEXT_3_1, "u_proj");
-                                bind_mat4(
-                                    &program,
-                                    &mut bindings2,
-                                    &mut out_bindings2,
-                                    proj_mat,
-                                    "u_proj".to_string(),
-                                );
-                                {
-                                    const BIND_CONTEXT_5_1: [&str; 32] =
-                                        update_bind_context(&BIND_CONTEXT_4_1, "u_model");
-                                    bind_mat4(
-                                        &program,
-                                        &mut bindings2,
-                                        &mut out_bindings2,
-                                        model_mat2,
-                                        "u_model".to_string(),
-                                    );
-
-                                    {
-                                        ready_to_run(BIND_CONTEXT_5_1);
-                                        wgpu_graphics_header::graphics_run_indicies(
-                                            &program,
-                                            rpass,
-                                            &mut bind_group2,
-                                            &mut bindings2,
-                                            &out_bindings2,
-                                            &index_data,
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
+                            rpass,
+                            &mut bindings,
+                            &mut out_bindings,
+                            "u_model",
+                            &draws,
+                            |draw| draw.world,
+                        )
+                    });
                 }
                 program.queue.submit(&[init_encoder.finish()]);
             }
@@ -279,6 +173,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *control_flow = ControlFlow::Exit,
+            Event::WindowEvent { event, .. } => {
+                controller.process_event(&event);
+            }
             // Ignore any other types of events
             _ => {}
         }
@@ -285,8 +285,8 @@ async fn execute_gpu() {
         {
             ready_to_run(BIND_CONTEXT_9);
             let result = run(&program, &mut bindings, out_bindings);
-            let dstParticlePos = read_fvec(&program, &result, "dstParticlePos").await;
-            let dstParticleVel = read_fvec(&program, &result, "dstParticleVel").await;
+            let dstParticlePos = read_fvec(&result, "dstParticlePos").await;
+            let dstParticleVel = read_fvec(&result, "dstParticleVel").await;
             println!("Current values");
             println!("{:?}", dstParticlePos);
             println!("{:?}", dstParticleVel);
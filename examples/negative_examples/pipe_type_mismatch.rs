@@ -0,0 +1,60 @@
+#![recursion_limit = "256"]
+
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{compile, pipe, read_uvec, run, ComputeShader};
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn execute_gpu() {
+    init!();
+
+    my_shader! {One = {
+        [[buffer loop in] uint[]] add_one_in;
+        [[buffer out] float[]] add_two_in;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                add_two_in[index] = float(add_one_in[index]+1);
+            }
+        }}
+    }}
+
+    const ADD_ONE: ComputeShader = eager! { lazy! {compute_shader! { eager!{One!()}}}};
+
+    eager! { lazy! { generic_bindings! { context = eager!{ One!()}}}};
+
+    // add_two_in is a float[] coming out of the first program but a uint[] going into the second
+    my_shader! {Two = {
+        [[buffer loop in] uint[]] add_two_in;
+        [[buffer out] uint[]] add_two_result;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                add_two_result[index] = add_two_in[index]+2;
+            }
+        }}
+    }}
+    const ADD_TWO: ComputeShader = eager! { lazy! {compute_shader! { eager!{Two!()}}}};
+
+    let (program1, mut bindings1, mut out_bindings1) = compile(&ADD_ONE).await;
+
+    let (program2, bindings2, out_bindings2) = compile(&ADD_TWO).await;
+
+    let indices: Vec<u32> = vec![1, 2, 3, 4];
+
+    let context1 = context.bind_add_one_in(&indices, &program1, &mut bindings1, &mut out_bindings1);
+    let result = context1.runable(|| run(&program1, &mut bindings1, out_bindings1));
+
+    // Panics with every mismatch between result and the parameters of ADD_TWO
+    let pipe_result = pipe(&program2, bindings2, out_bindings2, result, &[]);
+    println!("{:?}", read_uvec(&pipe_result, "add_two_result").await);
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
@@ -0,0 +1,181 @@
+#![recursion_limit = "512"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+pub use pipeline::wgpu_graphics_header::{
+    begin_pass, compile_buffer, generate_swap_chain, graphics_run_draws, graphics_run_with,
+    valid_fragment_shader, valid_vertex_shader, Draw, DrawOptions, GraphicsBindings,
+    GraphicsShader, Load, OutGraphicsBindings, PassDescriptor, Rect,
+};
+
+pub use pipeline::bind::Bindings;
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn run(event_loop: EventLoop<()>, window: Window) {
+    let size = window.inner_size();
+
+    init!();
+
+    my_shader! {background_vertex = {
+        [[] int] gl_VertexID;
+        [[out] vec2] uv;
+        [[out] vec4] gl_Position;
+        {{
+            void main() {
+                uv = vec2(gl_VertexID == 1 ? 2.0 : 0.0, gl_VertexID == 2 ? 2.0 : 0.0);
+                gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+            }
+        }}
+    }}
+
+    my_shader! {background_fragment = {
+        [[in] vec2] uv;
+        [[out] vec4] color;
+        {{
+            void main() {
+                color = vec4(0.1, 0.1, uv.y * 0.5, 1.0);
+            }
+        }}
+    }}
+
+    my_shader! {vertex = {
+        [[vertex in] vec3] a_position;
+        [[out] vec3] posColor;
+        [[out] vec4] gl_Position;
+        {{
+            void main() {
+                posColor = a_position;
+                gl_Position = vec4(a_position, 1.0);
+            }
+        }}
+    }}
+
+    my_shader! {fragment = {
+        [[in] vec3] posColor;
+        [[out] vec4] color;
+        {{
+            void main() {
+                color = vec4(posColor, 1.0);
+            }
+        }}
+    }}
+
+    const BACKGROUND_VERTEX: GraphicsShader = eager_graphics_shader! {background_vertex!()};
+    const BACKGROUND_FRAGMENT: GraphicsShader = eager_graphics_shader! {background_fragment!()};
+    const VERTEXT: GraphicsShader = eager_graphics_shader! {vertex!()};
+    const FRAGMENT: GraphicsShader = eager_graphics_shader! {fragment!()};
+
+    eager_binding! {background_context = background_vertex!(), background_fragment!()};
+    eager_binding! {context = vertex!(), fragment!()};
+
+    let (background_program, template_background_bindings, template_background_out_bindings, _) =
+        compile_valid_graphics_program!(window, BACKGROUND_VERTEX, BACKGROUND_FRAGMENT);
+
+    // The triangle is drawn on the device of the background so both programs can share a pass
+    let (program, template_bindings, template_out_bindings, _) =
+        compile_valid_graphics_program_shared!(background_program, VERTEXT, FRAGMENT);
+
+    let positions = vec![[0.0, 0.7, 0.0], [-0.5, 0.5, 0.0], [0.5, -0.5, 0.0]];
+
+    // The triangle only covers the right half of the window
+    let right_half = DrawOptions {
+        viewport: Some(Rect {
+            x: size.width / 2,
+            y: 0,
+            width: size.width - size.width / 2,
+            height: size.height,
+        }),
+        ..DrawOptions::new(size.width, size.height)
+    };
+
+    // A "chain" of buffers that we render on to the display
+    let mut swap_chain = generate_swap_chain(&background_program, &window);
+
+    let mut background_bindings: GraphicsBindings = template_background_bindings.clone();
+    let background_out_bindings: OutGraphicsBindings = template_background_out_bindings.clone();
+    let mut bindings: GraphicsBindings = template_bindings.clone();
+    let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
+    event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            // Everything that can be processed has been so we can now redraw the image on our window
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let mut init_encoder = program
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                let frame = swap_chain
+                    .get_next_texture()
+                    .expect("Timeout when acquiring next swap chain texture");
+
+                let mut rpass = begin_pass(
+                    &mut init_encoder,
+                    PassDescriptor {
+                        color_load: Load::Clear(wgpu::Color::BLACK),
+                        ..PassDescriptor::new(&frame.view)
+                    },
+                );
+
+                // Each program switches to its own pipeline before drawing
+                rpass = background_context.runable(|| {
+                    graphics_run_draws(
+                        &background_program,
+                        rpass,
+                        &mut background_bindings,
+                        &background_out_bindings,
+                        &[Draw::full_screen_triangle()],
+                    )
+                });
+
+                {
+                    let context1 = (&context).bind_a_position(
+                        &positions,
+                        &program,
+                        &mut bindings,
+                        &mut out_bindings,
+                    );
+                    context1.runable(|| {
+                        graphics_run_with(
+                            &program,
+                            rpass,
+                            &mut bindings,
+                            &out_bindings,
+                            &[Draw::all()],
+                            &right_half,
+                        )
+                    });
+                }
+                program.queue.submit(&[init_encoder.finish()]);
+            }
+            // When the window closes we are done. Change the status
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            // Ignore any other types of events
+            _ => {}
+        }
+    });
+}
+
+fn main() {
+    // From examples of wgpu-rs, set up a window we can use to view our stuff
+    let event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .build(&event_loop)
+        .unwrap();
+
+    // Why do we need to be async? Because of event_loop?
+    futures::executor::block_on(run(event_loop, window));
+}
@@ -0,0 +1,77 @@
+#![recursion_limit = "256"]
+
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{compile, pipe, read_uvec, run, ComputeShader};
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn execute_gpu() {
+    init!();
+
+    my_shader! {Square = {
+        [[buffer loop in] uint[]] values;
+        [[buffer out] uint[]] result;
+        [[buffer out] uint[]] doubled;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                result[index] = values[index]*values[index];
+                doubled[index] = values[index]*2;
+            }
+        }}
+    }}
+
+    const SQUARE: ComputeShader = eager! { lazy! {compute_shader! { eager!{Square!()}}}};
+
+    eager! { lazy! { generic_bindings! { context = eager!{ Square!()}}}};
+
+    my_shader! {Offset = {
+        [[buffer loop in] uint[]] indices;
+        [[buffer in] uint[]] offsets;
+        [[buffer out] uint[]] offset_result;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                offset_result[index] = indices[index]+offsets[index];
+            }
+        }}
+    }}
+
+    const OFFSET: ComputeShader = eager! { lazy! {compute_shader! { eager!{Offset!()}}}};
+
+    eager! { lazy! { generic_bindings! { next_context = eager!{ Offset!()}}}};
+
+    let (program1, mut bindings1, mut out_bindings1) = compile(&SQUARE).await;
+
+    let (program2, mut bindings2, mut out_bindings2) = compile(&OFFSET).await;
+
+    let values: Vec<u32> = vec![1, 2, 3, 4];
+    let offsets: Vec<u32> = vec![10, 20, 30, 40];
+
+    let context1 = context.bind_values(&values, &program1, &mut bindings1, &mut out_bindings1);
+    let result = context1.runable(|| run(&program1, &mut bindings1, out_bindings1));
+    println!("{:?}", read_uvec(&result, "doubled").await);
+
+    // offsets is bound by hand so only result needs to flow onward, under the name indices
+    // doubled isn't used by the next program and is left behind by mapping it to ""
+    let next_context1 =
+        next_context.bind_offsets(&offsets, &program2, &mut bindings2, &mut out_bindings2);
+    context1.can_pipe_mapped(&next_context1, &[("result", "indices"), ("doubled", "")]);
+    let pipe_result = pipe(
+        &program2,
+        bindings2,
+        out_bindings2,
+        result,
+        &[("result", "indices"), ("doubled", "")],
+    );
+    println!("{:?}", read_uvec(&pipe_result, "offset_result").await);
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
@@ -0,0 +1,73 @@
+#![recursion_limit = "256"]
+
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{
+    compile, compile_shared, read_uvec, ComputeSequence, ComputeShader,
+};
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn execute_gpu() {
+    init!();
+
+    my_shader! {One = {
+        [[buffer loop in] uint[]] add_one_in;
+        [[buffer out] uint[]] add_two_in;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                add_two_in[index] = add_one_in[index]+1;
+            }
+        }}
+    }}
+
+    const ADD_ONE: ComputeShader = eager! { lazy! {compute_shader! { eager!{One!()}}}};
+
+    eager! { lazy! { generic_bindings! { context = eager!{ One!()}}}};
+
+    my_shader! {Two = {
+        [[buffer loop in] uint[]] add_two_in;
+        [[buffer out] uint[]] add_two_result;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                add_two_result[index] = add_two_in[index]+2;
+            }
+        }}
+    }}
+    const ADD_TWO: ComputeShader = eager! { lazy! {compute_shader! { eager!{Two!()}}}};
+    eager! { lazy! { generic_bindings! { next_context = eager!{ Two!()}}}};
+
+    let (program1, mut bindings1, mut out_bindings1) = compile(&ADD_ONE).await;
+
+    // The second program has to live on the same device as the first to share an encoder
+    let (program2, bindings2, out_bindings2) = compile_shared(&program1, &ADD_TWO);
+
+    let indices: Vec<u32> = vec![1, 2, 3, 4];
+
+    {
+        let context1 =
+            context.bind_add_one_in(&indices, &program1, &mut bindings1, &mut out_bindings1);
+        {
+            let sequence = ComputeSequence::new(&program1);
+            let sequence =
+                context1.runable(|| sequence.run(&program1, &mut bindings1, out_bindings1));
+
+            context1.can_pipe(&next_context);
+            // Both dispatches are submitted together
+            let result = sequence
+                .pipe(&program2, bindings2, out_bindings2, &[])
+                .submit();
+            println!("{:?}", read_uvec(&result, "add_two_result").await);
+        }
+    }
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
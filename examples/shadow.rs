@@ -118,7 +118,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     static_assertions::const_assert!(valid_vertex_shader(&S_v));
     static_assertions::const_assert!(valid_fragment_shader(&S_f));
     let (program, mut template_bindings, mut template_out_bindings) =
-        wgpu_graphics_header::graphics_compile(&mut compile_buffer, &window, &S_v, &S_f).await;
+        wgpu_graphics_header::graphics_compile(&mut compile_buffer, &window, &S_v, &S_f, false)
+            .await;
 
     let (positions, normals, indicies) = load_cube();
 
@@ -12,9 +12,9 @@ pub use static_assertions::const_assert;
 
 pub use pipeline::wgpu_graphics_header;
 pub use pipeline::wgpu_graphics_header::{
-    bind_sampler, bind_texture, compile_buffer, default_bind_group, generate_swap_chain,
-    graphics_starting_context, setup_render_pass, valid_fragment_shader, valid_vertex_shader,
-    GraphicsBindings, GraphicsShader, OutGraphicsBindings,
+    bind_sampler, bind_texture, compile_buffer, generate_swap_chain, graphics_starting_context,
+    setup_render_pass, valid_fragment_shader, valid_vertex_shader, GraphicsBindings,
+    GraphicsShader, OutGraphicsBindings,
 };
 
 pub use pipeline::shared;
@@ -179,15 +179,12 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .get_next_texture()
                     .expect("Timeout when acquiring next swap chain texture");
 
-                let mut bind_group = default_bind_group(&program);
                 let mut bindings: GraphicsBindings = template_bindings.clone();
                 let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
 
-                let mut bind_group2 = default_bind_group(&program);
                 let mut bindings2: GraphicsBindings = template_bindings.clone();
                 let mut out_bindings2: OutGraphicsBindings = template_out_bindings.clone();
 
-                let mut bind_group3 = default_bind_group(&program_CUBE);
                 let mut bindings3: GraphicsBindings = template_bindings_CUBE.clone();
                 let mut out_bindings3: OutGraphicsBindings = template_out_bindings_CUBE.clone();
 
@@ -359,7 +356,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                             rpass = wgpu_graphics_header::graphics_run_indicies(
                                                 &program,
                                                 rpass,
-                                                &mut bind_group,
                                                 &mut bindings,
                                                 &out_bindings,
                                                 &indices,
@@ -446,7 +442,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                             rpass = wgpu_graphics_header::graphics_run_indicies(
                                                 &program,
                                                 rpass,
-                                                &mut bind_group2,
                                                 &mut bindings2,
                                                 &out_bindings2,
                                                 &indices2,
@@ -514,7 +509,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                     wgpu_graphics_header::graphics_run_indicies(
                                         &program_CUBE,
                                         rpass,
-                                        &mut bind_group3,
                                         &mut bindings3,
                                         &out_bindings3,
                                         &index_data_cube,
@@ -11,9 +11,8 @@ pub use static_assertions::const_assert;
 
 pub use pipeline::wgpu_graphics_header;
 pub use pipeline::wgpu_graphics_header::{
-    bind_sampler, bind_texture, compile_buffer, default_bind_group, generate_swap_chain,
-    graphics_starting_context, setup_render_pass, valid_fragment_shader, valid_vertex_shader,
-    GraphicsShader,
+    bind_sampler, bind_texture, compile_buffer, generate_swap_chain, graphics_starting_context,
+    setup_render_pass, valid_fragment_shader, valid_vertex_shader, GraphicsShader,
 };
 
 pub use pipeline::shared;
@@ -179,7 +178,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 });
 
                 let mut rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-                let mut bind_group = default_bind_group(&program);
 
                 const BIND_CONTEXT_1: [&str; 32] =
                     update_bind_context(&STARTING_BIND_CONTEXT, "t_Cubemap");
@@ -225,8 +223,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                 wgpu_graphics_header::graphics_run(
                                     &program,
                                     rpass,
-                                    &mut bind_group,
-                                    &bindings,
+                                    &mut bindings,
                                     &out_bindings,
                                 );
                                 program.queue.submit(&[init_encoder.finish()]);
@@ -8,8 +8,8 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    default_bind_group, generate_swap_chain, graphics_run_indicies, setup_render_pass,
-    GraphicsBindings, GraphicsShader, OutGraphicsBindings,
+    generate_swap_chain, graphics_run_indicies, setup_render_pass, GraphicsBindings,
+    GraphicsShader, OutGraphicsBindings,
 };
 
 pub use pipeline::shared::{bind_fvec, bind_mat4, bind_vec3, Bindings};
@@ -99,7 +99,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .expect("Timeout when acquiring next swap chain texture");
 
                 let rpass = setup_render_pass(&program, &mut init_encoder, &frame);
-                let mut bind_group = default_bind_group(&program);
 
                 let mut bindings: GraphicsBindings = template_bindings.clone();
                 let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
@@ -221,7 +220,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                             graphics_run_indicies(
                                                 &program,
                                                 rpass,
-                                                &mut bind_group,
                                                 &mut bindings,
                                                 &out_bindings,
                                                 &indices,
@@ -55,14 +55,14 @@ async fn execute_gpu() {
 
         let result1 = context2.runable(|| run(&program, &mut bindings, result_out_bindings));
 
-        println!("{:?}", read_uvec(&program, &result1, "indices").await);
+        println!("{:?}", read_uvec(&result1, "indices").await);
 
         {
             let context3 =
                 context1.bind_indices(&indices_2, &program, &mut bindings, &mut out_bindings);
             {
                 let result1 = context3.runable(|| run(&program, &mut bindings, out_bindings));
-                println!("{:?}", read_uvec(&program, &result1, "indices").await);
+                println!("{:?}", read_uvec(&result1, "indices").await);
             }
         }
     }
@@ -49,7 +49,7 @@ async fn execute_gpu() {
                 context2.runable();
                 let result_out_bindings = out_bindings.move_buffers();
                 let result1 = run(&program, &mut bindings, result_out_bindings);
-                println!("{:?}", read_uvec(&program, &result1, "indices").await);
+                println!("{:?}", read_uvec(&result1, "indices").await);
             }
         }
         {
@@ -58,7 +58,7 @@ async fn execute_gpu() {
             {
                 context3.runable();
                 let result1 = run(&program, &mut bindings, out_bindings);
-                println!("{:?}", read_uvec(&program, &result1, "indices").await);
+                println!("{:?}", read_uvec(&result1, "indices").await);
             }
         }
     }
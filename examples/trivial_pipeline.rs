@@ -68,16 +68,13 @@ async fn execute_gpu() {
         let context1 =
             context.bind_add_one_in(&indices, &program1, &mut bindings1, &mut out_bindings1);
         {
-            let result =  context1.runable(|| run(&program1, &mut bindings1, out_bindings1));
-            println!("{:?}", read_uvec(&program1, &result, "add_two_in").await);
+            let result = context1.runable(|| run(&program1, &mut bindings1, out_bindings1));
+            println!("{:?}", read_uvec(&result, "add_two_in").await);
 
             context1.can_pipe(&next_context);
-            let pipe_result = pipe(&program2, bindings2, out_bindings2, result);
+            let pipe_result = pipe(&program2, bindings2, out_bindings2, result, &[]);
             /*         println!("{:?}", read_vec(&program2, &pipe_result, "add_two_in").await); */
-            println!(
-                "{:?}",
-                read_uvec(&program2, &pipe_result, "add_two_result").await
-            );
+            println!("{:?}", read_uvec(&pipe_result, "add_two_result").await);
         }
     }
 }
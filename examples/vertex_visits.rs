@@ -0,0 +1,146 @@
+#![recursion_limit = "512"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+use winit::{
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::Window,
+};
+
+pub use pipeline::wgpu_compute_header::read_uvec;
+
+pub use pipeline::wgpu_graphics_header::{
+    compile_buffer, generate_swap_chain, graphics_results, graphics_run, setup_render_pass,
+    valid_fragment_shader, valid_vertex_shader, GraphicsBindings, GraphicsShader,
+    OutGraphicsBindings,
+};
+
+pub use pipeline::bind::Bindings;
+
+pub use wgpu_macros::{generic_bindings, init};
+
+async fn run(event_loop: EventLoop<()>, window: Window) {
+    init!();
+
+    // visits is a read-write storage buffer that every vertex invocation updates
+    my_shader! {vertex = {
+        [[vertex in] vec3] a_position;
+        [[buffer in out] uint[]] visits;
+        [[out] vec4] gl_Position;
+        {{
+            void main() {
+                visits[gl_VertexIndex] = visits[gl_VertexIndex] + 1;
+                gl_Position = vec4(a_position, 1.0);
+            }
+        }}
+    }}
+
+    my_shader! {fragment = {
+        [[out] vec4] color;
+        {{
+            void main() {
+                color = vec4(1.0, 0.4, 0.25, 1.0);
+            }
+        }}
+    }}
+
+    const VERTEXT: GraphicsShader = eager_graphics_shader! {vertex!()};
+
+    const FRAGMENT: GraphicsShader = eager_graphics_shader! {fragment!()};
+
+    eager_binding! {context = vertex!(), fragment!()};
+
+    const S_V: GraphicsShader = VERTEXT;
+    const S_F: GraphicsShader = FRAGMENT;
+
+    let (program, template_bindings, template_out_bindings, _) =
+        compile_valid_graphics_program!(window, S_V, S_F);
+
+    let positions = vec![[0.0, 0.7, 0.0], [-0.5, 0.5, 0.0], [0.5, -0.5, 0.0]];
+    let mut visits: Vec<u32> = vec![0, 0, 0];
+
+    // A "chain" of buffers that we render on to the display
+    let mut swap_chain = generate_swap_chain(&program, &window);
+
+    event_loop.run(move |event, _, control_flow: &mut ControlFlow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            // Everything that can be processed has been so we can now redraw the image on our window
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let mut init_encoder = program
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                let frame = swap_chain
+                    .get_next_texture()
+                    .expect("Timeout when acquiring next swap chain texture");
+                let rpass = setup_render_pass(&program, &mut init_encoder, &frame);
+
+                let mut bindings: GraphicsBindings = template_bindings.clone();
+                let mut out_bindings: OutGraphicsBindings = template_out_bindings.clone();
+
+                {
+                    let context1 = (&context).bind_a_position(
+                        &positions,
+                        &program,
+                        &mut bindings,
+                        &mut out_bindings,
+                    );
+                    {
+                        let context2 = context1.bind_visits(
+                            &visits,
+                            &program,
+                            &mut bindings,
+                            &mut out_bindings,
+                        );
+                        {
+                            context2.runable(|| {
+                                graphics_run(&program, rpass, &mut bindings, &out_bindings)
+                            });
+                        }
+                    }
+                }
+                program.queue.submit(&[init_encoder.finish()]);
+
+                // The draw returns its in out buffers just like a compute run
+                let result = graphics_results(out_bindings);
+                visits = futures::executor::block_on(read_uvec(&result, "visits"));
+            }
+            // Show how many times each vertex has been visited when space is pressed
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Space),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => println!("{:?}", visits),
+            // When the window closes we are done. Change the status
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            // Ignore any other types of events
+            _ => {}
+        }
+    });
+}
+
+fn main() {
+    // From examples of wgpu-rs, set up a window we can use to view our stuff
+    let event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .build(&event_loop)
+        .unwrap();
+
+    futures::executor::block_on(run(event_loop, window));
+}
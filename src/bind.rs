@@ -1,17 +1,32 @@
 use zerocopy::AsBytes as _;
 
-use crate::shared::{Program, GLSLTYPE, QUALIFIER};
+use std::any::Any;
+use std::sync::{Arc, Weak};
+
+use crate::shared::{array_type, PollHandle, Program, GLSLTYPE, QUALIFIER};
 
 #[derive(Debug)]
 pub struct DefaultBinding {
     pub binding_number: u32,
     pub name: String,
-    pub data: Option<wgpu::Buffer>,
+    pub data: Option<Arc<wgpu::Buffer>>,
     pub length: Option<u64>,
+    // The number of bytes written into the buffer in data, new data that fits is written into the same buffer
+    pub size: Option<u64>,
+    // The bytes of a binding of a program run by the cpu executor, which has no buffers
+    pub host: Option<Vec<u8>>,
+    // The poller of the device the buffer in data was created on, which reading it back needs
+    pub poller: Option<PollHandle>,
     pub gtype: GLSLTYPE,
     pub qual: Vec<QUALIFIER>,
 }
 
+impl DefaultBinding {
+    pub fn is_bound(&self) -> bool {
+        self.data.is_some() || self.host.is_some()
+    }
+}
+
 pub fn new_bindings(bindings: &Vec<DefaultBinding>) -> Vec<DefaultBinding> {
     let mut new = Vec::new();
 
@@ -23,11 +38,93 @@ pub fn new_bindings(bindings: &Vec<DefaultBinding>) -> Vec<DefaultBinding> {
             gtype: i.gtype.clone(),
             data: None,
             length: None,
+            size: None,
+            host: None,
+            poller: None,
         })
     }
     new
 }
 
+// Enough bind groups are kept for the buffers of a ping pong pair to alternate between them
+const CACHED_BIND_GROUPS: usize = 2;
+
+// The bind groups that were built for a program, keyed by the resources that were bound when they were built
+#[derive(Debug, Default)]
+pub struct BindGroupCache {
+    entries: Vec<(Vec<ResourceKey>, wgpu::BindGroup)>,
+}
+
+impl BindGroupCache {
+    pub fn contains(&self, key: &[ResourceKey]) -> bool {
+        self.entries.iter().any(|(k, _)| k.as_slice() == key)
+    }
+
+    pub fn insert(&mut self, key: Vec<ResourceKey>, bind_group: wgpu::BindGroup) {
+        // Bind groups whose resources were dropped can never be used again
+        self.entries.retain(|(k, _)| k.iter().all(|x| x.is_alive()));
+        if self.entries.len() == CACHED_BIND_GROUPS {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, bind_group));
+    }
+
+    pub fn get(&self, key: &[ResourceKey]) -> &wgpu::BindGroup {
+        &self
+            .entries
+            .iter()
+            .find(|(k, _)| k.as_slice() == key)
+            .expect("The bind group should have been built before it is used")
+            .1
+    }
+}
+
+// A resource that was bound when a bind group was built, told apart from others by its address and the bytes that were bound
+// A new address means the resource was replaced and the bind group using it has to be rebuilt
+// The weak reference keeps the address from being given to another resource while the key is cached
+#[derive(Debug, Clone)]
+pub struct ResourceKey {
+    binding_number: u32,
+    address: usize,
+    size: Option<u64>,
+    resource: Option<Weak<dyn Any>>,
+}
+
+impl PartialEq for ResourceKey {
+    fn eq(&self, other: &ResourceKey) -> bool {
+        self.binding_number == other.binding_number
+            && self.address == other.address
+            && self.size == other.size
+    }
+}
+
+pub fn resource_key<T: Any>(
+    binding_number: u32,
+    resource: &Option<Arc<T>>,
+    size: Option<u64>,
+) -> ResourceKey {
+    ResourceKey {
+        binding_number,
+        address: resource.as_ref().map_or(0, |x| Arc::as_ptr(x) as usize),
+        size,
+        resource: resource
+            .as_ref()
+            .map(|x| Arc::downgrade(x) as Weak<dyn Any>),
+    }
+}
+
+impl ResourceKey {
+    pub fn binding_number(&self) -> u32 {
+        self.binding_number
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.resource
+            .as_ref()
+            .map_or(true, |x| x.strong_count() > 0)
+    }
+}
+
 pub trait ProgramBindings {
     fn get_bindings(&mut self) -> &mut Vec<DefaultBinding>;
     fn index_binding(&mut self, index: usize) -> &mut DefaultBinding;
@@ -51,18 +148,184 @@ fn bind_helper<R: ProgramBindings, T: OutProgramBindings>(
     acceptable_types: Vec<GLSLTYPE>,
     name: String,
 ) {
-    let mut binding = match bindings.get_bindings().iter().position(|x| x.name == name) {
-        Some(x) => bindings.index_binding(x),
-        None => {
-            let x = out_bindings
-                .get_bindings()
-                .iter()
-                .position(|x| x.name == name)
-                .unwrap_or_else(|| panic!("We couldn't find the binding for {}", name));
-            out_bindings.index_binding(x)
+    let binding = get_binding(bindings, out_bindings, &name);
+
+    check_type(binding, acceptable_types);
+
+    store(program, binding, data, None);
+
+    binding.length = Some(length);
+}
+
+// The buffer that is already bound is written into when the data fits and nothing else holds it
+// A new buffer is only created when the data is larger or the buffer is shared, so a mesh or a pipe never sees the change
+fn store(
+    program: &dyn Program,
+    binding: &mut DefaultBinding,
+    data: &[u8],
+    encoder: Option<&mut wgpu::CommandEncoder>,
+) {
+    if program.on_cpu() {
+        binding.host = Some(data.to_vec());
+        binding.size = Some(data.len() as u64);
+        return;
+    }
+    let fits = binding.size.map_or(false, |size| data.len() as u64 <= size);
+    match &binding.data {
+        Some(buffer) if fits && Arc::strong_count(buffer) == 1 => {
+            write_buffer(program, buffer, data, encoder);
+        }
+        _ => {
+            let buffer = program
+                .get_device()
+                .create_buffer_with_data(data, buffer_usage(&binding.qual));
+            binding.data = Some(Arc::new(buffer));
         }
-    };
+    }
+    binding.poller = program.poller();
+    binding.size = Some(data.len() as u64);
+}
 
+// Bind a buffer that is already on the gpu, such as an attribute of a mesh, without copying it
+pub fn bind_shared_buffer<R: ProgramBindings, T: OutProgramBindings>(
+    bindings: &mut R,
+    out_bindings: &mut T,
+    buffer: &Arc<wgpu::Buffer>,
+    length: u64,
+    size: u64,
+    acceptable_types: Vec<GLSLTYPE>,
+    name: &str,
+) {
+    let binding = get_binding(bindings, out_bindings, name);
+
+    if !is_vertex_attribute(&binding.qual) {
+        panic!(
+            "{} is not a vertex or instance parameter so it can't be bound to a vertex buffer",
+            name
+        )
+    }
+
+    check_type(binding, acceptable_types);
+
+    binding.data = Some(Arc::clone(buffer));
+    binding.size = Some(size);
+    binding.length = Some(length);
+}
+
+// The distance between the values of a dynamic uniform since each one has to start at an aligned offset
+pub fn dynamic_stride(gtype: &GLSLTYPE) -> u64 {
+    let alignment = wgpu::BIND_BUFFER_ALIGNMENT;
+    (gtype.size_of() as u64 + alignment - 1) / alignment * alignment
+}
+
+// Write a value for every object into the buffer of a dynamic uniform
+// Each draw then selects its own value with an offset of the index of the object times the stride
+pub fn bind_dynamic<R: ProgramBindings, T: OutProgramBindings, V: UniformValue>(
+    program: &dyn Program,
+    bindings: &mut R,
+    out_bindings: &mut T,
+    values: &[V],
+    name: &str,
+) {
+    let binding = get_binding(bindings, out_bindings, name);
+
+    if !binding.qual.contains(&QUALIFIER::DYNAMIC) {
+        panic!(
+            "{} needs the dynamic qualifier to be given a value for each object",
+            name
+        )
+    }
+
+    check_type(binding, V::acceptable_types());
+
+    let stride = dynamic_stride(&binding.gtype) as usize;
+    let mut data = vec![0u8; stride * std::cmp::max(values.len(), 1)];
+    for (i, v) in values.iter().enumerate() {
+        let bytes = v.uniform_bytes();
+        data[i * stride..i * stride + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    store(program, binding, &data, None);
+
+    binding.length = Some(values.len() as u64);
+}
+
+// Whether a value is bound to a parameter or written over the value already bound
+// An update records its copy into the encoder it is given so it runs in order with the passes of that encoder
+#[derive(Debug)]
+pub enum WriteMode<'a> {
+    Bind,
+    Update(&'a mut wgpu::CommandEncoder),
+}
+
+// The path every value that is bound as bytes takes, for both bind and update
+pub fn write_helper<R: ProgramBindings, T: OutProgramBindings>(
+    program: &dyn Program,
+    bindings: &mut R,
+    out_bindings: &mut T,
+    data: &[u8],
+    length: u64,
+    acceptable_types: Vec<GLSLTYPE>,
+    name: String,
+    mode: WriteMode,
+) {
+    match mode {
+        WriteMode::Bind => bind_helper(
+            program,
+            bindings,
+            out_bindings,
+            data,
+            length,
+            acceptable_types,
+            name,
+        ),
+        WriteMode::Update(encoder) => update_helper(
+            program,
+            bindings,
+            out_bindings,
+            data,
+            length,
+            acceptable_types,
+            name,
+            encoder,
+        ),
+    }
+}
+
+// Write new data into the buffer that is already bound, which grows when the data doesn't fit
+fn update_helper<R: ProgramBindings, T: OutProgramBindings>(
+    program: &dyn Program,
+    bindings: &mut R,
+    out_bindings: &mut T,
+    data: &[u8],
+    length: u64,
+    acceptable_types: Vec<GLSLTYPE>,
+    name: String,
+    encoder: &mut wgpu::CommandEncoder,
+) {
+    let binding = get_binding(bindings, out_bindings, &name);
+
+    check_type(binding, acceptable_types);
+
+    if !binding.is_bound() {
+        panic!("{} needs to be bound before it can be updated", name)
+    }
+
+    store(program, binding, data, Some(encoder));
+
+    binding.length = Some(length);
+}
+
+fn get_binding<'a, R: ProgramBindings, T: OutProgramBindings>(
+    bindings: &'a mut R,
+    out_bindings: &'a mut T,
+    name: &str,
+) -> &'a mut DefaultBinding {
+    find_binding(bindings, out_bindings, name)
+        .unwrap_or_else(|| panic!("We couldn't find the binding for {}", name))
+}
+
+fn check_type(binding: &DefaultBinding, acceptable_types: Vec<GLSLTYPE>) {
     if !acceptable_types.contains(&binding.gtype) {
         println!("{:?}", &binding.name);
         println!("{:?}", acceptable_types);
@@ -71,45 +334,271 @@ fn bind_helper<R: ProgramBindings, T: OutProgramBindings>(
             &binding.gtype
         );
     }
+}
 
-    let buffer = program.get_device().create_buffer_with_data(
-        data,
-        if binding.qual.contains(&QUALIFIER::VERTEX) {
-            wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST
-        } else if binding.qual.contains(&QUALIFIER::UNIFORM) {
-            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST
-        } else {
-            wgpu::BufferUsage::MAP_READ
-                | wgpu::BufferUsage::COPY_DST
-                | wgpu::BufferUsage::STORAGE
-                | wgpu::BufferUsage::COPY_SRC
-                | wgpu::BufferUsage::VERTEX
+// Copy the data through a staging buffer into a buffer that already exists
+// The copy is recorded into the encoder when there is one, otherwise it is submitted right away
+fn write_buffer(
+    program: &dyn Program,
+    buffer: &wgpu::Buffer,
+    data: &[u8],
+    encoder: Option<&mut wgpu::CommandEncoder>,
+) {
+    let staging = program
+        .get_device()
+        .create_buffer_with_data(data, wgpu::BufferUsage::COPY_SRC);
+
+    match encoder {
+        Some(encoder) => encoder.copy_buffer_to_buffer(&staging, 0, buffer, 0, data.len() as u64),
+        None => {
+            let mut encoder = program
+                .get_device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.copy_buffer_to_buffer(&staging, 0, buffer, 0, data.len() as u64);
+            program.get_queue().submit(&[encoder.finish()]);
+        }
+    }
+}
+
+// The usage a buffer is created with when it is bound to a parameter with these qualifiers
+// Buffers created as the out parameters of a compute run share the usage of other storage buffers
+pub fn buffer_usage(qual: &[QUALIFIER]) -> wgpu::BufferUsage {
+    if qual.contains(&QUALIFIER::VERTEX) {
+        wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST
+    } else if qual.contains(&QUALIFIER::UNIFORM) {
+        wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST
+    } else {
+        wgpu::BufferUsage::MAP_READ
+            | wgpu::BufferUsage::COPY_DST
+            | wgpu::BufferUsage::STORAGE
+            | wgpu::BufferUsage::COPY_SRC
+            | wgpu::BufferUsage::VERTEX
+    }
+}
+
+// The usage a buffer needs to be used by a parameter with these qualifiers
+fn required_usage(qual: &[QUALIFIER]) -> wgpu::BufferUsage {
+    if qual.contains(&QUALIFIER::UNIFORM) {
+        wgpu::BufferUsage::UNIFORM
+    } else if qual.contains(&QUALIFIER::BUFFER) {
+        wgpu::BufferUsage::STORAGE
+    } else {
+        wgpu::BufferUsage::VERTEX
+    }
+}
+
+// Parameters that are neither uniforms nor buffers are read per vertex or per instance from a vertex buffer
+pub fn is_vertex_attribute(qual: &[QUALIFIER]) -> bool {
+    !qual.contains(&QUALIFIER::UNIFORM) && !qual.contains(&QUALIFIER::BUFFER)
+}
+
+// A result can be piped into a parameter of the same type
+// An array result can also be piped into a vertex attribute of its element type
+fn pipe_types_match(result: &DefaultBinding, param: &DefaultBinding) -> bool {
+    result.gtype == param.gtype
+        || (is_vertex_attribute(&param.qual)
+            && result.gtype.is_array()
+            && result.gtype == array_type(param.gtype.clone(), 1))
+}
+
+fn find_binding<'a, R: ProgramBindings, T: OutProgramBindings>(
+    bindings: &'a mut R,
+    out_bindings: &'a mut T,
+    name: &str,
+) -> Option<&'a mut DefaultBinding> {
+    match bindings.get_bindings().iter().position(|x| x.name == name) {
+        Some(x) => Some(bindings.index_binding(x)),
+        None => match out_bindings
+            .get_bindings()
+            .iter()
+            .position(|x| x.name == name)
+        {
+            Some(x) => Some(out_bindings.index_binding(x)),
+            None => None,
         },
-    );
+    }
+}
 
-    binding.data = Some(buffer);
-    binding.length = Some(length);
+// Move the buffers of one program's results into the parameters of the next program
+// Each (result, parameter) pair of the mapping pipes a result into a parameter with a different name
+// A result mapped to "" is left behind, every other result is piped into the parameter of the same name
+// Every result is checked against the parameter it is piped into and all of the mismatches are reported together
+pub fn pipe_bindings<R: ProgramBindings, T: OutProgramBindings>(
+    bindings: &mut R,
+    out_bindings: &mut T,
+    result_vec: Vec<DefaultBinding>,
+    mapping: &[(&str, &str)],
+) {
+    let mut mismatches = Vec::new();
+    let mut checked = Vec::new();
+
+    for (k, (from, to)) in mapping.iter().enumerate() {
+        if !result_vec.iter().any(|x| x.name == *from) {
+            mismatches.push(format!("{} is not one of the results being piped", from));
+        }
+        if !to.is_empty() && find_binding(bindings, out_bindings, to).is_none() {
+            mismatches.push(format!(
+                "{} is not a parameter of the program being piped into",
+                to
+            ));
+        }
+        if mapping[..k].iter().any(|(x, _)| x == from) {
+            mismatches.push(format!("{} is mapped more than once", from));
+        }
+    }
+
+    for i in result_vec {
+        let mapped = mapping.iter().find(|(from, _)| *from == i.name);
+        let target = match mapped {
+            Some((_, "")) => continue,
+            Some((_, to)) => to.to_string(),
+            None => i.name.clone(),
+        };
+
+        let param = match find_binding(bindings, out_bindings, &target) {
+            Some(x) => x,
+            None => {
+                // Mapped targets that don't exist were already reported
+                if mapped.is_none() {
+                    mismatches.push(format!(
+                        "{} is not a parameter of the program being piped into, map it to \"\" to leave it behind",
+                        target
+                    ));
+                }
+                continue;
+            }
+        };
+
+        if !pipe_types_match(&i, param) {
+            mismatches.push(format!(
+                "{} has type {} but is piped into {} of type {}",
+                i.name, i.gtype, target, param.gtype
+            ));
+        }
+
+        let length = match (i.is_bound(), i.length) {
+            (true, Some(length)) => length,
+            _ => {
+                mismatches.push(format!("{} has no data to pipe", i.name));
+                continue;
+            }
+        };
+
+        let usage = buffer_usage(&i.qual);
+        if !usage.contains(required_usage(&param.qual)) {
+            mismatches.push(format!(
+                "{} is a buffer with usage {:?} which can't be used by {} with qualifiers {:?}",
+                i.name, usage, target, param.qual
+            ));
+        }
+
+        if !param.gtype.is_array() && !is_vertex_attribute(&param.qual) && length != 1 {
+            mismatches.push(format!(
+                "{} has {} elements but is piped into {} of type {}",
+                i.name, length, target, param.gtype
+            ));
+        }
+
+        if checked.iter().any(|(x, _, _)| *x == target) {
+            mismatches.push(format!("More than one result is piped into {}", target));
+        }
+
+        let qual = param.qual.clone();
+        checked.push((target, i, qual));
+    }
+
+    // Loop and vertex parameters are each assumed to be of equal length
+    for group in [QUALIFIER::LOOP, QUALIFIER::VERTEX].iter() {
+        let mut lengths: Vec<(String, u64)> = bindings
+            .get_bindings()
+            .iter()
+            .chain(out_bindings.get_bindings().iter())
+            .filter(|x| x.qual.contains(group) && x.is_bound())
+            .filter(|x| !checked.iter().any(|(target, _, _)| *target == x.name))
+            .map(|x| (x.name.clone(), x.length.unwrap()))
+            .collect();
+        lengths.extend(
+            checked
+                .iter()
+                .filter(|(_, _, qual)| qual.contains(group))
+                .map(|(target, i, _)| (target.clone(), i.length.unwrap())),
+        );
+        if lengths.iter().any(|(_, x)| *x != lengths[0].1) {
+            mismatches.push(format!(
+                "The {:?} parameters would have different lengths: {:?}",
+                group, lengths
+            ));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        panic!(
+            "The results could not be piped into the program:\n{}",
+            mismatches.join("\n")
+        );
+    }
+
+    for (target, i, _) in checked {
+        let param = find_binding(bindings, out_bindings, &target).unwrap();
+        param.data = i.data;
+        param.length = i.length;
+        param.size = i.size;
+        param.host = i.host;
+        param.poller = i.poller;
+    }
 }
 
 pub trait Bindable {
-    fn bind<R: ProgramBindings, T: OutProgramBindings>(
+    // Bind or update a parameter with this value, bind and update both go through here
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
         &self,
         program: &dyn Program,
         bindings: &mut R,
         out_bindings: &mut T,
         name: String,
+        mode: WriteMode,
     );
+
+    fn bind<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+    ) {
+        self.write(program, bindings, out_bindings, name, WriteMode::Bind)
+    }
+
+    // Overwrite the contents of a parameter that is already bound, such as a uniform that changes every frame
+    // The copy is recorded into the encoder of the frame, before any pass that should see the new value
+    fn update<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        self.write(
+            program,
+            bindings,
+            out_bindings,
+            name,
+            WriteMode::Update(encoder),
+        )
+    }
 }
 
 impl Bindable for Vec<u32> {
-    fn bind<R: ProgramBindings, T: OutProgramBindings>(
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
         &self,
         program: &dyn Program,
         bindings: &mut R,
         out_bindings: &mut T,
         name: String,
+        mode: WriteMode,
     ) {
-        bind_helper(
+        write_helper(
             program,
             bindings,
             out_bindings,
@@ -117,19 +606,21 @@ impl Bindable for Vec<u32> {
             self.len() as u64,
             vec![GLSLTYPE::ArrayInt, GLSLTYPE::ArrayUint],
             name,
+            mode,
         );
     }
 }
 
 impl Bindable for Vec<f32> {
-    fn bind<R: ProgramBindings, T: OutProgramBindings>(
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
         &self,
         program: &dyn Program,
         bindings: &mut R,
         out_bindings: &mut T,
         name: String,
+        mode: WriteMode,
     ) {
-        bind_helper(
+        write_helper(
             program,
             bindings,
             out_bindings,
@@ -137,17 +628,19 @@ impl Bindable for Vec<f32> {
             self.len() as u64,
             vec![GLSLTYPE::Float, GLSLTYPE::ArrayFloat],
             name,
+            mode,
         )
     }
 }
 
 impl Bindable for Vec<[f32; 3]> {
-    fn bind<R: ProgramBindings, T: OutProgramBindings>(
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
         &self,
         program: &dyn Program,
         bindings: &mut R,
         out_bindings: &mut T,
         name: String,
+        mode: WriteMode,
     ) {
         let numbers: Vec<f32> = self
             .clone()
@@ -155,7 +648,7 @@ impl Bindable for Vec<[f32; 3]> {
             .map(|x| x.to_vec())
             .flatten()
             .collect();
-        bind_helper(
+        write_helper(
             program,
             bindings,
             out_bindings,
@@ -163,10 +656,100 @@ impl Bindable for Vec<[f32; 3]> {
             self.len() as u64,
             vec![GLSLTYPE::Vec3, GLSLTYPE::ArrayVec3],
             name,
+            mode,
         )
     }
 }
 
+impl Bindable for cgmath::Matrix4<f32> {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        write_helper(
+            program,
+            bindings,
+            out_bindings,
+            self.uniform_bytes().as_slice(),
+            1,
+            vec![GLSLTYPE::Mat4],
+            name,
+            mode,
+        )
+    }
+}
+
+// A matrix for every instance of an instance parameter
+impl Bindable for Vec<cgmath::Matrix4<f32>> {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        let numbers: Vec<u8> = self.iter().map(|x| x.uniform_bytes()).flatten().collect();
+        write_helper(
+            program,
+            bindings,
+            out_bindings,
+            numbers.as_slice(),
+            self.len() as u64,
+            vec![GLSLTYPE::Mat4],
+            name,
+            mode,
+        )
+    }
+}
+
+// A single value of a uniform, used for the per object values of a dynamic uniform
+pub trait UniformValue {
+    fn acceptable_types() -> Vec<GLSLTYPE>;
+    fn uniform_bytes(&self) -> Vec<u8>;
+}
+
+impl UniformValue for f32 {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        vec![GLSLTYPE::Float]
+    }
+    fn uniform_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl UniformValue for [f32; 3] {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        vec![GLSLTYPE::Vec3]
+    }
+    fn uniform_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl UniformValue for [f32; 4] {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        vec![GLSLTYPE::Vec4]
+    }
+    fn uniform_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl UniformValue for cgmath::Matrix4<f32> {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        vec![GLSLTYPE::Mat4]
+    }
+    fn uniform_bytes(&self) -> Vec<u8> {
+        let mat: &[f32; 16] = self.as_ref();
+        mat.as_bytes().to_vec()
+    }
+}
+
 /* pub fn bind_vec2(
     program: &dyn Program,
     bindings: &mut dyn ProgramBindings,
@@ -0,0 +1,383 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+use winit::event::{
+    ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+};
+
+use crate::helper::depth_correction_matrix;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Projection {
+    // fov_y is the vertical field of view in degrees
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    // height is how much of the world is visible from the bottom to the top of the screen
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+// Where the scene is looked at from, producing the u_view and u_proj matrices for a shader
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub projection: Projection,
+    // Width divided by height of the surface that is drawn to
+    pub aspect_ratio: f32,
+}
+
+// The same view and projection as generate_view_matrix and generate_projection_matrix
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::perspective(
+            Point3::new(0.1, 4.8, -10.0),
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+        )
+    }
+}
+
+impl Camera {
+    // A 45 degree field of view with near and far planes at 1 and 100 and z pointing up
+    pub fn perspective(eye: Point3<f32>, target: Point3<f32>, aspect_ratio: f32) -> Self {
+        Camera {
+            eye,
+            target,
+            up: Vector3::unit_z(),
+            projection: Projection::Perspective {
+                fov_y: 45.0,
+                near: 1.0,
+                far: 100.0,
+            },
+            aspect_ratio,
+        }
+    }
+
+    pub fn orthographic(
+        eye: Point3<f32>,
+        target: Point3<f32>,
+        aspect_ratio: f32,
+        height: f32,
+    ) -> Self {
+        Camera {
+            eye,
+            target,
+            up: Vector3::unit_z(),
+            projection: Projection::Orthographic {
+                height,
+                near: 1.0,
+                far: 100.0,
+            },
+            aspect_ratio,
+        }
+    }
+
+    pub fn with_up(mut self, up: Vector3<f32>) -> Self {
+        self.up = up;
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    // Keep the aspect ratio in line with the surface when the window is resized
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if height > 0 {
+            self.aspect_ratio = width as f32 / height as f32;
+        }
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        if (self.target - self.eye).magnitude2() == 0.0 {
+            panic!("The eye and target of a camera can't be the same point")
+        }
+        Matrix4::look_at(self.eye, self.target, self.up)
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        let projection = match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                check_planes(near, far);
+                if near <= 0.0 {
+                    panic!("The near plane of a perspective camera needs to be in front of it but it was {}", near)
+                }
+                cgmath::perspective(cgmath::Deg(fov_y), self.aspect_ratio, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                check_planes(near, far);
+                let half_height = height / 2.0;
+                let half_width = half_height * self.aspect_ratio;
+                cgmath::ortho(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        };
+        depth_correction_matrix() * projection
+    }
+}
+
+fn check_planes(near: f32, far: f32) {
+    if near >= far {
+        panic!(
+            "The near plane of a camera needs to be closer than the far plane but they were {} and {}",
+            near, far
+        )
+    }
+}
+
+// Two directions at right angles to up and each other, which yaw is measured from
+fn horizontal_axes(up: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let up = up.normalize();
+    let reference = if up.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let right = (reference - up * reference.dot(up)).normalize();
+    (right, up.cross(right))
+}
+
+fn direction(up: Vector3<f32>, yaw: f32, pitch: f32) -> Vector3<f32> {
+    let (right, side) = horizontal_axes(up);
+    (right * yaw.cos() + side * yaw.sin()) * pitch.cos() + up.normalize() * pitch.sin()
+}
+
+// The yaw and pitch that point in this direction
+fn angles(up: Vector3<f32>, direction: Vector3<f32>) -> (f32, f32) {
+    let (right, side) = horizontal_axes(up);
+    let direction = direction.normalize();
+    (
+        direction.dot(side).atan2(direction.dot(right)),
+        direction.dot(up.normalize()).max(-1.0).min(1.0).asin(),
+    )
+}
+
+// Looking straight along up makes the view matrix undefined so pitch stops just short of it
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+// Tracks the cursor while a mouse button is held and gives how far it moved
+#[derive(Debug, Default)]
+struct Drag {
+    held: bool,
+    last: Option<(f64, f64)>,
+}
+
+impl Drag {
+    fn process_event(&mut self, event: &WindowEvent, button: MouseButton) -> Option<(f32, f32)> {
+        match event {
+            WindowEvent::MouseInput {
+                state, button: b, ..
+            } if *b == button => {
+                self.held = *state == ElementState::Pressed;
+                None
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let moved = match self.last {
+                    Some((x, y)) if self.held => {
+                        Some(((position.x - x) as f32, (position.y - y) as f32))
+                    }
+                    _ => None,
+                };
+                self.last = Some((position.x, position.y));
+                moved
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.last = None;
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+fn key_event(event: &WindowEvent) -> Option<(VirtualKeyCode, bool)> {
+    match event {
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    virtual_keycode: Some(key),
+                    state,
+                    ..
+                },
+            ..
+        } => Some((*key, *state == ElementState::Pressed)),
+        _ => None,
+    }
+}
+
+// Circles the target of a camera, dragging with the left mouse button or the arrow keys turns around it
+// and the mouse wheel or page up and page down move closer or further away
+#[derive(Debug)]
+pub struct OrbitController {
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    // Radians turned for every pixel the cursor is dragged
+    pub sensitivity: f32,
+    // Radians turned for every press of an arrow key
+    pub key_step: f32,
+    // How much the distance is scaled by for every line scrolled
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    drag: Drag,
+}
+
+impl OrbitController {
+    // Start from wherever the camera is looking at its target from
+    pub fn new(camera: &Camera) -> Self {
+        let offset = camera.eye - camera.target;
+        let (yaw, pitch) = angles(camera.up, offset);
+        OrbitController {
+            distance: offset.magnitude(),
+            yaw,
+            pitch,
+            sensitivity: 0.01,
+            key_step: 0.05,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            drag: Drag::default(),
+        }
+    }
+
+    // Returns true when the event changed the controller
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        if let Some((dx, dy)) = self.drag.process_event(event, MouseButton::Left) {
+            self.turn(-dx * self.sensitivity, dy * self.sensitivity);
+            return true;
+        }
+
+        let zoom = match event {
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, y) => *y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+            },
+            _ => match key_event(event) {
+                Some((VirtualKeyCode::Left, true)) => {
+                    self.turn(self.key_step, 0.0);
+                    return true;
+                }
+                Some((VirtualKeyCode::Right, true)) => {
+                    self.turn(-self.key_step, 0.0);
+                    return true;
+                }
+                Some((VirtualKeyCode::Up, true)) => {
+                    self.turn(0.0, self.key_step);
+                    return true;
+                }
+                Some((VirtualKeyCode::Down, true)) => {
+                    self.turn(0.0, -self.key_step);
+                    return true;
+                }
+                Some((VirtualKeyCode::PageUp, true)) => 1.0,
+                Some((VirtualKeyCode::PageDown, true)) => -1.0,
+                _ => return false,
+            },
+        };
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(zoom)).max(self.min_distance);
+        true
+    }
+
+    fn turn(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).max(-MAX_PITCH).min(MAX_PITCH);
+    }
+
+    pub fn update_camera(&self, camera: &mut Camera) {
+        camera.eye = camera.target + direction(camera.up, self.yaw, self.pitch) * self.distance;
+    }
+}
+
+// Moves the camera like a first person game, w a s d move, q and e go down and up along the up axis
+// and dragging with the left mouse button looks around
+#[derive(Debug)]
+pub struct FlyController {
+    pub yaw: f32,
+    pub pitch: f32,
+    // Distance moved every second
+    pub speed: f32,
+    pub sensitivity: f32,
+    forward: f32,
+    right: f32,
+    rise: f32,
+    held: Vec<VirtualKeyCode>,
+    drag: Drag,
+}
+
+impl FlyController {
+    // Start looking the same way as the camera
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch) = angles(camera.up, camera.target - camera.eye);
+        FlyController {
+            yaw,
+            pitch,
+            speed: 5.0,
+            sensitivity: 0.005,
+            forward: 0.0,
+            right: 0.0,
+            rise: 0.0,
+            held: Vec::new(),
+            drag: Drag::default(),
+        }
+    }
+
+    // Returns true when the event changed the controller
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        if let Some((dx, dy)) = self.drag.process_event(event, MouseButton::Left) {
+            self.yaw -= dx * self.sensitivity;
+            self.pitch = (self.pitch - dy * self.sensitivity)
+                .max(-MAX_PITCH)
+                .min(MAX_PITCH);
+            return true;
+        }
+
+        match key_event(event) {
+            Some((key, pressed)) => {
+                match key {
+                    VirtualKeyCode::W
+                    | VirtualKeyCode::A
+                    | VirtualKeyCode::S
+                    | VirtualKeyCode::D
+                    | VirtualKeyCode::Q
+                    | VirtualKeyCode::E => {}
+                    _ => return false,
+                }
+                self.held.retain(|k| *k != key);
+                if pressed {
+                    self.held.push(key);
+                }
+                let held = &self.held;
+                let axis = |positive, negative| {
+                    held.contains(&positive) as i32 as f32 - held.contains(&negative) as i32 as f32
+                };
+                let (forward, right, rise) = (
+                    axis(VirtualKeyCode::W, VirtualKeyCode::S),
+                    axis(VirtualKeyCode::D, VirtualKeyCode::A),
+                    axis(VirtualKeyCode::E, VirtualKeyCode::Q),
+                );
+                self.forward = forward;
+                self.right = right;
+                self.rise = rise;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Move by however far the held keys go in seconds and look where the mouse has turned to
+    pub fn update_camera(&self, camera: &mut Camera, seconds: f32) {
+        let up = camera.up.normalize();
+        let forward = direction(camera.up, self.yaw, self.pitch);
+        let right = forward.cross(up).normalize();
+
+        let movement = forward * self.forward + right * self.right + up * self.rise;
+        if movement.magnitude2() > 0.0 {
+            camera.eye += movement.normalize() * self.speed * seconds;
+        }
+        camera.target = camera.eye + forward;
+    }
+}
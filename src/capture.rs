@@ -0,0 +1,367 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::bind::DefaultBinding;
+use crate::shared::Program;
+use crate::wgpu_compute_header::{read_fvec, read_uvec};
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(PathBuf, std::io::Error),
+    Encode(PathBuf, png::EncodingError),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaptureError::Io(path, e) => {
+                write!(f, "{} could not be created: {}", path.display(), e)
+            }
+            CaptureError::Encode(path, e) => {
+                write!(f, "{} could not be written as a png: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+fn check_pixels(width: u32, height: u32, pixels: usize, what: &str) {
+    if width == 0 || height == 0 {
+        panic!(
+            "An image needs to be at least 1 by 1 but it was {} by {}",
+            width, height
+        )
+    }
+    let needed = width as usize * height as usize;
+    if pixels != needed {
+        panic!(
+            "A {} by {} image needs {} {} but there were {}",
+            width, height, needed, what, pixels
+        )
+    }
+}
+
+// Write rgba8 pixels, row by row from the top, to a png file
+pub fn write_png(path: &str, width: u32, height: u32, pixels: &[u8]) -> Result<(), CaptureError> {
+    if pixels.len() % 4 != 0 {
+        panic!(
+            "Rgba pixels take 4 bytes each but {} bytes were given",
+            pixels.len()
+        )
+    }
+    check_pixels(width, height, pixels.len() / 4, "rgba pixels");
+
+    let path = Path::new(path);
+    let file = File::create(path).map_err(|e| CaptureError::Io(path.to_path_buf(), e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|e| CaptureError::Encode(path.to_path_buf(), e))
+}
+
+// Copy a texture back from the gpu as rgba8 pixels, row by row from the top
+// The texture needs to have been created with COPY_SRC usage and be in an 8 bit rgba or bgra format
+// The device of the program has to be polled for the copy to finish, which every compiled program does
+pub fn read_texture(
+    program: &dyn Program,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> Vec<u8> {
+    let bgra = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        _ => panic!(
+            "Only 8 bit rgba and bgra textures can be read back but this one is {:?}",
+            format
+        ),
+    };
+    check_pixels(width, height, (width * height) as usize, "pixels");
+
+    // Rows of a copy out of a texture have to start at an aligned offset
+    let row = 4 * width as usize;
+    let padded_row = (row + 255) / 256 * 256;
+    let size = (padded_row * height as usize) as u64;
+
+    let buffer = program.get_device().create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+    });
+    let mut encoder = program
+        .get_device()
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::BufferCopyView {
+            buffer: &buffer,
+            offset: 0,
+            bytes_per_row: padded_row as u32,
+            rows_per_image: height,
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    );
+    program.get_queue().submit(&[encoder.finish()]);
+
+    let _pending = program
+        .poller()
+        .expect("Textures can only be read back through a program with a device")
+        .start_mapping();
+    let mapping = futures::executor::block_on(buffer.map_read(0, size))
+        .expect("The texture could not be read back from the gpu");
+    let mut pixels = Vec::with_capacity(row * height as usize);
+    for line in mapping.as_slice().chunks(padded_row) {
+        pixels.extend_from_slice(&line[..row]);
+    }
+    if bgra {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    pixels
+}
+
+// Save a texture, such as a render target or one made by create_texture_rgba with COPY_SRC, to a png file
+pub fn save_texture_png(
+    program: &dyn Program,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    path: &str,
+) -> Result<(), CaptureError> {
+    let pixels = read_texture(program, texture, width, height, format);
+    write_png(path, width, height, &pixels)
+}
+
+// Save a uint[] result where each uint is one pixel packed like packUnorm4x8, red in the lowest byte
+pub fn save_rgba8_png(
+    results: &Vec<DefaultBinding>,
+    name: &str,
+    width: u32,
+    height: u32,
+    path: &str,
+) -> Result<(), CaptureError> {
+    let packed = futures::executor::block_on(read_uvec(results, name));
+    check_pixels(width, height, packed.len(), "packed pixels");
+    let pixels: Vec<u8> = packed
+        .iter()
+        .flat_map(|p| p.to_le_bytes().to_vec())
+        .collect();
+    write_png(path, width, height, &pixels)
+}
+
+// How the values of a float image are turned into colors, from the low end of the range to the high end
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Colormap {
+    Grayscale,
+    // Dark purple through blue and green to yellow, which stays readable in grayscale
+    Viridis,
+    // Blue through white to red, for values on either side of a middle such as signed errors
+    CoolWarm,
+}
+
+const VIRIDIS: [[f32; 3]; 5] = [
+    [0.267, 0.005, 0.329],
+    [0.229, 0.322, 0.546],
+    [0.128, 0.567, 0.551],
+    [0.369, 0.789, 0.383],
+    [0.993, 0.906, 0.144],
+];
+
+const COOL_WARM: [[f32; 3]; 3] = [
+    [0.230, 0.299, 0.754],
+    [0.865, 0.865, 0.865],
+    [0.706, 0.016, 0.150],
+];
+
+// Values that aren't finite numbers stand out in magenta
+const NOT_A_NUMBER: [u8; 4] = [255, 0, 255, 255];
+
+fn interpolate(stops: &[[f32; 3]], t: f32) -> [f32; 3] {
+    let scaled = t * (stops.len() - 1) as f32;
+    let i = (scaled.floor() as usize).min(stops.len() - 2);
+    let f = scaled - i as f32;
+    let (a, b) = (stops[i], stops[i + 1]);
+    [
+        a[0] + (b[0] - a[0]) * f,
+        a[1] + (b[1] - a[1]) * f,
+        a[2] + (b[2] - a[2]) * f,
+    ]
+}
+
+impl Colormap {
+    // t is clamped between 0 and 1
+    pub fn color(&self, t: f32) -> [u8; 4] {
+        if !t.is_finite() {
+            return NOT_A_NUMBER;
+        }
+        let t = t.max(0.0).min(1.0);
+        let rgb = match self {
+            Colormap::Grayscale => [t, t, t],
+            Colormap::Viridis => interpolate(&VIRIDIS, t),
+            Colormap::CoolWarm => interpolate(&COOL_WARM, t),
+        };
+        [
+            (rgb[0] * 255.0).round() as u8,
+            (rgb[1] * 255.0).round() as u8,
+            (rgb[2] * 255.0).round() as u8,
+            255,
+        ]
+    }
+}
+
+// Color float values with a colormap, row by row from the top
+// Values are scaled so the range goes from one end of the colormap to the other
+// Without a range the smallest and largest values that are numbers are used
+pub fn colormap_pixels(values: &[f32], colormap: Colormap, range: Option<(f32, f32)>) -> Vec<u8> {
+    let (low, high) = range.unwrap_or_else(|| {
+        values.iter().filter(|v| v.is_finite()).fold(
+            (std::f32::INFINITY, std::f32::NEG_INFINITY),
+            |(low, high), v| (low.min(*v), high.max(*v)),
+        )
+    });
+    let span = if high > low { high - low } else { 1.0 };
+    values
+        .iter()
+        .flat_map(|v| colormap.color((v - low) / span).to_vec())
+        .collect()
+}
+
+// Save a float[] result with one value per pixel as a png colored by the colormap
+pub fn save_float_png(
+    results: &Vec<DefaultBinding>,
+    name: &str,
+    width: u32,
+    height: u32,
+    colormap: Colormap,
+    range: Option<(f32, f32)>,
+    path: &str,
+) -> Result<(), CaptureError> {
+    let values = futures::executor::block_on(read_fvec(results, name));
+    check_pixels(width, height, values.len(), "values");
+    write_png(
+        path,
+        width,
+        height,
+        &colormap_pixels(&values, colormap, range),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_png(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("capture_{}_{}.png", std::process::id(), name))
+    }
+
+    #[test]
+    fn colormaps_reach_their_ends() {
+        assert_eq!(Colormap::Grayscale.color(0.0), [0, 0, 0, 255]);
+        assert_eq!(Colormap::Grayscale.color(1.0), [255, 255, 255, 255]);
+        assert_eq!(Colormap::Viridis.color(0.0), [68, 1, 84, 255]);
+        assert_eq!(Colormap::Viridis.color(1.0), [253, 231, 37, 255]);
+        assert_eq!(Colormap::CoolWarm.color(0.0), [59, 76, 192, 255]);
+        assert_eq!(Colormap::CoolWarm.color(0.5), [221, 221, 221, 255]);
+        assert_eq!(Colormap::CoolWarm.color(1.0), [180, 4, 38, 255]);
+    }
+
+    #[test]
+    fn colormaps_clamp_outside_the_range() {
+        for colormap in [Colormap::Grayscale, Colormap::Viridis, Colormap::CoolWarm].iter() {
+            assert_eq!(colormap.color(-3.0), colormap.color(0.0));
+            assert_eq!(colormap.color(7.0), colormap.color(1.0));
+        }
+    }
+
+    #[test]
+    fn values_that_are_not_numbers_are_magenta() {
+        assert_eq!(Colormap::Viridis.color(std::f32::NAN), NOT_A_NUMBER);
+        assert_eq!(Colormap::Grayscale.color(std::f32::INFINITY), NOT_A_NUMBER);
+        assert_eq!(
+            Colormap::CoolWarm.color(std::f32::NEG_INFINITY),
+            NOT_A_NUMBER
+        );
+    }
+
+    #[test]
+    fn the_range_of_the_data_skips_values_that_are_not_numbers() {
+        let pixels = colormap_pixels(&[2.0, std::f32::NAN, 4.0, 3.0], Colormap::Grayscale, None);
+        assert_eq!(
+            pixels,
+            vec![0, 0, 0, 255, 255, 0, 255, 255, 255, 255, 255, 255, 128, 128, 128, 255]
+        );
+    }
+
+    #[test]
+    fn a_given_range_is_used_as_is() {
+        let pixels = colormap_pixels(&[0.0, 5.0, 10.0], Colormap::Grayscale, Some((0.0, 5.0)));
+        assert_eq!(
+            pixels,
+            vec![0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn flat_and_empty_data_still_get_colors() {
+        assert_eq!(
+            colormap_pixels(&[3.0, 3.0], Colormap::Grayscale, None),
+            vec![0, 0, 0, 255, 0, 0, 0, 255]
+        );
+        assert_eq!(
+            colormap_pixels(&[std::f32::NAN], Colormap::Grayscale, None),
+            NOT_A_NUMBER.to_vec()
+        );
+    }
+
+    #[test]
+    fn written_pngs_keep_their_pixels() {
+        let path = temp_png("round_trip");
+        let pixels: Vec<u8> = (0..2 * 3 * 4).map(|i| (i * 10) as u8).collect();
+        write_png(path.to_str().unwrap(), 2, 3, &pixels).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((info.width, info.height), (2, 3));
+        assert_eq!(info.color_type, png::ColorType::RGBA);
+        assert_eq!(buf, pixels);
+    }
+
+    #[test]
+    fn writing_into_a_missing_directory_is_an_error() {
+        let path = std::env::temp_dir()
+            .join(format!("capture_missing_{}", std::process::id()))
+            .join("image.png");
+        match write_png(path.to_str().unwrap(), 1, 1, &[0, 0, 0, 255]) {
+            Err(CaptureError::Io(p, _)) => assert_eq!(p, path),
+            other => panic!("Expected an io error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "A 2 by 2 image needs 4 rgba pixels but there were 3")]
+    fn the_pixels_have_to_fill_the_image() {
+        write_png(temp_png("short").to_str().unwrap(), 2, 2, &[0; 12]).unwrap();
+    }
+}
@@ -91,3 +91,80 @@ pub const fn can_pipe(s_out: &BindingContext, s_in: &BindingContext) -> bool {
     }
     true
 }
+
+const fn mapping_contains_from(mapping: &[(&str, &str)], name: &str) -> bool {
+    let mut acc = 0;
+    while acc < mapping.len() {
+        if string_compare(mapping[acc].0, name) {
+            return true;
+        }
+        acc += 1;
+    }
+    false
+}
+
+const fn mapping_contains_to(mapping: &[(&str, &str)], name: &str) -> bool {
+    let mut acc = 0;
+    while acc < mapping.len() {
+        if string_compare(mapping[acc].1, name) {
+            return true;
+        }
+        acc += 1;
+    }
+    false
+}
+
+// Each (out, in) pair of the mapping renames an out of s_out to an in of s_in
+// Every in of s_in that is still unbound must be produced by s_out, either through the mapping or by name
+// Outs that don't flow onward have to be mapped to "" so that only part of the results is piped on purpose
+pub const fn can_pipe_mapped(
+    s_out: &BindingContext,
+    s_in: &BindingContext,
+    mapping: &[(&str, &str)],
+) -> bool {
+    let mut acc = 0;
+    while acc < mapping.len() {
+        let leaves_behind = string_compare(mapping[acc].1, "");
+        if !params_contain_string(&s_out.result_context, mapping[acc].0)
+            || (!leaves_behind && !params_contain_string(&s_in.starting_context, mapping[acc].1))
+        {
+            return false;
+        }
+        let mut other = acc + 1;
+        while other < mapping.len() {
+            if string_compare(mapping[acc].0, mapping[other].0)
+                || (!leaves_behind && string_compare(mapping[acc].1, mapping[other].1))
+            {
+                return false;
+            }
+            other += 1;
+        }
+        acc += 1;
+    }
+
+    acc = 0;
+    while acc < 32 {
+        let name = s_out.result_context[acc];
+        if !string_compare(name, "")
+            && !mapping_contains_from(mapping, name)
+            && !params_contain_string(&s_in.starting_context, name)
+        {
+            return false;
+        }
+        acc += 1;
+    }
+
+    acc = 0;
+    while acc < 32 {
+        let name = s_in.starting_context[acc];
+        if !string_compare(name, "")
+            && !mapping_contains_to(mapping, name)
+            && (mapping_contains_from(mapping, name)
+                || !params_contain_string(&s_out.result_context, name))
+        {
+            return false;
+        }
+        acc += 1;
+    }
+    true
+}
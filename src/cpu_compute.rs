@@ -0,0 +1,2280 @@
+// Runs compute shaders on the cpu so that programs can be tested on machines without a gpu
+// The body of the shader is interpreted directly, which covers the part of glsl that small kernels use:
+// bool, int, uint and float scalars and vectors, buffer and uniform parameters, functions, if, for, while,
+// do while, break, continue and return, and the common built in functions
+// Matrices, structs, local arrays, shared memory and barriers are not supported
+// Invocations run one after another in order of their index, so reading an element that an earlier invocation wrote gives
+// the new value where the gpu makes no promise, shaders that are meant for both should only read what no other invocation writes
+use std::fmt;
+
+use crate::bind::{pipe_bindings, DefaultBinding};
+use crate::shared::{check_gl_builtin_type, process_body, Program, GLSLTYPE, QUALIFIER};
+use crate::wgpu_compute_header::{
+    compute_bindings, loop_length, out_buffer_size, ComputeBindings, ComputeShader,
+    OutComputeBindings,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Bool,
+    Int,
+    Uint,
+    Float,
+}
+
+// A scalar when size is 1, a vector of up to 4 components otherwise and void when it is 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Type {
+    kind: Kind,
+    size: usize,
+}
+
+const VOID: Type = Type {
+    kind: Kind::Bool,
+    size: 0,
+};
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = match self.kind {
+            Kind::Bool => "b",
+            Kind::Int => "i",
+            Kind::Uint => "u",
+            Kind::Float => "",
+        };
+        match (self.size, self.kind) {
+            (0, _) => write!(f, "void"),
+            (1, Kind::Bool) => write!(f, "bool"),
+            (1, Kind::Int) => write!(f, "int"),
+            (1, Kind::Uint) => write!(f, "uint"),
+            (1, Kind::Float) => write!(f, "float"),
+            (size, _) => write!(f, "{}vec{}", prefix, size),
+        }
+    }
+}
+
+fn type_named(name: &str) -> Option<Type> {
+    let scalar = |kind| Some(Type { kind, size: 1 });
+    match name {
+        "void" => Some(VOID),
+        "bool" => scalar(Kind::Bool),
+        "int" => scalar(Kind::Int),
+        "uint" => scalar(Kind::Uint),
+        "float" => scalar(Kind::Float),
+        _ => {
+            let (kind, rest) = if let Some(rest) = name.strip_prefix("vec") {
+                (Kind::Float, rest)
+            } else if let Some(rest) = name.strip_prefix("ivec") {
+                (Kind::Int, rest)
+            } else if let Some(rest) = name.strip_prefix("uvec") {
+                (Kind::Uint, rest)
+            } else if let Some(rest) = name.strip_prefix("bvec") {
+                (Kind::Bool, rest)
+            } else {
+                return None;
+            };
+            match rest {
+                "2" => Some(Type { kind, size: 2 }),
+                "3" => Some(Type { kind, size: 3 }),
+                "4" => Some(Type { kind, size: 4 }),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn is_unsupported_type(name: &str) -> bool {
+    name.starts_with("mat")
+        || name.starts_with("dmat")
+        || name.starts_with("dvec")
+        || name.starts_with("sampler")
+        || name.starts_with("texture")
+        || name.starts_with("image")
+        || name == "double"
+        || name == "struct"
+}
+
+// Every component is kept as the 32 bits it would have in a buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Value {
+    kind: Kind,
+    size: usize,
+    bits: [u32; 4],
+}
+
+impl Value {
+    fn zero(ty: Type) -> Value {
+        Value {
+            kind: ty.kind,
+            size: ty.size,
+            bits: [0; 4],
+        }
+    }
+
+    fn scalar(kind: Kind, bits: u32) -> Value {
+        Value {
+            kind,
+            size: 1,
+            bits: [bits, 0, 0, 0],
+        }
+    }
+
+    fn float(x: f32) -> Value {
+        Value::scalar(Kind::Float, x.to_bits())
+    }
+
+    fn int(x: i32) -> Value {
+        Value::scalar(Kind::Int, x as u32)
+    }
+
+    fn uint(x: u32) -> Value {
+        Value::scalar(Kind::Uint, x)
+    }
+
+    fn boolean(x: bool) -> Value {
+        Value::scalar(Kind::Bool, x as u32)
+    }
+
+    fn uvec3(x: u32, y: u32, z: u32) -> Value {
+        Value {
+            kind: Kind::Uint,
+            size: 3,
+            bits: [x, y, z, 0],
+        }
+    }
+
+    fn ty(&self) -> Type {
+        Type {
+            kind: self.kind,
+            size: self.size,
+        }
+    }
+
+    fn component(&self, i: usize) -> Value {
+        if i >= self.size {
+            panic!("Component {} of a {} doesn't exist", i, self.ty())
+        }
+        Value::scalar(self.kind, self.bits[i])
+    }
+
+    fn splat(&self, size: usize) -> Value {
+        if self.size == size {
+            return *self;
+        }
+        Value {
+            kind: self.kind,
+            size,
+            bits: [self.bits[0]; 4],
+        }
+    }
+
+    fn convert(&self, kind: Kind) -> Value {
+        let mut converted = *self;
+        converted.kind = kind;
+        for i in 0..self.size {
+            converted.bits[i] = convert_bits(self.bits[i], self.kind, kind);
+        }
+        converted
+    }
+
+    fn f32(&self, i: usize) -> f32 {
+        f32::from_bits(self.bits[i])
+    }
+
+    fn to_bool(&self, what: &str) -> bool {
+        if self.ty()
+            != (Type {
+                kind: Kind::Bool,
+                size: 1,
+            })
+        {
+            panic!("{} has to be a bool but it is a {}", what, self.ty())
+        }
+        self.bits[0] != 0
+    }
+
+    // A non negative integer used to index an array or vector
+    fn to_index(&self) -> usize {
+        match (self.kind, self.size) {
+            (Kind::Uint, 1) => self.bits[0] as usize,
+            (Kind::Int, 1) if (self.bits[0] as i32) >= 0 => self.bits[0] as usize,
+            (Kind::Int, 1) => panic!("Index {} is negative", self.bits[0] as i32),
+            _ => panic!(
+                "An index has to be an int or uint but it is a {}",
+                self.ty()
+            ),
+        }
+    }
+}
+
+// Any kind to any other like a constructor does, convert_to limits which of these happen implicitly
+fn convert_bits(bits: u32, from: Kind, to: Kind) -> u32 {
+    match (from, to) {
+        (Kind::Float, Kind::Float) => bits,
+        (Kind::Float, Kind::Int) => f32::from_bits(bits) as i32 as u32,
+        (Kind::Float, Kind::Uint) => f32::from_bits(bits) as u32,
+        (Kind::Float, Kind::Bool) => (f32::from_bits(bits) != 0.0) as u32,
+        (Kind::Int, Kind::Float) => (bits as i32 as f32).to_bits(),
+        (Kind::Uint, Kind::Float) => (bits as f32).to_bits(),
+        (Kind::Bool, Kind::Float) => (bits as f32).to_bits(),
+        (_, Kind::Bool) => (bits != 0) as u32,
+        // Int, uint and bool share their bits
+        _ => bits,
+    }
+}
+
+// The kind both sides of an operation are converted to, ints become uints and both become floats
+fn common_kind(a: Kind, b: Kind, op: &str) -> Kind {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Kind::Bool, _) | (_, Kind::Bool) => {
+            panic!("{} can't be used on a bool and a number", op)
+        }
+        (Kind::Float, _) | (_, Kind::Float) => Kind::Float,
+        _ => Kind::Uint,
+    }
+}
+
+// Values of the same size or a scalar and a vector, which is used for each of the components of the vector
+fn common_size(values: &[Value], op: &str) -> usize {
+    let size = values.iter().map(|v| v.size).max().unwrap_or(1);
+    for v in values {
+        if v.size != 1 && v.size != size {
+            panic!(
+                "{} can't be used on values of different sizes, {:?}",
+                op,
+                values
+                    .iter()
+                    .map(|v| v.ty().to_string())
+                    .collect::<Vec<_>>()
+            )
+        }
+    }
+    size
+}
+
+fn arithmetic(op: &str, a: Value, b: Value) -> Value {
+    let kind = common_kind(a.kind, b.kind, op);
+    let size = common_size(&[a, b], op);
+    let (a, b) = (a.convert(kind).splat(size), b.convert(kind).splat(size));
+    let mut result = Value::zero(Type { kind, size });
+    for i in 0..size {
+        let (x, y) = (a.bits[i], b.bits[i]);
+        result.bits[i] = match kind {
+            Kind::Float => {
+                let (x, y) = (f32::from_bits(x), f32::from_bits(y));
+                match op {
+                    "+" => x + y,
+                    "-" => x - y,
+                    "*" => x * y,
+                    "/" => x / y,
+                    _ => panic!("{} only works on integers", op),
+                }
+                .to_bits()
+            }
+            Kind::Int => {
+                let (x, y) = (x as i32, y as i32);
+                if (op == "/" || op == "%") && y == 0 {
+                    panic!("Integer division by zero")
+                }
+                (match op {
+                    "+" => x.wrapping_add(y),
+                    "-" => x.wrapping_sub(y),
+                    "*" => x.wrapping_mul(y),
+                    "/" => x.wrapping_div(y),
+                    "%" => x.wrapping_rem(y),
+                    "&" => x & y,
+                    "|" => x | y,
+                    "^" => x ^ y,
+                    "<<" => x.wrapping_shl(y as u32),
+                    ">>" => x.wrapping_shr(y as u32),
+                    _ => panic!("{} isn't an arithmetic operator", op),
+                }) as u32
+            }
+            Kind::Uint => {
+                if (op == "/" || op == "%") && y == 0 {
+                    panic!("Integer division by zero")
+                }
+                match op {
+                    "+" => x.wrapping_add(y),
+                    "-" => x.wrapping_sub(y),
+                    "*" => x.wrapping_mul(y),
+                    "/" => x / y,
+                    "%" => x % y,
+                    "&" => x & y,
+                    "|" => x | y,
+                    "^" => x ^ y,
+                    "<<" => x.wrapping_shl(y),
+                    ">>" => x.wrapping_shr(y),
+                    _ => panic!("{} isn't an arithmetic operator", op),
+                }
+            }
+            Kind::Bool => panic!("{} can't be used on bools", op),
+        }
+    }
+    result
+}
+
+fn compare(op: &str, a: Value, b: Value) -> Value {
+    let kind = common_kind(a.kind, b.kind, op);
+    let (a, b) = (a.convert(kind), b.convert(kind));
+    if op == "==" || op == "!=" {
+        if a.size != b.size {
+            panic!("A {} can't be compared with a {}", a.ty(), b.ty())
+        }
+        let equal = (0..a.size).all(|i| match kind {
+            Kind::Float => a.f32(i) == b.f32(i),
+            _ => a.bits[i] == b.bits[i],
+        });
+        return Value::boolean(equal == (op == "=="));
+    }
+    if a.size != 1 || b.size != 1 || kind == Kind::Bool {
+        panic!(
+            "{} only compares scalar numbers but was given a {} and a {}",
+            op,
+            a.ty(),
+            b.ty()
+        )
+    }
+    let ordering = match kind {
+        Kind::Float => a.f32(0).partial_cmp(&b.f32(0)),
+        Kind::Int => Some((a.bits[0] as i32).cmp(&(b.bits[0] as i32))),
+        _ => Some(a.bits[0].cmp(&b.bits[0])),
+    };
+    use std::cmp::Ordering::*;
+    Value::boolean(match (op, ordering) {
+        (_, None) => false,
+        ("<", Some(o)) => o == Less,
+        (">", Some(o)) => o == Greater,
+        ("<=", Some(o)) => o != Greater,
+        (">=", Some(o)) => o != Less,
+        _ => panic!("{} isn't a comparison", op),
+    })
+}
+
+// Assignments, arguments and return values only convert the way glsl does without being asked,
+// ints to uints and either of those to floats, anything else needs a constructor like int(x)
+fn convert_to(value: Value, ty: Type, what: &str) -> Value {
+    if value.size != ty.size {
+        panic!(
+            "A {} can't be used as {} which is a {}",
+            value.ty(),
+            what,
+            ty
+        )
+    }
+    match (value.kind, ty.kind) {
+        (a, b) if a == b => value,
+        (Kind::Int, Kind::Uint) | (Kind::Int, Kind::Float) | (Kind::Uint, Kind::Float) => {
+            value.convert(ty.kind)
+        }
+        _ => panic!(
+            "A {} can't be used as {} which is a {} without converting it with {}()",
+            value.ty(),
+            what,
+            ty,
+            ty
+        ),
+    }
+}
+
+// Apply a function to each component of some float values, scalars are used for every component
+fn map_float(name: &str, args: &[Value], f: impl Fn(&[f32]) -> f32) -> Value {
+    let size = common_size(args, name);
+    let args: Vec<Value> = args
+        .iter()
+        .map(|a| a.convert(Kind::Float).splat(size))
+        .collect();
+    let mut result = Value::zero(Type {
+        kind: Kind::Float,
+        size,
+    });
+    for i in 0..size {
+        let components: Vec<f32> = args.iter().map(|a| a.f32(i)).collect();
+        result.bits[i] = f(&components).to_bits();
+    }
+    result
+}
+
+// Apply a function that works on each kind of number to each component
+fn map_numeric(
+    name: &str,
+    args: &[Value],
+    int: impl Fn(&[i32]) -> i32,
+    uint: impl Fn(&[u32]) -> u32,
+    float: impl Fn(&[f32]) -> f32,
+) -> Value {
+    let kind = args
+        .iter()
+        .fold(args[0].kind, |k, a| common_kind(k, a.kind, name));
+    let size = common_size(args, name);
+    let args: Vec<Value> = args.iter().map(|a| a.convert(kind).splat(size)).collect();
+    let mut result = Value::zero(Type { kind, size });
+    for i in 0..size {
+        result.bits[i] = match kind {
+            Kind::Float => float(&args.iter().map(|a| a.f32(i)).collect::<Vec<_>>()).to_bits(),
+            Kind::Int => int(&args.iter().map(|a| a.bits[i] as i32).collect::<Vec<_>>()) as u32,
+            Kind::Uint => uint(&args.iter().map(|a| a.bits[i]).collect::<Vec<_>>()),
+            Kind::Bool => panic!("{} doesn't work on bools", name),
+        }
+    }
+    result
+}
+
+fn float_components(value: &Value) -> Vec<f32> {
+    let value = value.convert(Kind::Float);
+    (0..value.size).map(|i| value.f32(i)).collect()
+}
+
+fn float_vector(components: &[f32]) -> Value {
+    let mut result = Value::zero(Type {
+        kind: Kind::Float,
+        size: components.len(),
+    });
+    for (i, c) in components.iter().enumerate() {
+        result.bits[i] = c.to_bits();
+    }
+    result
+}
+
+fn builtin(name: &str, args: &[Value]) -> Value {
+    let float1 = |f: fn(f32) -> f32| map_float(name, args, |x| f(x[0]));
+    match (name, args.len()) {
+        ("abs", 1) => map_numeric(
+            name,
+            args,
+            |x| x[0].wrapping_abs(),
+            |x| x[0],
+            |x| x[0].abs(),
+        ),
+        ("sign", 1) => map_numeric(
+            name,
+            args,
+            |x| x[0].signum(),
+            |x| (x[0] > 0) as u32,
+            |x| if x[0] == 0.0 { 0.0 } else { x[0].signum() },
+        ),
+        ("min", 2) => map_numeric(
+            name,
+            args,
+            |x| x[0].min(x[1]),
+            |x| x[0].min(x[1]),
+            |x| x[0].min(x[1]),
+        ),
+        ("max", 2) => map_numeric(
+            name,
+            args,
+            |x| x[0].max(x[1]),
+            |x| x[0].max(x[1]),
+            |x| x[0].max(x[1]),
+        ),
+        ("clamp", 3) => map_numeric(
+            name,
+            args,
+            |x| x[0].max(x[1]).min(x[2]),
+            |x| x[0].max(x[1]).min(x[2]),
+            |x| x[0].max(x[1]).min(x[2]),
+        ),
+        ("floor", 1) => float1(f32::floor),
+        ("ceil", 1) => float1(f32::ceil),
+        ("round", 1) => float1(f32::round),
+        ("trunc", 1) => float1(f32::trunc),
+        ("fract", 1) => float1(|x| x - x.floor()),
+        ("sqrt", 1) => float1(f32::sqrt),
+        ("inversesqrt", 1) => float1(|x| 1.0 / x.sqrt()),
+        ("exp", 1) => float1(f32::exp),
+        ("exp2", 1) => float1(f32::exp2),
+        ("log", 1) => float1(f32::ln),
+        ("log2", 1) => float1(f32::log2),
+        ("sin", 1) => float1(f32::sin),
+        ("cos", 1) => float1(f32::cos),
+        ("tan", 1) => float1(f32::tan),
+        ("asin", 1) => float1(f32::asin),
+        ("acos", 1) => float1(f32::acos),
+        ("atan", 1) => float1(f32::atan),
+        ("radians", 1) => float1(f32::to_radians),
+        ("degrees", 1) => float1(f32::to_degrees),
+        ("atan", 2) => map_float(name, args, |x| x[0].atan2(x[1])),
+        ("pow", 2) => map_float(name, args, |x| x[0].powf(x[1])),
+        // The glsl definition, which unlike % takes the sign of y
+        ("mod", 2) => map_float(name, args, |x| x[0] - x[1] * (x[0] / x[1]).floor()),
+        ("step", 2) => map_float(name, args, |x| if x[1] < x[0] { 0.0 } else { 1.0 }),
+        ("mix", 3) => map_float(name, args, |x| x[0] * (1.0 - x[2]) + x[1] * x[2]),
+        ("smoothstep", 3) => map_float(name, args, |x| {
+            let t = ((x[2] - x[0]) / (x[1] - x[0])).max(0.0).min(1.0);
+            t * t * (3.0 - 2.0 * t)
+        }),
+        ("dot", 2) => {
+            common_size(args, name);
+            let (a, b) = (float_components(&args[0]), float_components(&args[1]));
+            Value::float(a.iter().zip(b.iter()).map(|(x, y)| x * y).sum())
+        }
+        ("length", 1) => {
+            let a = float_components(&args[0]);
+            Value::float(a.iter().map(|x| x * x).sum::<f32>().sqrt())
+        }
+        ("distance", 2) => {
+            let difference = arithmetic("-", args[0], args[1]);
+            builtin("length", &[difference])
+        }
+        ("normalize", 1) => {
+            let a = float_components(&args[0]);
+            let length = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+            float_vector(&a.iter().map(|x| x / length).collect::<Vec<_>>())
+        }
+        ("cross", 2) if args[0].size == 3 && args[1].size == 3 => {
+            let (a, b) = (float_components(&args[0]), float_components(&args[1]));
+            float_vector(&[
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ])
+        }
+        _ => panic!(
+            "{} with {} arguments isn't a function the cpu executor knows",
+            name,
+            args.len()
+        ),
+    }
+}
+
+// A scalar takes the first component of its argument, a vector is filled from a scalar
+// or from the components of all of its arguments in order
+fn construct(ty: Type, args: &[Value]) -> Value {
+    if args.is_empty() {
+        panic!("A {} can't be constructed from nothing", ty)
+    }
+    if ty.size == 1 {
+        return args[0].component(0).convert(ty.kind);
+    }
+    if args.len() == 1 && args[0].size == 1 {
+        return args[0].convert(ty.kind).splat(ty.size);
+    }
+    let components: Vec<Value> = args
+        .iter()
+        .flat_map(|a| (0..a.size).map(move |i| a.component(i).convert(ty.kind)))
+        .collect();
+    if components.len() < ty.size {
+        panic!(
+            "A {} needs {} components but was given {}",
+            ty,
+            ty.size,
+            components.len()
+        )
+    }
+    let mut result = Value::zero(ty);
+    for i in 0..ty.size {
+        result.bits[i] = components[i].bits[0];
+    }
+    result
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(Value),
+    Punct(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Number(value) if value.kind == Kind::Float => write!(f, "{}", value.f32(0)),
+            Token::Number(value) if value.kind == Kind::Int => {
+                write!(f, "{}", value.bits[0] as i32)
+            }
+            Token::Number(value) => write!(f, "{}u", value.bits[0]),
+            Token::Punct(p) => write!(f, "{}", p),
+        }
+    }
+}
+
+// Longer punctuation comes first so that it is matched before its prefixes
+const PUNCTUATION: [&str; 46] = [
+    "<<=", ">>=", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "==", "!=", "<=",
+    ">=", "&&", "||", "^^", "<<", ">>", "+", "-", "*", "/", "%", "=", "<", ">", "!", "&", "|", "^",
+    "~", "?", ":", ";", ",", ".", "(", ")", "{", "}", "[", "]", "#",
+];
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned().unwrap_or(' ');
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && next == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == '*' {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() || (c == '.' && next.is_ascii_digit()) {
+            let (value, end) = number(&chars, i);
+            tokens.push(Token::Number(value));
+            i = end;
+        } else {
+            let rest: String = chars[i..(i + 3).min(chars.len())].iter().collect();
+            match PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+                Some(p) => {
+                    tokens.push(Token::Punct(p));
+                    i += p.len();
+                }
+                None => panic!("The cpu executor found a character it doesn't know: {}", c),
+            }
+        }
+    }
+    tokens
+}
+
+fn number(chars: &[char], start: usize) -> (Value, usize) {
+    let mut i = start;
+    let text = |from: usize, to: usize| chars[from..to].iter().collect::<String>();
+
+    if chars[i] == '0' && (chars.get(i + 1) == Some(&'x') || chars.get(i + 1) == Some(&'X')) {
+        i += 2;
+        while i < chars.len() && chars[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+        let value = u32::from_str_radix(&text(start + 2, i), 16)
+            .unwrap_or_else(|_| panic!("{} is too big for 32 bits", text(start, i)));
+        return integer_suffix(chars, i, value);
+    }
+
+    let mut is_float = false;
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < chars.len() && chars[i] == '.' {
+        is_float = true;
+        i += 1;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+        let mut j = i + 1;
+        if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+            j += 1;
+        }
+        if j < chars.len() && chars[j].is_ascii_digit() {
+            is_float = true;
+            i = j;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+    }
+    let digits = text(start, i);
+
+    if is_float || (i < chars.len() && (chars[i] == 'f' || chars[i] == 'F')) {
+        let value: f32 = digits
+            .parse()
+            .unwrap_or_else(|_| panic!("{} is not a float the cpu executor can read", digits));
+        if i < chars.len() && (chars[i] == 'f' || chars[i] == 'F') {
+            i += 1;
+        } else if text(i, (i + 2).min(chars.len())) == "lf" {
+            i += 2;
+        }
+        return (Value::float(value), i);
+    }
+    let value: u32 = digits
+        .parse()
+        .unwrap_or_else(|_| panic!("{} is too big for 32 bits", digits));
+    integer_suffix(chars, i, value)
+}
+
+fn integer_suffix(chars: &[char], i: usize, value: u32) -> (Value, usize) {
+    if i < chars.len() && (chars[i] == 'u' || chars[i] == 'U') {
+        (Value::uint(value), i + 1)
+    } else {
+        (Value::int(value as i32), i)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Var(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    // An operator of = or one like += which is applied before assigning
+    Assign(&'static str, Box<Expr>, Box<Expr>),
+    IncDec {
+        op: &'static str,
+        prefix: bool,
+        target: Box<Expr>,
+    },
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    Swizzle(Box<Expr>, Vec<usize>),
+    // The number of elements of a buffer array
+    Length(Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Decl(Type, Vec<(String, Option<Expr>)>),
+    Expr(Expr),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    For(Option<Box<Stmt>>, Option<Expr>, Option<Expr>, Box<Stmt>),
+    While(Expr, Box<Stmt>),
+    DoWhile(Box<Stmt>, Expr),
+    Block(Vec<Stmt>),
+    Break,
+    Continue,
+    Return(Option<Expr>),
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    In,
+    Out,
+    InOut,
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    ret: Type,
+    name: String,
+    params: Vec<(Direction, Type, String)>,
+    body: Vec<Stmt>,
+}
+
+// Qualifiers that don't change what the cpu executor does
+const IGNORED_QUALIFIERS: [&str; 6] = ["const", "highp", "mediump", "lowp", "precise", "in"];
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> ! {
+        let from = self.pos.saturating_sub(8);
+        let to = (self.pos + 8).min(self.tokens.len());
+        let near: Vec<String> = self.tokens[from..to]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        panic!(
+            "The cpu executor could not read the shader, {} near: {}",
+            message,
+            near.join(" ")
+        )
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        match self.peek() {
+            Some(Token::Punct(x)) => *x == p,
+            _ => false,
+        }
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(x)) => x == name,
+            _ => false,
+        }
+    }
+
+    fn eat_punct(&mut self, p: &str) -> bool {
+        if self.is_punct(p) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, name: &str) -> bool {
+        if self.is_ident(name) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, p: &str) {
+        if !self.eat_punct(p) {
+            self.error(&format!("expected {}", p))
+        }
+    }
+
+    fn expect_ident(&mut self) -> String {
+        match self.peek().cloned() {
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                name
+            }
+            _ => self.error("expected a name"),
+        }
+    }
+
+    fn skip_qualifiers(&mut self) {
+        while let Some(Token::Ident(name)) = self.peek() {
+            if IGNORED_QUALIFIERS.contains(&name.as_str()) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_type(&mut self) -> Type {
+        let name = self.expect_ident();
+        match type_named(&name) {
+            Some(ty) => ty,
+            None if is_unsupported_type(&name) => {
+                self.error(&format!("{} isn't supported by the cpu executor", name))
+            }
+            None => self.error(&format!("{} is not a type", name)),
+        }
+    }
+
+    // A declaration starts with qualifiers or a type followed by a name
+    fn at_declaration(&self) -> bool {
+        match (self.peek(), self.peek_at(1)) {
+            (Some(Token::Ident(name)), _) if IGNORED_QUALIFIERS.contains(&name.as_str()) => true,
+            (Some(Token::Ident(name)), Some(Token::Ident(_))) => {
+                type_named(name).is_some() || is_unsupported_type(name)
+            }
+            _ => false,
+        }
+    }
+
+    fn program(&mut self) -> (Vec<Function>, Vec<Stmt>) {
+        let mut functions = Vec::new();
+        let mut globals = Vec::new();
+        while self.peek().is_some() {
+            if self.is_punct("#") {
+                self.error("the preprocessor isn't supported by the cpu executor")
+            }
+            if self.eat_punct(";") {
+                continue;
+            }
+            self.skip_qualifiers();
+            let ty = self.parse_type();
+            let name = self.expect_ident();
+            if self.eat_punct("(") {
+                let params = self.params();
+                if self.eat_punct(";") {
+                    continue;
+                }
+                let body = match self.block() {
+                    Stmt::Block(body) => body,
+                    _ => unreachable!(),
+                };
+                functions.push(Function {
+                    ret: ty,
+                    name,
+                    params,
+                    body,
+                });
+            } else {
+                globals.push(self.declarators(ty, name));
+            }
+        }
+        (functions, globals)
+    }
+
+    fn params(&mut self) -> Vec<(Direction, Type, String)> {
+        let mut params = Vec::new();
+        if self.is_ident("void") && self.peek_at(1) == Some(&Token::Punct(")")) {
+            self.pos += 1;
+        }
+        while !self.eat_punct(")") {
+            if !params.is_empty() {
+                self.expect_punct(",");
+            }
+            let mut direction = Direction::In;
+            loop {
+                if self.eat_ident("out") {
+                    direction = Direction::Out;
+                } else if self.eat_ident("inout") {
+                    direction = Direction::InOut;
+                } else if let Some(Token::Ident(name)) = self.peek() {
+                    if IGNORED_QUALIFIERS.contains(&name.as_str()) {
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let ty = self.parse_type();
+            let name = self.expect_ident();
+            params.push((direction, ty, name));
+        }
+        params
+    }
+
+    // The names and values of a declaration after its type and first name, up to the ;
+    fn declarators(&mut self, ty: Type, first: String) -> Stmt {
+        let mut names = Vec::new();
+        let mut name = first;
+        loop {
+            if self.is_punct("[") {
+                self.error("arrays other than buffers aren't supported by the cpu executor")
+            }
+            let value = if self.eat_punct("=") {
+                Some(self.assignment())
+            } else {
+                None
+            };
+            names.push((name, value));
+            if self.eat_punct(",") {
+                name = self.expect_ident();
+            } else {
+                break;
+            }
+        }
+        self.expect_punct(";");
+        Stmt::Decl(ty, names)
+    }
+
+    fn block(&mut self) -> Stmt {
+        self.expect_punct("{");
+        let mut statements = Vec::new();
+        while !self.eat_punct("}") {
+            if self.peek().is_none() {
+                self.error("expected }")
+            }
+            statements.push(self.statement());
+        }
+        Stmt::Block(statements)
+    }
+
+    fn statement(&mut self) -> Stmt {
+        if self.is_punct("{") {
+            return self.block();
+        }
+        if self.eat_punct(";") {
+            return Stmt::Empty;
+        }
+        if self.eat_ident("if") {
+            self.expect_punct("(");
+            let condition = self.expression();
+            self.expect_punct(")");
+            let then = Box::new(self.statement());
+            let otherwise = if self.eat_ident("else") {
+                Some(Box::new(self.statement()))
+            } else {
+                None
+            };
+            return Stmt::If(condition, then, otherwise);
+        }
+        if self.eat_ident("for") {
+            self.expect_punct("(");
+            let init = if self.eat_punct(";") {
+                None
+            } else if self.at_declaration() {
+                Some(Box::new(self.declaration()))
+            } else {
+                let e = self.expression();
+                self.expect_punct(";");
+                Some(Box::new(Stmt::Expr(e)))
+            };
+            let condition = if self.is_punct(";") {
+                None
+            } else {
+                Some(self.expression())
+            };
+            self.expect_punct(";");
+            let step = if self.is_punct(")") {
+                None
+            } else {
+                Some(self.expression())
+            };
+            self.expect_punct(")");
+            return Stmt::For(init, condition, step, Box::new(self.statement()));
+        }
+        if self.eat_ident("while") {
+            self.expect_punct("(");
+            let condition = self.expression();
+            self.expect_punct(")");
+            return Stmt::While(condition, Box::new(self.statement()));
+        }
+        if self.eat_ident("do") {
+            let body = Box::new(self.statement());
+            if !self.eat_ident("while") {
+                self.error("expected while")
+            }
+            self.expect_punct("(");
+            let condition = self.expression();
+            self.expect_punct(")");
+            self.expect_punct(";");
+            return Stmt::DoWhile(body, condition);
+        }
+        if self.eat_ident("break") {
+            self.expect_punct(";");
+            return Stmt::Break;
+        }
+        if self.eat_ident("continue") {
+            self.expect_punct(";");
+            return Stmt::Continue;
+        }
+        if self.eat_ident("return") {
+            let value = if self.is_punct(";") {
+                None
+            } else {
+                Some(self.expression())
+            };
+            self.expect_punct(";");
+            return Stmt::Return(value);
+        }
+        if self.is_ident("switch") || self.is_ident("barrier") || self.is_ident("discard") {
+            self.error("this isn't supported by the cpu executor")
+        }
+        if self.at_declaration() {
+            return self.declaration();
+        }
+        let e = self.expression();
+        self.expect_punct(";");
+        Stmt::Expr(e)
+    }
+
+    fn declaration(&mut self) -> Stmt {
+        self.skip_qualifiers();
+        let ty = self.parse_type();
+        let name = self.expect_ident();
+        self.declarators(ty, name)
+    }
+
+    fn expression(&mut self) -> Expr {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Expr {
+        let target = self.ternary();
+        const ASSIGNMENTS: [&str; 11] = [
+            "=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>=",
+        ];
+        if let Some(Token::Punct(p)) = self.peek() {
+            if let Some(op) = ASSIGNMENTS.iter().find(|a| *a == p) {
+                self.pos += 1;
+                let value = self.assignment();
+                return Expr::Assign(op, Box::new(target), Box::new(value));
+            }
+        }
+        target
+    }
+
+    fn ternary(&mut self) -> Expr {
+        let condition = self.binary(1);
+        if self.eat_punct("?") {
+            let a = self.assignment();
+            self.expect_punct(":");
+            let b = self.assignment();
+            return Expr::Ternary(Box::new(condition), Box::new(a), Box::new(b));
+        }
+        condition
+    }
+
+    fn precedence(op: &str) -> Option<u8> {
+        Some(match op {
+            "||" => 1,
+            "^^" => 2,
+            "&&" => 3,
+            "|" => 4,
+            "^" => 5,
+            "&" => 6,
+            "==" | "!=" => 7,
+            "<" | ">" | "<=" | ">=" => 8,
+            "<<" | ">>" => 9,
+            "+" | "-" => 10,
+            "*" | "/" | "%" => 11,
+            _ => return None,
+        })
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Expr {
+        let mut left = self.unary();
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct(p)) => *p,
+                _ => break,
+            };
+            let precedence = match Parser::precedence(op) {
+                Some(p) if p >= min_precedence => p,
+                _ => break,
+            };
+            self.pos += 1;
+            let right = self.binary(precedence + 1);
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        left
+    }
+
+    fn unary(&mut self) -> Expr {
+        for op in ["-", "!", "~"].iter() {
+            if self.eat_punct(op) {
+                return Expr::Unary(op, Box::new(self.unary()));
+            }
+        }
+        if self.eat_punct("+") {
+            return self.unary();
+        }
+        for op in ["++", "--"].iter() {
+            if self.eat_punct(op) {
+                return Expr::IncDec {
+                    op,
+                    prefix: true,
+                    target: Box::new(self.unary()),
+                };
+            }
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Expr {
+        let mut e = self.primary();
+        loop {
+            if self.eat_punct("[") {
+                let index = self.expression();
+                self.expect_punct("]");
+                e = Expr::Index(Box::new(e), Box::new(index));
+            } else if self.eat_punct(".") {
+                let field = self.expect_ident();
+                if field == "length" && self.eat_punct("(") {
+                    self.expect_punct(")");
+                    e = Expr::Length(Box::new(e));
+                } else {
+                    e = Expr::Swizzle(Box::new(e), self.swizzle(&field));
+                }
+            } else if self.is_punct("++") || self.is_punct("--") {
+                let op = if self.eat_punct("++") {
+                    "++"
+                } else {
+                    self.expect_punct("--");
+                    "--"
+                };
+                e = Expr::IncDec {
+                    op,
+                    prefix: false,
+                    target: Box::new(e),
+                };
+            } else {
+                return e;
+            }
+        }
+    }
+
+    fn swizzle(&self, field: &str) -> Vec<usize> {
+        if field.len() > 4 {
+            self.error(&format!("{} has too many components", field))
+        }
+        field
+            .chars()
+            .map(|c| match c {
+                'x' | 'r' | 's' => 0,
+                'y' | 'g' | 't' => 1,
+                'z' | 'b' | 'p' => 2,
+                'w' | 'a' | 'q' => 3,
+                _ => self.error(&format!("{} is not a swizzle", field)),
+            })
+            .collect()
+    }
+
+    fn primary(&mut self) -> Expr {
+        match self.peek().cloned() {
+            Some(Token::Number(value)) => {
+                self.pos += 1;
+                Expr::Literal(value)
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                match name.as_str() {
+                    "true" => return Expr::Literal(Value::boolean(true)),
+                    "false" => return Expr::Literal(Value::boolean(false)),
+                    _ => {}
+                }
+                if self.eat_punct("(") {
+                    let mut args = Vec::new();
+                    while !self.eat_punct(")") {
+                        if !args.is_empty() {
+                            self.expect_punct(",");
+                        }
+                        args.push(self.assignment());
+                    }
+                    Expr::Call(name, args)
+                } else {
+                    Expr::Var(name)
+                }
+            }
+            Some(Token::Punct("(")) => {
+                self.pos += 1;
+                let e = self.expression();
+                self.expect_punct(")");
+                e
+            }
+            _ => self.error("expected a value"),
+        }
+    }
+}
+
+// A compute shader read into a form the cpu can run, along with bindings that keep their data in host memory
+#[derive(Debug)]
+pub struct CpuProgram {
+    functions: Vec<Function>,
+    // Variables declared outside of any function, every invocation starts with their initial values
+    globals: Vec<Stmt>,
+}
+
+impl Program for CpuProgram {
+    fn get_device(&self) -> &wgpu::Device {
+        panic!("A program run on the cpu has no device, only data can be bound to it")
+    }
+    fn get_queue(&self) -> &wgpu::Queue {
+        panic!("A program run on the cpu has no queue, only data can be bound to it")
+    }
+    fn on_cpu(&self) -> bool {
+        true
+    }
+}
+
+// The stand in for compile, the bindings it gives back are used like the ones from compile
+pub fn compile_cpu(compute: &ComputeShader) -> (CpuProgram, ComputeBindings, OutComputeBindings) {
+    for p in compute.params.iter() {
+        if check_gl_builtin_type(p.name, &p.gtype) {
+            continue;
+        }
+        match p.gtype {
+            GLSLTYPE::Mat4 | GLSLTYPE::Sampler | GLSLTYPE::TextureCube | GLSLTYPE::Texture2D => {
+                panic!(
+                    "{} is a {} which isn't supported by the cpu executor",
+                    p.name, p.gtype
+                )
+            }
+            // The gpu puts the elements of a vec3[] 16 bytes apart but they are bound 12 bytes apart
+            GLSLTYPE::ArrayVec3 => panic!(
+                "{} is a vec3[] whose layout differs between the gpu and the cpu executor, use a vec4[] or a float[] instead",
+                p.name
+            ),
+            _ => {}
+        }
+    }
+
+    let mut parser = Parser {
+        tokens: tokenize(&process_body(compute.body)),
+        pos: 0,
+    };
+    let (functions, globals) = parser.program();
+    if !functions
+        .iter()
+        .any(|f| f.name == "main" && f.params.is_empty())
+    {
+        panic!("The shader needs a void main() to be run on the cpu")
+    }
+
+    let (bindings, out_bindings) = compute_bindings(compute);
+    (CpuProgram { functions, globals }, bindings, out_bindings)
+}
+
+// The bytes of a parameter while the program runs, laid out the way the gpu reads them
+struct Buffer {
+    name: String,
+    element: Type,
+    // Bytes from the start of one element of an array to the next
+    stride: usize,
+    array: bool,
+    uniform: bool,
+    bytes: Vec<u8>,
+}
+
+impl Buffer {
+    fn new(binding: &mut DefaultBinding) -> Buffer {
+        let bytes = binding.host.take().unwrap_or_else(|| {
+            panic!(
+                "{} has to be bound to a program compiled with compile_cpu before it is run",
+                binding.name
+            )
+        });
+        let element_type = binding.gtype.element_type();
+        let element = match element_type {
+            GLSLTYPE::Bool => Type {
+                kind: Kind::Bool,
+                size: 1,
+            },
+            GLSLTYPE::Int => Type {
+                kind: Kind::Int,
+                size: 1,
+            },
+            GLSLTYPE::Uint => Type {
+                kind: Kind::Uint,
+                size: 1,
+            },
+            GLSLTYPE::Float => Type {
+                kind: Kind::Float,
+                size: 1,
+            },
+            GLSLTYPE::Vec2 => Type {
+                kind: Kind::Float,
+                size: 2,
+            },
+            GLSLTYPE::Vec3 => Type {
+                kind: Kind::Float,
+                size: 3,
+            },
+            GLSLTYPE::Uvec3 => Type {
+                kind: Kind::Uint,
+                size: 3,
+            },
+            GLSLTYPE::Vec4 => Type {
+                kind: Kind::Float,
+                size: 4,
+            },
+            other => panic!("{} isn't supported by the cpu executor", other),
+        };
+        let uniform = binding.qual.contains(&QUALIFIER::UNIFORM);
+        // Elements are packed one after another the way they are bound and read back, which matches std430 since compile_cpu refuses vec3[]
+        let stride = 4 * element.size;
+        Buffer {
+            name: binding.name.clone(),
+            element,
+            stride,
+            array: binding.gtype.is_array(),
+            uniform,
+            bytes,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len() / self.stride
+    }
+
+    fn offset(&self, index: usize) -> usize {
+        if index >= self.len() {
+            panic!(
+                "{}[{}] is out of bounds, {} only has room for {} elements",
+                self.name,
+                index,
+                self.name,
+                self.len()
+            )
+        }
+        index * self.stride
+    }
+
+    fn read(&self, index: usize) -> Value {
+        let offset = self.offset(index);
+        let mut value = Value::zero(self.element);
+        for i in 0..self.element.size {
+            let start = offset + 4 * i;
+            let mut word = [0; 4];
+            word.copy_from_slice(&self.bytes[start..start + 4]);
+            value.bits[i] = u32::from_ne_bytes(word);
+        }
+        value
+    }
+
+    fn write(&mut self, index: usize, value: Value) {
+        if self.uniform {
+            panic!("{} is a uniform and can't be written to", self.name)
+        }
+        let offset = self.offset(index);
+        for i in 0..self.element.size {
+            let start = offset + 4 * i;
+            self.bytes[start..start + 4].copy_from_slice(&value.bits[i].to_ne_bytes());
+        }
+    }
+}
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+enum Root {
+    Variable(String),
+    Element(usize, usize),
+}
+
+// Something that can be assigned to, the components select part of a vector
+struct Place {
+    root: Root,
+    components: Vec<usize>,
+}
+
+type Scope = Vec<(String, Value)>;
+
+struct Invocation<'a> {
+    program: &'a CpuProgram,
+    buffers: &'a mut Vec<Buffer>,
+    // The built in variables and the variables declared outside of functions
+    globals: Scope,
+    // The scopes of every function that is being called, innermost last
+    frames: Vec<Vec<Scope>>,
+}
+
+impl<'a> Invocation<'a> {
+    fn new(
+        program: &'a CpuProgram,
+        buffers: &'a mut Vec<Buffer>,
+        index: u32,
+        length: u32,
+    ) -> Invocation<'a> {
+        let globals = vec![
+            (
+                "gl_GlobalInvocationID".to_string(),
+                Value::uvec3(index, 0, 0),
+            ),
+            ("gl_WorkGroupID".to_string(), Value::uvec3(index, 0, 0)),
+            ("gl_LocalInvocationID".to_string(), Value::uvec3(0, 0, 0)),
+            ("gl_NumWorkGroups".to_string(), Value::uvec3(length, 1, 1)),
+            ("gl_WorkGroupSize".to_string(), Value::uvec3(1, 1, 1)),
+            ("gl_LocalInvocationIndex".to_string(), Value::uint(0)),
+        ];
+        Invocation {
+            program,
+            buffers,
+            globals,
+            frames: vec![vec![Vec::new()]],
+        }
+    }
+
+    fn run(&mut self) {
+        let program = self.program;
+        for g in program.globals.iter() {
+            self.exec(g);
+        }
+        let declared = self.frames[0].pop().unwrap();
+        self.globals.extend(declared);
+        self.frames[0].push(Vec::new());
+        self.call("main", &[]);
+    }
+
+    fn variable(&mut self, name: &str) -> Option<&mut Value> {
+        let frame = self.frames.last_mut().unwrap();
+        for scope in frame.iter_mut().rev() {
+            if let Some((_, v)) = scope.iter_mut().rev().find(|(n, _)| n == name) {
+                return Some(v);
+            }
+        }
+        self.globals
+            .iter_mut()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        self.frames
+            .last_mut()
+            .unwrap()
+            .last_mut()
+            .unwrap()
+            .push((name.to_string(), value));
+    }
+
+    fn buffer(&self, name: &str) -> Option<usize> {
+        self.buffers.iter().position(|b| b.name == name)
+    }
+
+    // A buffer array named by an expression, unless a variable hides it
+    fn array(&mut self, e: &Expr) -> Option<usize> {
+        match e {
+            Expr::Var(name) if self.variable(name).is_none() => {
+                self.buffer(name).filter(|b| self.buffers[*b].array)
+            }
+            _ => None,
+        }
+    }
+
+    fn place(&mut self, e: &Expr) -> Place {
+        match e {
+            Expr::Var(name) => {
+                if self.variable(name).is_some() {
+                    return Place {
+                        root: Root::Variable(name.clone()),
+                        components: Vec::new(),
+                    };
+                }
+                match self.buffer(name) {
+                    Some(b) if self.buffers[b].array => {
+                        panic!(
+                            "{} is an array and can only be assigned one element at a time",
+                            name
+                        )
+                    }
+                    Some(b) => Place {
+                        root: Root::Element(b, 0),
+                        components: Vec::new(),
+                    },
+                    None => panic!("{} is not a variable or parameter", name),
+                }
+            }
+            Expr::Index(base, index) => {
+                if let Some(b) = self.array(base) {
+                    let index = self.eval(index).to_index();
+                    return Place {
+                        root: Root::Element(b, index),
+                        components: Vec::new(),
+                    };
+                }
+                let mut place = self.place(base);
+                let index = self.eval(index).to_index();
+                place.components = select(&place.components, &[index], self.read(&place).size);
+                place
+            }
+            Expr::Swizzle(base, components) => {
+                let mut place = self.place(base);
+                place.components = select(&place.components, components, self.read(&place).size);
+                place
+            }
+            _ => panic!("{:?} can't be assigned to", e),
+        }
+    }
+
+    fn read_root(&mut self, root: &Root) -> Value {
+        match root {
+            Root::Variable(name) => *self.variable(name).unwrap(),
+            Root::Element(b, index) => self.buffers[*b].read(*index),
+        }
+    }
+
+    fn read(&mut self, place: &Place) -> Value {
+        let value = self.read_root(&place.root);
+        if place.components.is_empty() {
+            return value;
+        }
+        let mut selected = Value::zero(Type {
+            kind: value.kind,
+            size: place.components.len(),
+        });
+        for (i, c) in place.components.iter().enumerate() {
+            selected.bits[i] = value.bits[*c];
+        }
+        selected
+    }
+
+    fn write(&mut self, place: &Place, value: Value) -> Value {
+        let value = convert_to(value, self.read(place).ty(), "the target of an assignment");
+        let mut whole = self.read_root(&place.root);
+        if place.components.is_empty() {
+            whole = value;
+        } else {
+            for (i, c) in place.components.iter().enumerate() {
+                whole.bits[*c] = value.bits[i];
+            }
+        }
+        match &place.root {
+            Root::Variable(name) => *self.variable(name).unwrap() = whole,
+            Root::Element(b, index) => self.buffers[*b].write(*index, whole),
+        }
+        value
+    }
+
+    fn eval(&mut self, e: &Expr) -> Value {
+        match e {
+            Expr::Literal(value) => *value,
+            Expr::Var(name) => {
+                if let Some(value) = self.variable(name) {
+                    return *value;
+                }
+                match self.buffer(name) {
+                    Some(b) if self.buffers[b].array => {
+                        panic!("{} is an array and has to be indexed", name)
+                    }
+                    Some(b) => self.buffers[b].read(0),
+                    None => panic!("{} is not a variable or parameter", name),
+                }
+            }
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand);
+                match *op {
+                    "!" => Value::boolean(!value.to_bool("The operand of !")),
+                    "-" => arithmetic("-", Value::zero(value.ty()), value),
+                    _ => {
+                        let ones = Value {
+                            bits: [std::u32::MAX; 4],
+                            ..value
+                        };
+                        arithmetic("^", value, ones)
+                    }
+                }
+            }
+            Expr::Binary(op, a, b) => match *op {
+                "&&" => Value::boolean(
+                    self.eval(a).to_bool("The left side of &&")
+                        && self.eval(b).to_bool("The right side of &&"),
+                ),
+                "||" => Value::boolean(
+                    self.eval(a).to_bool("The left side of ||")
+                        || self.eval(b).to_bool("The right side of ||"),
+                ),
+                "^^" => Value::boolean(
+                    self.eval(a).to_bool("The left side of ^^")
+                        != self.eval(b).to_bool("The right side of ^^"),
+                ),
+                "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                    let (a, b) = (self.eval(a), self.eval(b));
+                    compare(op, a, b)
+                }
+                _ => {
+                    let (a, b) = (self.eval(a), self.eval(b));
+                    arithmetic(op, a, b)
+                }
+            },
+            Expr::Assign(op, target, value) => {
+                let place = self.place(target);
+                let value = self.eval(value);
+                let value = if *op == "=" {
+                    value
+                } else {
+                    let current = self.read(&place);
+                    arithmetic(op.trim_end_matches('='), current, value)
+                };
+                self.write(&place, value)
+            }
+            Expr::IncDec { op, prefix, target } => {
+                let place = self.place(target);
+                let old = self.read(&place);
+                let one = Value::scalar(Kind::Int, 1).convert(old.kind);
+                let new = arithmetic(&op[..1], old, one);
+                let new = self.write(&place, new);
+                if *prefix {
+                    new
+                } else {
+                    old
+                }
+            }
+            Expr::Ternary(condition, a, b) => {
+                if self.eval(condition).to_bool("The condition of ?:") {
+                    self.eval(a)
+                } else {
+                    self.eval(b)
+                }
+            }
+            Expr::Call(name, args) => self.call(name, args),
+            Expr::Index(base, index) => {
+                if let Some(b) = self.array(base) {
+                    let index = self.eval(index).to_index();
+                    return self.buffers[b].read(index);
+                }
+                let value = self.eval(base);
+                let index = self.eval(index).to_index();
+                value.component(index)
+            }
+            Expr::Swizzle(base, components) => {
+                let value = self.eval(base);
+                let mut selected = Value::zero(Type {
+                    kind: value.kind,
+                    size: components.len(),
+                });
+                for (i, c) in components.iter().enumerate() {
+                    selected.bits[i] = value.component(*c).bits[0];
+                }
+                selected
+            }
+            Expr::Length(base) => match self.array(base) {
+                Some(b) => Value::int(self.buffers[b].len() as i32),
+                None => panic!("Only buffer arrays have a length"),
+            },
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr]) -> Value {
+        let program = self.program;
+        if let Some(f) = program
+            .functions
+            .iter()
+            .find(|f| f.name == name && f.params.len() == args.len())
+        {
+            let mut scope = Vec::new();
+            let mut outs = Vec::new();
+            for ((direction, ty, param), arg) in f.params.iter().zip(args.iter()) {
+                let value = match direction {
+                    Direction::In => {
+                        let value = self.eval(arg);
+                        convert_to(value, *ty, param)
+                    }
+                    Direction::Out => {
+                        outs.push((self.place(arg), param.clone()));
+                        Value::zero(*ty)
+                    }
+                    Direction::InOut => {
+                        let place = self.place(arg);
+                        let value = convert_to(self.read(&place), *ty, param);
+                        outs.push((place, param.clone()));
+                        value
+                    }
+                };
+                scope.push((param.clone(), value));
+            }
+
+            self.frames.push(vec![scope]);
+            let mut flow = Flow::Next;
+            for s in f.body.iter() {
+                flow = self.exec(s);
+                if let Flow::Return(_) = flow {
+                    break;
+                }
+            }
+            let frame = self.frames.pop().unwrap();
+
+            for (place, param) in outs {
+                let value = frame[0].iter().find(|(n, _)| *n == param).unwrap().1;
+                self.write(&place, value);
+            }
+            return match flow {
+                Flow::Return(Some(value)) => convert_to(value, f.ret, "the return value"),
+                _ if f.ret == VOID => Value::zero(VOID),
+                _ => panic!("{} ended without returning a {}", name, f.ret),
+            };
+        }
+
+        let values: Vec<Value> = args.iter().map(|a| self.eval(a)).collect();
+        match type_named(name) {
+            Some(ty) if ty != VOID => construct(ty, &values),
+            _ => builtin(name, &values),
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self) -> Flow) -> Flow {
+        self.frames.last_mut().unwrap().push(Vec::new());
+        let flow = f(self);
+        self.frames.last_mut().unwrap().pop();
+        flow
+    }
+
+    fn condition(&mut self, e: &Expr, what: &str) -> bool {
+        self.eval(e).to_bool(what)
+    }
+
+    fn exec(&mut self, s: &Stmt) -> Flow {
+        match s {
+            Stmt::Decl(ty, names) => {
+                for (name, value) in names.iter() {
+                    let value = match value {
+                        Some(e) => {
+                            let value = self.eval(e);
+                            convert_to(value, *ty, name)
+                        }
+                        None => Value::zero(*ty),
+                    };
+                    self.declare(name, value);
+                }
+                Flow::Next
+            }
+            Stmt::Expr(e) => {
+                self.eval(e);
+                Flow::Next
+            }
+            Stmt::If(condition, then, otherwise) => {
+                if self.condition(condition, "The condition of an if") {
+                    self.scoped(|s| s.exec(then))
+                } else if let Some(otherwise) = otherwise {
+                    self.scoped(|s| s.exec(otherwise))
+                } else {
+                    Flow::Next
+                }
+            }
+            Stmt::For(init, condition, step, body) => self.scoped(|s| {
+                if let Some(init) = init {
+                    s.exec(init);
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        if !s.condition(condition, "The condition of a for loop") {
+                            return Flow::Next;
+                        }
+                    }
+                    match s.scoped(|s| s.exec(body)) {
+                        Flow::Break => return Flow::Next,
+                        Flow::Return(value) => return Flow::Return(value),
+                        _ => {}
+                    }
+                    if let Some(step) = step {
+                        s.eval(step);
+                    }
+                }
+            }),
+            Stmt::While(condition, body) => loop {
+                if !self.condition(condition, "The condition of a while loop") {
+                    return Flow::Next;
+                }
+                match self.scoped(|s| s.exec(body)) {
+                    Flow::Break => return Flow::Next,
+                    Flow::Return(value) => return Flow::Return(value),
+                    _ => {}
+                }
+            },
+            Stmt::DoWhile(body, condition) => loop {
+                match self.scoped(|s| s.exec(body)) {
+                    Flow::Break => return Flow::Next,
+                    Flow::Return(value) => return Flow::Return(value),
+                    _ => {}
+                }
+                if !self.condition(condition, "The condition of a do while loop") {
+                    return Flow::Next;
+                }
+            },
+            Stmt::Block(statements) => self.scoped(|s| {
+                for statement in statements.iter() {
+                    match s.exec(statement) {
+                        Flow::Next => {}
+                        flow => return flow,
+                    }
+                }
+                Flow::Next
+            }),
+            Stmt::Break => Flow::Break,
+            Stmt::Continue => Flow::Continue,
+            Stmt::Return(value) => Flow::Return(value.as_ref().map(|e| self.eval(e))),
+            Stmt::Empty => Flow::Next,
+        }
+    }
+}
+
+// Pick components out of those already selected, or out of the whole vector when none are
+fn select(selected: &[usize], picks: &[usize], size: usize) -> Vec<usize> {
+    picks
+        .iter()
+        .map(|p| {
+            if *p >= size {
+                panic!(
+                    "Component {} of a vector with {} components doesn't exist",
+                    p, size
+                )
+            }
+            if selected.is_empty() {
+                *p
+            } else {
+                selected[*p]
+            }
+        })
+        .collect()
+}
+
+// The stand in for run, every invocation is run one after another
+// Out buffers are created the same size as they are on the gpu and the results are read with read_uvec and the others
+pub fn run_cpu(
+    program: &CpuProgram,
+    bindings: &mut ComputeBindings,
+    mut out_bindings: OutComputeBindings,
+) -> Vec<DefaultBinding> {
+    let length = loop_length(bindings, &out_bindings);
+
+    for b in out_bindings.bindings.iter_mut() {
+        if !b.qual.contains(&QUALIFIER::IN) {
+            let size = out_buffer_size(&b.gtype, length);
+            b.host = Some(vec![0; size as usize]);
+            b.length = Some(length);
+            b.size = Some(size);
+        }
+    }
+
+    let mut buffers: Vec<Buffer> = bindings
+        .bindings
+        .iter_mut()
+        .chain(out_bindings.bindings.iter_mut())
+        .map(Buffer::new)
+        .collect();
+
+    for index in 0..length {
+        Invocation::new(program, &mut buffers, index as u32, length as u32).run();
+    }
+
+    for (b, buffer) in bindings
+        .bindings
+        .iter_mut()
+        .chain(out_bindings.bindings.iter_mut())
+        .zip(buffers.into_iter())
+    {
+        b.host = Some(buffer.bytes);
+    }
+
+    out_bindings.bindings
+}
+
+// The stand in for pipe
+pub fn pipe_cpu(
+    program: &CpuProgram,
+    mut in_bindings: ComputeBindings,
+    mut out_bindings: OutComputeBindings,
+    result_vec: Vec<DefaultBinding>,
+    mapping: &[(&str, &str)],
+) -> Vec<DefaultBinding> {
+    pipe_bindings(&mut in_bindings, &mut out_bindings, result_vec, mapping);
+
+    run_cpu(program, &mut in_bindings, out_bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bind::Bindable;
+    use crate::shared::PARAMETER;
+    use crate::wgpu_compute_header::{read_fvec, read_uvec};
+    use futures::executor::block_on;
+
+    const IN_OUT: &[QUALIFIER] = &[
+        QUALIFIER::BUFFER,
+        QUALIFIER::LOOP,
+        QUALIFIER::IN,
+        QUALIFIER::OUT,
+    ];
+
+    const UINTS: &[PARAMETER] = &[PARAMETER {
+        qual: IN_OUT,
+        gtype: GLSLTYPE::ArrayUint,
+        name: "data",
+        frames: &[],
+    }];
+
+    const FLOATS: &[PARAMETER] = &[PARAMETER {
+        qual: IN_OUT,
+        gtype: GLSLTYPE::ArrayFloat,
+        name: "data",
+        frames: &[],
+    }];
+
+    // Run the body once for every element of data, which is both read and written
+    fn run_uints(body: &'static str, data: Vec<u32>) -> Vec<u32> {
+        let (program, mut bindings, mut out_bindings) = compile_cpu(&ComputeShader {
+            params: UINTS,
+            body,
+        });
+        data.bind(
+            &program,
+            &mut bindings,
+            &mut out_bindings,
+            "data".to_string(),
+        );
+        let results = run_cpu(&program, &mut bindings, out_bindings);
+        block_on(read_uvec(&results, "data"))
+    }
+
+    fn run_floats(body: &'static str, data: Vec<f32>) -> Vec<f32> {
+        let (program, mut bindings, mut out_bindings) = compile_cpu(&ComputeShader {
+            params: FLOATS,
+            body,
+        });
+        data.bind(
+            &program,
+            &mut bindings,
+            &mut out_bindings,
+            "data".to_string(),
+        );
+        let results = run_cpu(&program, &mut bindings, out_bindings);
+        block_on(read_fvec(&results, "data"))
+    }
+
+    #[test]
+    fn arithmetic_wraps_like_the_gpu() {
+        let results = run_uints(
+            "{
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    if (i == 0) {
+                        data[i] = 7 + 3 * 2;
+                    } else if (i == 1) {
+                        data[i] = -7 / 2;
+                    } else if (i == 2) {
+                        data[i] = 4294967295u + 2u;
+                    } else if (i == 3) {
+                        data[i] = 2147483647 + 1;
+                    } else if (i == 4) {
+                        data[i] = 0u - 1u;
+                    } else if (i == 5) {
+                        data[i] = -7 % 3;
+                    } else if (i == 6) {
+                        data[i] = (1u << 31) >> 3;
+                    } else {
+                        data[i] = (10 - 4) * (2 + 1) / 4;
+                    }
+                }
+            }",
+            vec![0; 8],
+        );
+        assert_eq!(
+            results,
+            vec![
+                13,
+                -3i32 as u32,
+                1,
+                std::i32::MIN as u32,
+                std::u32::MAX,
+                -1i32 as u32,
+                1 << 28,
+                4
+            ]
+        );
+    }
+
+    #[test]
+    fn later_elements_are_read_before_they_are_written() {
+        let results = run_uints(
+            "{
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    if (i < 3) {
+                        data[i] = data[i + 1] * 10;
+                    }
+                }
+            }",
+            vec![1, 2, 3, 4],
+        );
+        assert_eq!(results, vec![20, 30, 40, 4]);
+    }
+
+    #[test]
+    fn floats_and_ints_mix_in_arithmetic() {
+        let results = run_floats(
+            "{
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    data[i] = data[i] * 2 + i;
+                }
+            }",
+            vec![0.5, 1.25, -3.0],
+        );
+        assert_eq!(results, vec![1.0, 3.5, -4.0]);
+    }
+
+    #[test]
+    fn swizzles_can_be_assigned() {
+        let results = run_floats(
+            "{
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    vec4 v = vec4(1.0, 2.0, 3.0, 4.0);
+                    v.xz = vec2(10.0, 30.0);
+                    v.wy = v.xx + vec2(1.0, 2.0);
+                    v.z += 1.0;
+                    data[i] = v[i];
+                }
+            }",
+            vec![0.0; 4],
+        );
+        assert_eq!(results, vec![10.0, 12.0, 31.0, 11.0]);
+    }
+
+    #[test]
+    fn loops_break_and_continue() {
+        let results = run_uints(
+            "{
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    uint total = 0;
+                    for (uint k = 0; k < 10; k++) {
+                        if (k == 2) {
+                            continue;
+                        }
+                        if (k == 6) {
+                            break;
+                        }
+                        total += k;
+                    }
+                    uint w = 0;
+                    while (true) {
+                        w++;
+                        if (w < 3) {
+                            continue;
+                        }
+                        if (w >= 5) {
+                            break;
+                        }
+                    }
+                    uint d = 0;
+                    uint skipped = 0;
+                    do {
+                        d += 2;
+                        if (d == 4) {
+                            skipped++;
+                            continue;
+                        }
+                    } while (d < 9);
+                    if (i == 0) {
+                        data[i] = total;
+                    } else if (i == 1) {
+                        data[i] = w;
+                    } else {
+                        data[i] = d * 10 + skipped;
+                    }
+                }
+            }",
+            vec![0; 3],
+        );
+        assert_eq!(results, vec![13, 5, 101]);
+    }
+
+    #[test]
+    fn out_and_inout_parameters_write_back() {
+        let results = run_uints(
+            "{
+                void split(uint x, out uint high, out uint low) {
+                    high = x / 10;
+                    low = x % 10;
+                }
+
+                void twice(inout uint x) {
+                    x *= 2;
+                }
+
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    uint h;
+                    uint l;
+                    split(data[i], h, l);
+                    twice(l);
+                    data[i] = h * 100 + l;
+                    twice(data[i]);
+                }
+            }",
+            vec![42, 17],
+        );
+        assert_eq!(results, vec![808, 228]);
+    }
+
+    #[test]
+    fn arrays_know_their_length() {
+        let results = run_uints(
+            "{
+                void main() {
+                    data[gl_GlobalInvocationID.x] = data.length() * 10 + gl_GlobalInvocationID.x;
+                }
+            }",
+            vec![0; 3],
+        );
+        assert_eq!(results, vec![30, 31, 32]);
+    }
+
+    #[test]
+    fn results_can_be_piped_into_another_program() {
+        let (first, mut bindings, mut out_bindings) = compile_cpu(&ComputeShader {
+            params: UINTS,
+            body: "{
+                void main() {
+                    data[gl_GlobalInvocationID.x] *= 2;
+                }
+            }",
+        });
+        vec![1u32, 2, 3].bind(&first, &mut bindings, &mut out_bindings, "data".to_string());
+        let results = run_cpu(&first, &mut bindings, out_bindings);
+
+        const VALUES: &[PARAMETER] = &[PARAMETER {
+            qual: IN_OUT,
+            gtype: GLSLTYPE::ArrayUint,
+            name: "values",
+            frames: &[],
+        }];
+        let (second, next_bindings, next_out_bindings) = compile_cpu(&ComputeShader {
+            params: VALUES,
+            body: "{
+                void main() {
+                    values[gl_GlobalInvocationID.x] += 1;
+                }
+            }",
+        });
+        let piped = pipe_cpu(
+            &second,
+            next_bindings,
+            next_out_bindings,
+            results,
+            &[("data", "values")],
+        );
+        assert_eq!(block_on(read_uvec(&piped, "values")), vec![3, 5, 7]);
+    }
+
+    #[test]
+    fn constructors_convert_explicitly() {
+        let results = run_floats(
+            "{
+                void main() {
+                    uint i = gl_GlobalInvocationID.x;
+                    int n = int(data[i]);
+                    uint u = uint(n);
+                    data[i] = float(n) + float(u) + float(bool(n));
+                }
+            }",
+            vec![1.5, 2.75, 0.25],
+        );
+        assert_eq!(results, vec![3.0, 5.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "A float can't be used as x which is a int")]
+    fn floats_are_not_implicitly_ints() {
+        run_uints("{ void main() { int x = 1.5; } }", vec![0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "A int can't be used as b which is a bool")]
+    fn ints_are_not_implicitly_bools() {
+        run_uints("{ void main() { bool b = 1; } }", vec![0]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "A float can't be used as the target of an assignment which is a uint"
+    )]
+    fn floats_are_not_implicitly_stored_as_uints() {
+        run_uints(
+            "{ void main() { data[gl_GlobalInvocationID.x] = 1.5; } }",
+            vec![0],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "points is a vec3[] whose layout differs")]
+    fn vec3_arrays_are_refused() {
+        const POINTS: &[PARAMETER] = &[PARAMETER {
+            qual: IN_OUT,
+            gtype: GLSLTYPE::ArrayVec3,
+            name: "points",
+            frames: &[],
+        }];
+        compile_cpu(&ComputeShader {
+            params: POINTS,
+            body: "{ void main() {} }",
+        });
+    }
+
+    #[test]
+    fn try_read_keeps_the_values() {
+        let (program, mut bindings, mut out_bindings) = compile_cpu(&ComputeShader {
+            params: UINTS,
+            body: "{ void main() { data[gl_GlobalInvocationID.x] += 1; } }",
+        });
+        vec![1u32, 2].bind(
+            &program,
+            &mut bindings,
+            &mut out_bindings,
+            "data".to_string(),
+        );
+        let results = run_cpu(&program, &mut bindings, out_bindings);
+        let mut readback = read_uvec(&results, "data");
+        assert_eq!(readback.try_read(), Some(&[2u32, 3][..]));
+        assert_eq!(readback.try_read(), Some(&[2u32, 3][..]));
+        assert_eq!(block_on(readback), vec![2, 3]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::mesh::Mesh;
+use crate::shared::{Program, QUALIFIER};
+use crate::wgpu_graphics_header::{create_texture_rgba, GraphicsBindings};
+
+// The names of the vertex parameters that the attributes of a gltf mesh are bound to
+pub const POSITION_ATTRIBUTE: &str = "a_position";
+pub const NORMAL_ATTRIBUTE: &str = "a_normal";
+pub const TEX_COORD_ATTRIBUTE: &str = "a_TexCoord";
+
+// One primitive of a gltf mesh, normals and texture coordinates are empty when the file doesn't have them
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub indicies: Vec<u16>,
+    pub material: Option<usize>,
+}
+
+impl GltfMesh {
+    // The vertex parameters this mesh has data for
+    pub fn attributes(&self) -> Vec<&'static str> {
+        let mut attributes = vec![POSITION_ATTRIBUTE];
+        if !self.normals.is_empty() {
+            attributes.push(NORMAL_ATTRIBUTE);
+        }
+        if !self.tex_coords.is_empty() {
+            attributes.push(TEX_COORD_ATTRIBUTE);
+        }
+        attributes
+    }
+
+    pub fn to_mesh(&self, program: &dyn Program) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.add_vec3(program, POSITION_ATTRIBUTE, &self.positions);
+        if !self.normals.is_empty() {
+            mesh.add_vec3(program, NORMAL_ATTRIBUTE, &self.normals);
+        }
+        if !self.tex_coords.is_empty() {
+            mesh.add_vec2(program, TEX_COORD_ATTRIBUTE, &self.tex_coords);
+        }
+        mesh.set_indicies(program, &self.indicies);
+        mesh
+    }
+}
+
+// Rgba8 pixels, row by row from the top
+#[derive(Debug, Clone)]
+pub struct GltfTexture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl GltfTexture {
+    pub fn upload(&self, program: &dyn Program) -> wgpu::TextureView {
+        create_texture_rgba(program, self.width, self.height, &self.pixels)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color: [f32; 4],
+    // An index into the textures of the scene
+    pub base_color_texture: Option<usize>,
+}
+
+// A mesh placed in the world by the transforms of the node it belongs to and all of that node's parents
+#[derive(Debug, Clone)]
+pub struct GltfDraw {
+    pub node: Option<String>,
+    pub mesh: usize,
+    pub material: Option<usize>,
+    pub transform: cgmath::Matrix4<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub draws: Vec<GltfDraw>,
+}
+
+#[derive(Debug)]
+pub enum GltfError {
+    Import(PathBuf, gltf::Error),
+    MissingPositions(PathBuf, Option<String>),
+    // Only triangle lists are supported
+    UnsupportedMode(PathBuf, Option<String>, gltf::mesh::Mode),
+    UnsupportedImage(PathBuf, usize, gltf::image::Format),
+    // Indicies are u16 so a primitive can't have more vertices than that
+    TooManyVertices(PathBuf, Option<String>),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Import(path, e) => {
+                write!(f, "{} could not be imported: {}", path.display(), e)
+            }
+            GltfError::MissingPositions(path, mesh) => write!(
+                f,
+                "{} has a primitive of mesh {:?} without positions",
+                path.display(),
+                mesh
+            ),
+            GltfError::UnsupportedMode(path, mesh, mode) => write!(
+                f,
+                "{} has a primitive of mesh {:?} drawn as {:?} but only triangles are supported",
+                path.display(),
+                mesh,
+                mode
+            ),
+            GltfError::UnsupportedImage(path, image, format) => write!(
+                f,
+                "{} has image {} in the {:?} format which can't be converted to rgba8",
+                path.display(),
+                image,
+                format
+            ),
+            GltfError::TooManyVertices(path, mesh) => write!(
+                f,
+                "{} has a primitive of mesh {:?} with more than {} vertices which is too many for u16 indicies",
+                path.display(),
+                mesh,
+                u16::MAX as usize + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+// Load a .gltf or .glb file and flatten the default scene, or the first scene when there is no default, into draws
+// Triangles keep the counter-clockwise winding of the file
+pub fn load_gltf(file_name: &str) -> Result<GltfScene, GltfError> {
+    let path = Path::new(file_name);
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| GltfError::Import(path.to_path_buf(), e))?;
+
+    let textures = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| gltf_texture(path, i, image))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Textures are referenced through the index of their image
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            GltfMaterial {
+                name: material.name().map(String::from),
+                base_color: pbr.base_color_factor(),
+                base_color_texture: pbr
+                    .base_color_texture()
+                    .map(|info| info.texture().source().index()),
+            }
+        })
+        .collect();
+
+    // The primitives of every gltf mesh, which each become one of our meshes
+    let mut meshes = Vec::new();
+    let mut primitives = Vec::new();
+    for mesh in document.meshes() {
+        let name = mesh.name().map(String::from);
+        let mut indexes = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                return Err(GltfError::UnsupportedMode(
+                    path.to_path_buf(),
+                    name,
+                    primitive.mode(),
+                ));
+            }
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| GltfError::MissingPositions(path.to_path_buf(), name.clone()))?
+                .collect();
+            if positions.len() > u16::MAX as usize + 1 {
+                return Err(GltfError::TooManyVertices(path.to_path_buf(), name));
+            }
+            let normals = reader.read_normals().map_or_else(Vec::new, |n| n.collect());
+            let tex_coords = reader
+                .read_tex_coords(0)
+                .map_or_else(Vec::new, |t| t.into_f32().collect());
+            let indicies: Vec<u16> = match reader.read_indices() {
+                Some(i) => i.into_u32().map(|i| i as u16).collect(),
+                None => (0..positions.len()).map(|i| i as u16).collect(),
+            };
+
+            indexes.push(meshes.len());
+            meshes.push(GltfMesh {
+                name: name.clone(),
+                positions,
+                normals,
+                tex_coords,
+                indicies,
+                material: primitive.material().index(),
+            });
+        }
+        primitives.push(indexes);
+    }
+
+    let mut draws = Vec::new();
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        use cgmath::SquareMatrix;
+        for node in scene.nodes() {
+            flatten_node(
+                &node,
+                cgmath::Matrix4::identity(),
+                &primitives,
+                &meshes,
+                &mut draws,
+            );
+        }
+    }
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        textures,
+        draws,
+    })
+}
+
+fn flatten_node(
+    node: &gltf::Node,
+    parent: cgmath::Matrix4<f32>,
+    primitives: &[Vec<usize>],
+    meshes: &[GltfMesh],
+    draws: &mut Vec<GltfDraw>,
+) {
+    let transform = parent * cgmath::Matrix4::from(node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        for &index in primitives[mesh.index()].iter() {
+            draws.push(GltfDraw {
+                node: node.name().map(String::from),
+                mesh: index,
+                material: meshes[index].material,
+                transform,
+            });
+        }
+    }
+
+    for child in node.children() {
+        flatten_node(&child, transform, primitives, meshes, draws);
+    }
+}
+
+fn gltf_texture(
+    path: &Path,
+    index: usize,
+    image: &gltf::image::Data,
+) -> Result<GltfTexture, GltfError> {
+    use gltf::image::Format;
+
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels.clone(),
+        Format::R8G8B8 => image
+            .pixels
+            .chunks(3)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect(),
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|&p| vec![p, p, p, 255])
+            .collect(),
+        format => {
+            return Err(GltfError::UnsupportedImage(
+                path.to_path_buf(),
+                index,
+                format,
+            ))
+        }
+    };
+
+    Ok(GltfTexture {
+        width: image.width,
+        height: image.height,
+        pixels,
+    })
+}
+
+// A vertex parameter of the program that a mesh has no data for
+#[derive(Debug, Clone)]
+pub struct MissingAttribute {
+    pub mesh: usize,
+    pub mesh_name: Option<String>,
+    pub attribute: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GltfReport {
+    pub missing: Vec<MissingAttribute>,
+}
+
+impl GltfReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+impl fmt::Display for GltfReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_ok() {
+            return write!(
+                f,
+                "Every mesh has all of the vertex parameters of the program"
+            );
+        }
+        for m in self.missing.iter() {
+            writeln!(
+                f,
+                "Mesh {} ({:?}) has no data for the vertex parameter {}",
+                m.mesh, m.mesh_name, m.attribute
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Check that every mesh of the scene has data for each vertex parameter of the program
+pub fn validate_gltf(scene: &GltfScene, bindings: &GraphicsBindings) -> GltfReport {
+    let mut report = GltfReport::default();
+    for binding in bindings.bindings.iter() {
+        if !binding.qual.contains(&QUALIFIER::VERTEX) {
+            continue;
+        }
+        for (i, mesh) in scene.meshes.iter().enumerate() {
+            if !mesh.attributes().contains(&binding.name.as_str()) {
+                report.missing.push(MissingAttribute {
+                    mesh: i,
+                    mesh_name: mesh.name.clone(),
+                    attribute: binding.name.clone(),
+                })
+            }
+        }
+    }
+    report
+}
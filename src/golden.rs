@@ -0,0 +1,516 @@
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::capture::{write_png, CaptureError};
+use crate::wgpu_graphics_header::{
+    create_depth_view, create_render_target, GraphicsProgram, PassDescriptor,
+};
+
+// Set this environment variable to write what is rendered as the new golden images instead of comparing
+pub const UPDATE_GOLDENS: &str = "UPDATE_GOLDENS";
+
+// How far a rendering can drift from its golden image before the comparison fails
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    // The largest difference allowed in any of the red, green, blue or alpha values of a pixel
+    pub channel: u8,
+    // How many pixels can be further off than that
+    pub max_differing_pixels: usize,
+}
+
+// Allows for the rounding that differs between gpus but not for a single pixel that is really different
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            channel: 2,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Capture(CaptureError),
+    Open(PathBuf, std::io::Error),
+    Decode(PathBuf, png::DecodingError),
+    // Only 8 bit images can be compared
+    UnsupportedFormat(PathBuf, png::ColorType, png::BitDepth),
+    Missing(PathBuf),
+    SizeMismatch {
+        path: PathBuf,
+        golden: (u32, u32),
+        rendered: (u32, u32),
+    },
+    TooManyDifferingPixels {
+        path: PathBuf,
+        differing: usize,
+        allowed: usize,
+        diff: PathBuf,
+        rendered: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoldenError::Capture(e) => write!(f, "{}", e),
+            GoldenError::Open(path, e) => write!(f, "{} could not be opened: {}", path.display(), e),
+            GoldenError::Decode(path, e) => {
+                write!(f, "{} could not be read as a png: {}", path.display(), e)
+            }
+            GoldenError::UnsupportedFormat(path, color, depth) => write!(
+                f,
+                "{} is a {:?} {:?} png but only 8 bit images can be compared",
+                path.display(),
+                color,
+                depth
+            ),
+            GoldenError::Missing(path) => write!(
+                f,
+                "There is no golden image at {}, run with {}=1 to create it",
+                path.display(),
+                UPDATE_GOLDENS
+            ),
+            GoldenError::SizeMismatch {
+                path,
+                golden,
+                rendered,
+            } => write!(
+                f,
+                "{} is {} by {} but the rendering is {} by {}",
+                path.display(),
+                golden.0,
+                golden.1,
+                rendered.0,
+                rendered.1
+            ),
+            GoldenError::TooManyDifferingPixels {
+                path,
+                differing,
+                allowed,
+                diff,
+                rendered,
+            } => write!(
+                f,
+                "{} pixels differ from {} but only {} are allowed, see {} for where and {} for what was rendered",
+                differing,
+                path.display(),
+                allowed,
+                diff.display(),
+                rendered.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<CaptureError> for GoldenError {
+    fn from(e: CaptureError) -> Self {
+        GoldenError::Capture(e)
+    }
+}
+
+// Read a png as rgba8 pixels, row by row from the top, along with its width and height
+pub fn read_png(path: &str) -> Result<(u32, u32, Vec<u8>), GoldenError> {
+    let path = Path::new(path);
+    let file = File::open(path).map_err(|e| GoldenError::Open(path.to_path_buf(), e))?;
+    let mut decoder = png::Decoder::new(file);
+    // Palettes are turned into the colors they stand for
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let (info, mut reader) = decoder
+        .read_info()
+        .map_err(|e| GoldenError::Decode(path.to_path_buf(), e))?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader
+        .next_frame(&mut buf)
+        .map_err(|e| GoldenError::Decode(path.to_path_buf(), e))?;
+
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(GoldenError::UnsupportedFormat(
+            path.to_path_buf(),
+            info.color_type,
+            info.bit_depth,
+        ));
+    }
+    let pixels = match info.color_type {
+        png::ColorType::RGBA => buf,
+        png::ColorType::RGB => buf
+            .chunks(3)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks(2)
+            .flat_map(|p| vec![p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&p| vec![p, p, p, 255]).collect(),
+        color => {
+            return Err(GoldenError::UnsupportedFormat(
+                path.to_path_buf(),
+                color,
+                info.bit_depth,
+            ))
+        }
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+fn updating_goldens() -> bool {
+    match std::env::var(UPDATE_GOLDENS) {
+        Ok(value) => !value.is_empty() && value != "0",
+        Err(_) => false,
+    }
+}
+
+// Where the diff and the rendering are written next to a golden image, foo.png gives foo.diff.png and foo.rendered.png
+fn beside(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+// Compare rgba8 pixels, row by row from the top, to the golden image at path and give back how many pixels differ
+// When too many differ a diff with the differing pixels in red over a faded copy of the golden image is written next to it
+// With UPDATE_GOLDENS set the pixels are written as the golden image instead
+pub fn compare_golden(
+    path: &str,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    tolerance: Tolerance,
+) -> Result<usize, GoldenError> {
+    compare_golden_with(updating_goldens(), path, width, height, pixels, tolerance)
+}
+
+// The comparison behind compare_golden with whether to write the golden image instead given rather than read from UPDATE_GOLDENS
+pub fn compare_golden_with(
+    update: bool,
+    path: &str,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    tolerance: Tolerance,
+) -> Result<usize, GoldenError> {
+    let bytes = 4 * width as usize * height as usize;
+    if pixels.len() != bytes {
+        panic!(
+            "A {} by {} rendering needs {} bytes of rgba pixels but {} were given",
+            width,
+            height,
+            bytes,
+            pixels.len()
+        )
+    }
+    if update {
+        write_png(path, width, height, pixels)?;
+        return Ok(0);
+    }
+
+    let golden_path = Path::new(path);
+    if !golden_path.exists() {
+        return Err(GoldenError::Missing(golden_path.to_path_buf()));
+    }
+    let (golden_width, golden_height, golden) = read_png(path)?;
+    if (golden_width, golden_height) != (width, height) {
+        return Err(GoldenError::SizeMismatch {
+            path: golden_path.to_path_buf(),
+            golden: (golden_width, golden_height),
+            rendered: (width, height),
+        });
+    }
+
+    let mut differing = 0;
+    let mut diff = Vec::with_capacity(pixels.len());
+    for (rendered, expected) in pixels.chunks(4).zip(golden.chunks(4)) {
+        let off = rendered
+            .iter()
+            .zip(expected.iter())
+            .any(|(a, b)| (*a as i16 - *b as i16).abs() > tolerance.channel as i16);
+        if off {
+            differing += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = ((expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 9) as u8;
+            diff.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+
+    if differing > tolerance.max_differing_pixels {
+        let diff_path = beside(golden_path, "diff");
+        let rendered_path = beside(golden_path, "rendered");
+        write_png(&diff_path.to_string_lossy(), width, height, &diff)?;
+        write_png(&rendered_path.to_string_lossy(), width, height, pixels)?;
+        return Err(GoldenError::TooManyDifferingPixels {
+            path: golden_path.to_path_buf(),
+            differing,
+            allowed: tolerance.max_differing_pixels,
+            diff: diff_path,
+            rendered: rendered_path,
+        });
+    }
+    Ok(differing)
+}
+
+// Render into a target of a fixed size instead of the window and read back the rgba8 pixels
+// The draw function begins its passes on the encoder with begin_pass, the descriptor it is given already has the target
+// and a depth attachment when the program tests depth, and everything it records is submitted once it returns
+pub fn render_offscreen<F>(program: &GraphicsProgram, width: u32, height: u32, draw: F) -> Vec<u8>
+where
+    F: FnOnce(&mut wgpu::CommandEncoder, PassDescriptor),
+{
+    let target = create_render_target(program, width, height);
+    let depth = if program.depth_test {
+        Some(create_depth_view(program, width, height))
+    } else {
+        None
+    };
+
+    let mut encoder = program
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    draw(
+        &mut encoder,
+        PassDescriptor {
+            depth: depth.as_ref(),
+            ..PassDescriptor::new(&target.view)
+        },
+    );
+    program.queue.submit(&[encoder.finish()]);
+
+    target.read_pixels(program)
+}
+
+// Render offscreen and panic with what went wrong if the result doesn't match the golden image at path
+pub fn assert_golden<F>(
+    program: &GraphicsProgram,
+    width: u32,
+    height: u32,
+    path: &str,
+    tolerance: Tolerance,
+    draw: F,
+) where
+    F: FnOnce(&mut wgpu::CommandEncoder, PassDescriptor),
+{
+    let pixels = render_offscreen(program, width, height, draw);
+    if let Err(e) = compare_golden(path, width, height, &pixels, tolerance) {
+        panic!("{}", e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    // The tests compare without updating whatever UPDATE_GOLDENS is set to
+    fn compare(
+        path: &str,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        tolerance: Tolerance,
+    ) -> Result<usize, GoldenError> {
+        compare_golden_with(false, path, width, height, pixels, tolerance)
+    }
+
+    // A directory of its own for each test which is removed when the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir = std::env::temp_dir().join(format!("golden_{}_{}", std::process::id(), name));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn file(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write_raw_png(
+        path: &str,
+        width: u32,
+        height: u32,
+        color: png::ColorType,
+        depth: png::BitDepth,
+        data: &[u8],
+    ) {
+        let file = File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(data)
+            .unwrap();
+    }
+
+    // Two by one pixels of the same gray that is in the middle of the range so it can be off in either direction
+    fn golden(dir: &TempDir) -> String {
+        let path = dir.file("golden.png");
+        write_png(&path, 2, 1, &[100; 8]).unwrap();
+        path
+    }
+
+    fn tolerance(channel: u8, max_differing_pixels: usize) -> Tolerance {
+        Tolerance {
+            channel,
+            max_differing_pixels,
+        }
+    }
+
+    #[test]
+    fn channels_can_be_off_by_the_tolerance() {
+        let dir = TempDir::new("channel");
+        let path = golden(&dir);
+
+        let within = [102, 98, 100, 100, 100, 100, 102, 100];
+        assert_eq!(compare(&path, 2, 1, &within, tolerance(2, 0)).unwrap(), 0);
+        let beyond = [103, 100, 100, 100, 100, 100, 100, 100];
+        assert_eq!(compare(&path, 2, 1, &beyond, tolerance(2, 1)).unwrap(), 1);
+    }
+
+    #[test]
+    fn differing_pixels_can_reach_the_budget() {
+        let dir = TempDir::new("budget");
+        let path = golden(&dir);
+        let pixels = [0, 100, 100, 100, 100, 100, 100, 255];
+
+        assert_eq!(compare(&path, 2, 1, &pixels, tolerance(0, 2)).unwrap(), 2);
+        match compare(&path, 2, 1, &pixels, tolerance(0, 1)) {
+            Err(GoldenError::TooManyDifferingPixels {
+                differing,
+                allowed,
+                diff,
+                rendered,
+                ..
+            }) => {
+                assert_eq!((differing, allowed), (2, 1));
+                assert_eq!(
+                    read_png(&diff.to_string_lossy()).unwrap().2,
+                    [255, 0, 0, 255, 255, 0, 0, 255]
+                );
+                assert_eq!(
+                    read_png(&rendered.to_string_lossy()).unwrap().2,
+                    pixels.to_vec()
+                );
+            }
+            other => panic!("Expected too many differing pixels but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sizes_have_to_match() {
+        let dir = TempDir::new("size");
+        let path = golden(&dir);
+
+        match compare(&path, 1, 2, &[100; 8], Tolerance::default()) {
+            Err(GoldenError::SizeMismatch {
+                golden, rendered, ..
+            }) => assert_eq!((golden, rendered), ((2, 1), (1, 2))),
+            other => panic!("Expected a size mismatch but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_goldens_are_reported() {
+        let dir = TempDir::new("missing");
+        let path = dir.file("missing.png");
+
+        match compare(&path, 1, 1, &[0; 4], Tolerance::default()) {
+            Err(GoldenError::Missing(missing)) => assert_eq!(missing, Path::new(&path)),
+            other => panic!("Expected a missing golden but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn updating_writes_the_rendering_as_the_golden() {
+        let dir = TempDir::new("update");
+        let path = dir.file("new.png");
+        let pixels = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        let updated = compare_golden_with(true, &path, 2, 1, &pixels, Tolerance::default());
+
+        assert_eq!(updated.unwrap(), 0);
+        assert_eq!(read_png(&path).unwrap(), (2, 1, pixels.to_vec()));
+        assert_eq!(compare(&path, 2, 1, &pixels, tolerance(0, 0)).unwrap(), 0);
+    }
+
+    #[test]
+    fn rgb_and_grayscale_are_expanded_to_rgba() {
+        let dir = TempDir::new("expand");
+
+        let rgb = dir.file("rgb.png");
+        write_raw_png(
+            &rgb,
+            2,
+            1,
+            png::ColorType::RGB,
+            png::BitDepth::Eight,
+            &[1, 2, 3, 4, 5, 6],
+        );
+        assert_eq!(
+            read_png(&rgb).unwrap(),
+            (2, 1, vec![1, 2, 3, 255, 4, 5, 6, 255])
+        );
+
+        let gray = dir.file("gray.png");
+        write_raw_png(
+            &gray,
+            2,
+            1,
+            png::ColorType::Grayscale,
+            png::BitDepth::Eight,
+            &[7, 8],
+        );
+        assert_eq!(
+            read_png(&gray).unwrap(),
+            (2, 1, vec![7, 7, 7, 255, 8, 8, 8, 255])
+        );
+
+        let gray_alpha = dir.file("gray_alpha.png");
+        write_raw_png(
+            &gray_alpha,
+            1,
+            1,
+            png::ColorType::GrayscaleAlpha,
+            png::BitDepth::Eight,
+            &[9, 10],
+        );
+        assert_eq!(read_png(&gray_alpha).unwrap(), (1, 1, vec![9, 9, 9, 10]));
+    }
+
+    #[test]
+    fn only_8_bit_images_can_be_read() {
+        let dir = TempDir::new("depth");
+        let path = dir.file("deep.png");
+        write_raw_png(
+            &path,
+            1,
+            1,
+            png::ColorType::RGB,
+            png::BitDepth::Sixteen,
+            &[0; 6],
+        );
+
+        match read_png(&path) {
+            Err(GoldenError::UnsupportedFormat(_, color, depth)) => {
+                assert_eq!(
+                    (color, depth),
+                    (png::ColorType::RGB, png::BitDepth::Sixteen)
+                )
+            }
+            other => panic!("Expected an unsupported format but got {:?}", other),
+        }
+    }
+}
@@ -1,6 +1,18 @@
+use cgmath::InnerSpace;
+use obj::raw::material::{Material, MtlColor, MtlTextureMap};
+use obj::raw::object::{Group, Polygon};
+use obj::raw::{parse_mtl, parse_obj, RawObj};
 use obj::{load_obj, Obj};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
+use std::marker::PhantomData;
+use std::ops::{Mul, Range};
+use std::path::{Path, PathBuf};
+
+use crate::bind::{Bindable, OutProgramBindings, ProgramBindings, UniformValue, WriteMode};
+use crate::shared::{Program, GLSLTYPE};
 
 pub fn load_model(file_name: &str) -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u16>) {
     let input = BufReader::new(
@@ -17,6 +29,306 @@ pub fn load_model(file_name: &str) -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u16>) {
     (positions, normals, indices)
 }
 
+// How normals are made for the faces of an obj file that doesn't give any
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenerateNormals {
+    // Every triangle gets its own vertices which face the same way as the triangle
+    Flat,
+    // Vertices at the same position average the normals of the triangles around them, weighted by area
+    Smooth,
+}
+
+// The parameters of a material from an mtl file
+// Texture paths are relative to the working directory instead of the mtl file
+#[derive(Debug, Clone, Default)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: Option<[f32; 3]>,
+    pub diffuse: Option<[f32; 3]>,
+    pub specular: Option<[f32; 3]>,
+    pub emissive: Option<[f32; 3]>,
+    pub specular_exponent: Option<f32>,
+    pub dissolve: Option<f32>,
+    pub ambient_map: Option<PathBuf>,
+    pub diffuse_map: Option<PathBuf>,
+    pub specular_map: Option<PathBuf>,
+    pub bump_map: Option<PathBuf>,
+}
+
+// The triangles of one group of an obj file that use the same material
+// A group that switches material part way through is split into several of these
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub indicies: Range<u32>,
+}
+
+// Every vertex has a position and a normal, and a texture coordinate when the file has any
+// Texture coordinates are flipped so that 0 is the top of the image like it is for wgpu textures
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indicies: Vec<u16>,
+    pub groups: Vec<ObjGroup>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, obj::ObjError),
+    // A face refers to a position, texture coordinate or normal that the file doesn't have
+    BadIndex(PathBuf, &'static str, usize),
+    // Indicies are u16 so a model can't have more vertices than that
+    TooManyVertices(PathBuf),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::Io(path, e) => write!(f, "{} could not be read: {}", path.display(), e),
+            ModelError::Parse(path, e) => {
+                write!(f, "{} could not be parsed: {}", path.display(), e)
+            }
+            ModelError::BadIndex(path, kind, index) => write!(
+                f,
+                "{} has a face using {} {} which doesn't exist",
+                path.display(),
+                kind,
+                index + 1
+            ),
+            ModelError::TooManyVertices(path) => write!(
+                f,
+                "{} has more than {} vertices which is too many for u16 indicies",
+                path.display(),
+                u16::MAX as usize + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+fn open_file(path: &Path) -> Result<BufReader<File>, ModelError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| ModelError::Io(path.to_path_buf(), e))
+}
+
+// Load an obj file along with the materials of the mtl files it uses
+// Faces are split into triangles which are wound the same way as load_model
+pub fn load_obj_model(file_name: &str, generate: GenerateNormals) -> Result<ObjModel, ModelError> {
+    let path = Path::new(file_name);
+    let raw = parse_obj(open_file(path)?).map_err(|e| ModelError::Parse(path.to_path_buf(), e))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut model = ObjModel::default();
+
+    for library in raw.material_libraries.iter() {
+        let mtl_path = dir.join(library);
+        let mtl = parse_mtl(open_file(&mtl_path)?).map_err(|e| ModelError::Parse(mtl_path, e))?;
+        let mut names: Vec<&String> = mtl.materials.keys().collect();
+        names.sort();
+        for name in names {
+            model
+                .materials
+                .push(obj_material(name, &mtl.materials[name], dir));
+        }
+    }
+
+    let faces = raw
+        .polygons
+        .iter()
+        .map(|p| face_corners(p, &raw, path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let positions: Vec<[f32; 3]> = raw.positions.iter().map(|p| [p.0, p.1, p.2]).collect();
+    let smooth = match generate {
+        GenerateNormals::Smooth => smooth_normals(&positions, &faces),
+        GenerateNormals::Flat => Vec::new(),
+    };
+
+    let group_names = polygon_labels(&raw.groups, faces.len());
+    let material_names = polygon_labels(&raw.meshes, faces.len());
+
+    // Vertices that are the same corner of several faces are only added once
+    let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), u16> = HashMap::new();
+
+    for (i, face) in faces.iter().enumerate() {
+        let start = model.indicies.len() as u32;
+
+        for k in 1..face.len().saturating_sub(1) {
+            let flat = triangle_normal(
+                positions[face[0].0],
+                positions[face[k].0],
+                positions[face[k + 1].0],
+            );
+
+            for &(p, t, n) in [face[0], face[k + 1], face[k]].iter() {
+                let normal = match (n, generate) {
+                    (Some(n), _) => {
+                        let n = raw.normals[n];
+                        [n.0, n.1, n.2]
+                    }
+                    (None, GenerateNormals::Flat) => flat,
+                    (None, GenerateNormals::Smooth) => smooth[p],
+                };
+                let uv = t.map(|t| [raw.tex_coords[t].0, 1.0 - raw.tex_coords[t].1]);
+
+                let index = match vertices.get(&(p, t, n)) {
+                    // Generated flat normals differ for every triangle so those vertices are never shared
+                    Some(&index) if n.is_some() || generate == GenerateNormals::Smooth => index,
+                    _ => {
+                        if model.positions.len() > u16::MAX as usize {
+                            return Err(ModelError::TooManyVertices(path.to_path_buf()));
+                        }
+                        let index = model.positions.len() as u16;
+                        model.positions.push(positions[p]);
+                        model.normals.push(normal);
+                        if !raw.tex_coords.is_empty() {
+                            model.uvs.push(uv.unwrap_or([0.0, 0.0]));
+                        }
+                        vertices.insert((p, t, n), index);
+                        index
+                    }
+                };
+                model.indicies.push(index);
+            }
+        }
+
+        let end = model.indicies.len() as u32;
+        let name = group_names[i]
+            .clone()
+            .unwrap_or_else(|| "default".to_string());
+        let material = material_names[i].clone().filter(|m| !m.is_empty());
+        match model.groups.last_mut() {
+            Some(last) if last.name == name && last.material == material => last.indicies.end = end,
+            _ => model.groups.push(ObjGroup {
+                name,
+                material,
+                indicies: start..end,
+            }),
+        }
+    }
+
+    Ok(model)
+}
+
+// The position, texture coordinate and normal of every corner of a face
+fn face_corners(
+    polygon: &Polygon,
+    raw: &RawObj,
+    path: &Path,
+) -> Result<Vec<(usize, Option<usize>, Option<usize>)>, ModelError> {
+    let corners: Vec<(usize, Option<usize>, Option<usize>)> = match polygon {
+        Polygon::P(c) => c.iter().map(|&p| (p, None, None)).collect(),
+        Polygon::PT(c) => c.iter().map(|&(p, t)| (p, Some(t), None)).collect(),
+        Polygon::PN(c) => c.iter().map(|&(p, n)| (p, None, Some(n))).collect(),
+        Polygon::PTN(c) => c.iter().map(|&(p, t, n)| (p, Some(t), Some(n))).collect(),
+    };
+
+    for &(p, t, n) in corners.iter() {
+        if p >= raw.positions.len() {
+            return Err(ModelError::BadIndex(path.to_path_buf(), "position", p));
+        }
+        if let Some(t) = t.filter(|&t| t >= raw.tex_coords.len()) {
+            return Err(ModelError::BadIndex(
+                path.to_path_buf(),
+                "texture coordinate",
+                t,
+            ));
+        }
+        if let Some(n) = n.filter(|&n| n >= raw.normals.len()) {
+            return Err(ModelError::BadIndex(path.to_path_buf(), "normal", n));
+        }
+    }
+    Ok(corners)
+}
+
+// The name of the group each polygon belongs to, taking the first name in order when it is in several
+fn polygon_labels(groups: &HashMap<String, Group>, count: usize) -> Vec<Option<String>> {
+    let mut labels = vec![None; count];
+    let mut names: Vec<&String> = groups.keys().collect();
+    names.sort();
+    for name in names {
+        for range in groups[name].polygons.iter() {
+            for label in labels[range.start..range.end.min(count)].iter_mut() {
+                if label.is_none() {
+                    *label = Some(name.clone());
+                }
+            }
+        }
+    }
+    labels
+}
+
+// Not normalized so that larger triangles count for more when normals are summed
+fn triangle_cross(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> cgmath::Vector3<f32> {
+    let a = cgmath::Vector3::from(a);
+    (cgmath::Vector3::from(b) - a).cross(cgmath::Vector3::from(c) - a)
+}
+
+fn triangle_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    normalize_or_zero(triangle_cross(a, b, c))
+}
+
+fn normalize_or_zero(v: cgmath::Vector3<f32>) -> [f32; 3] {
+    if v.magnitude2() > 0.0 {
+        v.normalize().into()
+    } else {
+        [0.0, 0.0, 0.0]
+    }
+}
+
+fn smooth_normals(
+    positions: &[[f32; 3]],
+    faces: &[Vec<(usize, Option<usize>, Option<usize>)>],
+) -> Vec<[f32; 3]> {
+    let mut sums = vec![cgmath::Vector3::new(0.0, 0.0, 0.0); positions.len()];
+    for face in faces.iter() {
+        for k in 1..face.len().saturating_sub(1) {
+            let (a, b, c) = (face[0].0, face[k].0, face[k + 1].0);
+            let cross = triangle_cross(positions[a], positions[b], positions[c]);
+            sums[a] += cross;
+            sums[b] += cross;
+            sums[c] += cross;
+        }
+    }
+    sums.into_iter().map(normalize_or_zero).collect()
+}
+
+fn obj_material(name: &str, material: &Material, dir: &Path) -> ObjMaterial {
+    // Only rgb colors are supported, xyz and spectral colors are left out
+    let color = |c: &Option<MtlColor>| match c {
+        Some(MtlColor::Rgb(r, g, b)) => Some([*r, *g, *b]),
+        _ => None,
+    };
+    let map = |m: &Option<MtlTextureMap>| m.as_ref().map(|m| dir.join(&m.file));
+
+    ObjMaterial {
+        name: name.to_string(),
+        ambient: color(&material.ambient),
+        diffuse: color(&material.diffuse),
+        specular: color(&material.specular),
+        emissive: color(&material.emissive),
+        specular_exponent: material.specular_exponent,
+        dissolve: material.dissolve,
+        ambient_map: map(&material.ambient_map),
+        diffuse_map: map(&material.diffuse_map),
+        specular_map: map(&material.specular_map),
+        bump_map: map(&material.bump_map),
+    }
+}
+
 pub fn load_cube() -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u16>) {
     let positions = vec![
         [-1.0, -1.0, 1.0],
@@ -88,6 +400,272 @@ pub fn load_cube() -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u16>) {
     (positions, normals, index_data)
 }
 
+// Positions, normals, texture coordinates and indicies of a generated shape
+// Shapes are centered on the origin with z pointing up like the view from generate_view_matrix
+// Triangles are wound like the faces of an obj file after load_model, which is clockwise when seen from the outside
+pub type GeneratedMesh = (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<[f32; 2]>, Vec<u16>);
+
+fn check_vertex_count(shape: &str, count: usize) {
+    if count > u16::MAX as usize + 1 {
+        panic!(
+            "The {} would have {} vertices which is too many for u16 indicies, use fewer subdivisions",
+            shape, count
+        )
+    }
+}
+
+// A surface made of columns by rows of quads where vertex gives the position and normal at (u, t)
+// u and t both go from 0 to 1 and the texture coordinate is (u, 1 - t) so that t = 1 is the top of the texture
+// The cross product of the directions of increasing u and increasing t needs to point outside
+fn parametric_grid(
+    shape: &str,
+    columns: u32,
+    rows: u32,
+    vertex: impl Fn(f32, f32) -> ([f32; 3], [f32; 3]),
+) -> GeneratedMesh {
+    check_vertex_count(shape, ((columns + 1) * (rows + 1)) as usize);
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    for j in 0..=rows {
+        for i in 0..=columns {
+            let (u, t) = (i as f32 / columns as f32, j as f32 / rows as f32);
+            let (position, normal) = vertex(u, t);
+            positions.push(position);
+            normals.push(normal);
+            uvs.push([u, 1.0 - t]);
+        }
+    }
+
+    let mut indicies = Vec::new();
+    for j in 0..rows {
+        for i in 0..columns {
+            let a = (j * (columns + 1) + i) as u16;
+            let b = a + 1;
+            let c = a + columns as u16 + 1;
+            let d = c + 1;
+            indicies.extend_from_slice(&[a, d, b, a, c, d]);
+        }
+    }
+    (positions, normals, uvs, indicies)
+}
+
+// A flat disk at height z facing up or down, added onto a shape that has already been generated
+fn add_cap(mesh: &mut GeneratedMesh, shape: &str, radius: f32, z: f32, segments: u32, up: bool) {
+    let (positions, normals, uvs, indicies) = mesh;
+    check_vertex_count(shape, positions.len() + segments as usize + 2);
+
+    let normal = [0.0, 0.0, if up { 1.0 } else { -1.0 }];
+    let center = positions.len() as u16;
+    positions.push([0.0, 0.0, z]);
+    normals.push(normal);
+    uvs.push([0.5, 0.5]);
+    for i in 0..=segments {
+        let theta = 2.0 * std::f32::consts::PI * i as f32 / segments as f32;
+        positions.push([radius * theta.cos(), radius * theta.sin(), z]);
+        normals.push(normal);
+        uvs.push([0.5 + 0.5 * theta.cos(), 0.5 - 0.5 * theta.sin()]);
+    }
+    for i in 0..segments as u16 {
+        let (a, b) = (center + 1 + i, center + 2 + i);
+        if up {
+            indicies.extend_from_slice(&[center, b, a]);
+        } else {
+            indicies.extend_from_slice(&[center, a, b]);
+        }
+    }
+}
+
+// A width by height rectangle facing up, split into a grid of quads
+pub fn generate_plane(width: f32, height: f32, columns: u32, rows: u32) -> GeneratedMesh {
+    if columns == 0 || rows == 0 {
+        panic!("A plane needs at least one column and one row of quads")
+    }
+    parametric_grid("plane", columns, rows, |u, t| {
+        (
+            [(u - 0.5) * width, (t - 0.5) * height, 0.0],
+            [0.0, 0.0, 1.0],
+        )
+    })
+}
+
+// A sphere split into segments around its middle and rings from pole to pole
+pub fn generate_uv_sphere(radius: f32, segments: u32, rings: u32) -> GeneratedMesh {
+    if segments < 3 || rings < 2 {
+        panic!("A uv sphere needs at least 3 segments and 2 rings")
+    }
+    parametric_grid("uv sphere", segments, rings, |u, t| {
+        let theta = 2.0 * std::f32::consts::PI * u;
+        let phi = std::f32::consts::PI * (t - 0.5);
+        let normal = [phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin()];
+        (
+            [radius * normal[0], radius * normal[1], radius * normal[2]],
+            normal,
+        )
+    })
+}
+
+// A sphere made by splitting every triangle of an icosahedron into four subdivisions times
+// Texture coordinates are wrapped around the z axis so there is a seam where they go from 1 back to 0
+pub fn generate_icosphere(radius: f32, subdivisions: u32) -> GeneratedMesh {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut points: Vec<cgmath::Vector3<f32>> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .iter()
+    .map(|&p| cgmath::Vector3::from(p).normalize())
+    .collect();
+
+    #[rustfmt::skip]
+    let mut triangles: Vec<[u16; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        check_vertex_count("icosphere", points.len() + triangles.len() * 3 / 2);
+        // Edges are shared by two triangles so each midpoint is only added once
+        let mut midpoints: HashMap<(u16, u16), u16> = HashMap::new();
+        let mut midpoint = |a: u16, b: u16| -> u16 {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push((points[a as usize] + points[b as usize]).normalize());
+                (points.len() - 1) as u16
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                vec![[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let positions = points.iter().map(|p| (p * radius).into()).collect();
+    let normals = points.iter().map(|&p| p.into()).collect();
+    let uvs = points
+        .iter()
+        .map(|p| {
+            [
+                0.5 + p.y.atan2(p.x) / (2.0 * std::f32::consts::PI),
+                0.5 - p.z.asin() / std::f32::consts::PI,
+            ]
+        })
+        .collect();
+    // The triangles above are counter clockwise from the outside so they are reversed like load_model does
+    let mut indicies: Vec<u16> = triangles.into_iter().flatten().collect();
+    indicies.reverse();
+    (positions, normals, uvs, indicies)
+}
+
+// A cylinder standing on the z axis with a cap on each end
+pub fn generate_cylinder(radius: f32, height: f32, segments: u32, rows: u32) -> GeneratedMesh {
+    if segments < 3 || rows == 0 {
+        panic!("A cylinder needs at least 3 segments and 1 row")
+    }
+    let mut mesh = parametric_grid("cylinder", segments, rows, |u, t| {
+        let theta = 2.0 * std::f32::consts::PI * u;
+        (
+            [
+                radius * theta.cos(),
+                radius * theta.sin(),
+                (t - 0.5) * height,
+            ],
+            [theta.cos(), theta.sin(), 0.0],
+        )
+    });
+    add_cap(&mut mesh, "cylinder", radius, height / 2.0, segments, true);
+    add_cap(
+        &mut mesh,
+        "cylinder",
+        radius,
+        -height / 2.0,
+        segments,
+        false,
+    );
+    mesh
+}
+
+// A cone standing on the z axis with its point at the top and a cap on the bottom
+pub fn generate_cone(radius: f32, height: f32, segments: u32, rows: u32) -> GeneratedMesh {
+    if segments < 3 || rows == 0 {
+        panic!("A cone needs at least 3 segments and 1 row")
+    }
+    let slope = (height * height + radius * radius).sqrt();
+    let mut mesh = parametric_grid("cone", segments, rows, |u, t| {
+        let theta = 2.0 * std::f32::consts::PI * u;
+        (
+            [
+                (1.0 - t) * radius * theta.cos(),
+                (1.0 - t) * radius * theta.sin(),
+                (t - 0.5) * height,
+            ],
+            [
+                height * theta.cos() / slope,
+                height * theta.sin() / slope,
+                radius / slope,
+            ],
+        )
+    });
+    add_cap(&mut mesh, "cone", radius, -height / 2.0, segments, false);
+    mesh
+}
+
+// A ring around the z axis where major_radius is the distance to the center of the tube
+pub fn generate_torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> GeneratedMesh {
+    if major_segments < 3 || minor_segments < 3 {
+        panic!("A torus needs at least 3 segments around the ring and around the tube")
+    }
+    parametric_grid("torus", major_segments, minor_segments, |u, t| {
+        let theta = 2.0 * std::f32::consts::PI * u;
+        let phi = 2.0 * std::f32::consts::PI * t;
+        let distance = major_radius + minor_radius * phi.cos();
+        (
+            [
+                distance * theta.cos(),
+                distance * theta.sin(),
+                minor_radius * phi.sin(),
+            ],
+            [phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin()],
+        )
+    })
+}
+
+// Two triangles covering the screen when positions are used directly as gl_Position
+// Unlike the shapes these are counter clockwise on the screen, so the back face culling of the pipelines keeps them
+pub fn generate_full_screen_quad() -> GeneratedMesh {
+    (
+        vec![
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ],
+        vec![[0.0, 0.0, 1.0]; 4],
+        vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+        vec![0, 1, 2, 2, 3, 0],
+    )
+}
+
 #[rustfmt::skip]
 macro_rules! mx_correction {
     () => {
@@ -117,6 +695,11 @@ pub fn generate_projection_matrix(aspect_ratio: f32) -> cgmath::Matrix4<f32> {
     mx_correction!() * mx_projection
 }
 
+// Maps the -1 to 1 depth of the projections from cgmath onto the 0 to 1 depth that wgpu uses
+pub fn depth_correction_matrix() -> cgmath::Matrix4<f32> {
+    mx_correction!()
+}
+
 pub fn generate_identity_matrix() -> cgmath::Matrix4<f32> {
     use cgmath::SquareMatrix;
     cgmath::Matrix4::identity()
@@ -169,3 +752,345 @@ pub fn create_texels(size: usize) -> Vec<u8> {
         })
         .collect()
 }
+
+// Coordinate frames that points and transforms are tagged with so that mixing up spaces doesn't compile
+pub trait Frame: Copy + fmt::Debug {}
+
+// The space a mesh is modeled in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Model;
+// The space every object of a scene is placed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct World;
+// The space where the camera is at the origin looking down -z
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View;
+// The space gl_Position is given in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip;
+
+impl Frame for Model {}
+impl Frame for World {}
+impl Frame for View {}
+impl Frame for Clip {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point<F: Frame> {
+    pub value: cgmath::Point3<f32>,
+    frame: PhantomData<F>,
+}
+
+impl<F: Frame> Point<F> {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Point::from(cgmath::Point3::new(x, y, z))
+    }
+
+    pub fn to_array(&self) -> [f32; 3] {
+        [self.value.x, self.value.y, self.value.z]
+    }
+}
+
+impl<F: Frame> From<cgmath::Point3<f32>> for Point<F> {
+    fn from(value: cgmath::Point3<f32>) -> Self {
+        Point {
+            value,
+            frame: PhantomData,
+        }
+    }
+}
+
+// A direction which unlike a point isn't moved by the translation of a transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector<F: Frame> {
+    pub value: cgmath::Vector3<f32>,
+    frame: PhantomData<F>,
+}
+
+impl<F: Frame> Vector<F> {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Vector::from(cgmath::Vector3::new(x, y, z))
+    }
+}
+
+impl<F: Frame> From<cgmath::Vector3<f32>> for Vector<F> {
+    fn from(value: cgmath::Vector3<f32>) -> Self {
+        Vector {
+            value,
+            frame: PhantomData,
+        }
+    }
+}
+
+// A matrix that takes points in the From frame to the To frame
+// Transforms only multiply when the frame one goes to is the frame the other comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform<From: Frame, To: Frame> {
+    pub matrix: cgmath::Matrix4<f32>,
+    frame: PhantomData<(From, To)>,
+}
+
+impl<From: Frame, To: Frame> Transform<From, To> {
+    // Nothing checks that the matrix really goes between these frames so this is where they are decided
+    pub fn new(matrix: cgmath::Matrix4<f32>) -> Self {
+        Transform {
+            matrix,
+            frame: PhantomData,
+        }
+    }
+
+    // Apply this transform and then next
+    pub fn then<Next: Frame>(self, next: Transform<To, Next>) -> Transform<From, Next> {
+        next * self
+    }
+
+    pub fn invert(&self) -> Transform<To, From> {
+        use cgmath::SquareMatrix;
+        Transform::new(
+            self.matrix
+                .invert()
+                .expect("This transform can't be inverted since it flattens space"),
+        )
+    }
+}
+
+impl<F: Frame> Transform<F, F> {
+    pub fn identity() -> Self {
+        Transform::new(generate_identity_matrix())
+    }
+}
+
+impl<A: Frame, B: Frame, C: Frame> Mul<Transform<A, B>> for Transform<B, C> {
+    type Output = Transform<A, C>;
+
+    fn mul(self, rhs: Transform<A, B>) -> Transform<A, C> {
+        Transform::new(self.matrix * rhs.matrix)
+    }
+}
+
+impl<A: Frame, B: Frame> Mul<Point<A>> for Transform<A, B> {
+    type Output = Point<B>;
+
+    fn mul(self, rhs: Point<A>) -> Point<B> {
+        use cgmath::Transform as _;
+        Point::from(self.matrix.transform_point(rhs.value))
+    }
+}
+
+impl<A: Frame, B: Frame> Mul<Vector<A>> for Transform<A, B> {
+    type Output = Vector<B>;
+
+    fn mul(self, rhs: Vector<A>) -> Vector<B> {
+        use cgmath::Transform as _;
+        Vector::from(self.matrix.transform_vector(rhs.value))
+    }
+}
+
+pub fn model_to_world(matrix: cgmath::Matrix4<f32>) -> Transform<Model, World> {
+    Transform::new(matrix)
+}
+
+// generate_view_matrix tagged as going from the world to the view
+pub fn generate_view_transform() -> Transform<World, View> {
+    Transform::new(generate_view_matrix())
+}
+
+// generate_projection_matrix tagged as going from the view to clip space
+pub fn generate_projection_transform(aspect_ratio: f32) -> Transform<View, Clip> {
+    Transform::new(generate_projection_matrix(aspect_ratio))
+}
+
+// Transforms bind to mat4 parameters the same way their matrix would
+impl<From: Frame, To: Frame> Bindable for Transform<From, To> {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        self.matrix
+            .write(program, bindings, out_bindings, name, mode)
+    }
+}
+
+impl<From: Frame, To: Frame> UniformValue for Transform<From, To> {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        cgmath::Matrix4::<f32>::acceptable_types()
+    }
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        self.matrix.uniform_bytes()
+    }
+}
+
+// Points bind to vec3 parameters
+impl<F: Frame> Bindable for Point<F> {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        vec![self.to_array()].write(program, bindings, out_bindings, name, mode)
+    }
+}
+
+impl<F: Frame> Bindable for Vec<Point<F>> {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        let values: Vec<[f32; 3]> = self.iter().map(Point::to_array).collect();
+        values.write(program, bindings, out_bindings, name, mode)
+    }
+}
+
+impl<F: Frame> UniformValue for Point<F> {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        <[f32; 3]>::acceptable_types()
+    }
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        self.to_array().uniform_bytes()
+    }
+}
+
+// Directions bind to vec3 parameters the same way points do
+impl<F: Frame> Bindable for Vector<F> {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        vec![[self.value.x, self.value.y, self.value.z]].write(
+            program,
+            bindings,
+            out_bindings,
+            name,
+            mode,
+        )
+    }
+}
+
+impl<F: Frame> UniformValue for Vector<F> {
+    fn acceptable_types() -> Vec<GLSLTYPE> {
+        <[f32; 3]>::acceptable_types()
+    }
+
+    fn uniform_bytes(&self) -> Vec<u8> {
+        [self.value.x, self.value.y, self.value.z].uniform_bytes()
+    }
+}
+
+// The frames a value is tagged with, as a tuple in the order they are written in a shader! declaration
+// A parameter declared like [[uniform in] mat4<model, world>] u_model only binds values with HasFrames<(Model, World)>
+pub trait HasFrames<F> {}
+
+impl<From: Frame, To: Frame> HasFrames<(From, To)> for Transform<From, To> {}
+impl<F: Frame> HasFrames<(F,)> for Point<F> {}
+impl<F: Frame> HasFrames<(F,)> for Vec<Point<F>> {}
+impl<F: Frame> HasFrames<(F,)> for Vector<F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether the triangles that aren't degenerate are counter clockwise when seen from the side the normals of their corners face
+    // None when the triangles don't all agree
+    fn winding(positions: &[[f32; 3]], normals: &[[f32; 3]], indicies: &[u16]) -> Option<bool> {
+        let mut counter_clockwise = None;
+        for triangle in indicies.chunks(3) {
+            let [a, b, c] = [
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            ];
+            let cross = triangle_cross(positions[a], positions[b], positions[c]);
+            if cross.magnitude2() < 1e-12 {
+                continue;
+            }
+            let normal = cgmath::Vector3::from(normals[a])
+                + cgmath::Vector3::from(normals[b])
+                + cgmath::Vector3::from(normals[c]);
+            let facing = cross.dot(normal) > 0.0;
+            if *counter_clockwise.get_or_insert(facing) != facing {
+                return None;
+            }
+        }
+        counter_clockwise
+    }
+
+    // Faces of an obj file are counter clockwise from the outside, and load_model reverses them
+    fn check_winding(shape: &str, positions: &[[f32; 3]], normals: &[[f32; 3]], indicies: &[u16]) {
+        assert_eq!(
+            winding(positions, normals, indicies),
+            Some(false),
+            "The triangles of the {} aren't all wound like load_model leaves them",
+            shape
+        );
+    }
+
+    fn check_generated(shape: &str, mesh: GeneratedMesh) {
+        let (positions, normals, _, indicies) = mesh;
+        check_winding(shape, &positions, &normals, &indicies);
+    }
+
+    // Two counter clockwise triangles facing up, load_model only reads triangles
+    const SQUARE: &str =
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\nf 1//1 3//1 4//1\n";
+
+    #[test]
+    fn generated_shapes_are_wound_like_load_model() {
+        check_generated("plane", generate_plane(2.0, 3.0, 4, 5));
+        check_generated("uv sphere", generate_uv_sphere(1.0, 16, 8));
+        check_generated("icosphere", generate_icosphere(1.0, 2));
+        check_generated("cylinder", generate_cylinder(1.0, 2.0, 12, 3));
+        check_generated("cone", generate_cone(1.0, 2.0, 12, 3));
+        check_generated("torus", generate_torus(2.0, 0.5, 16, 8));
+    }
+
+    #[test]
+    fn full_screen_quad_faces_the_screen() {
+        let (positions, normals, _, indicies) = generate_full_screen_quad();
+        assert_eq!(winding(&positions, &normals, &indicies), Some(true));
+    }
+
+    #[test]
+    fn obj_loaders_reverse_the_winding_of_the_file() {
+        let path = std::env::temp_dir().join(format!("winding_{}.obj", std::process::id()));
+        std::fs::write(&path, SQUARE).unwrap();
+        let (positions, normals, indicies) = load_model(path.to_str().unwrap());
+        let model = load_obj_model(path.to_str().unwrap(), GenerateNormals::Flat).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        check_winding("obj", &positions, &normals, &indicies);
+        assert_eq!(model.indicies.len(), 6);
+        assert_eq!(model.positions[model.indicies[1] as usize], [1.0, 1.0, 0.0]);
+        check_winding("obj", &model.positions, &model.normals, &model.indicies);
+    }
+
+    #[test]
+    fn generated_obj_normals_face_outside() {
+        let path = std::env::temp_dir().join(format!("normals_{}.obj", std::process::id()));
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        let flat = load_obj_model(path.to_str().unwrap(), GenerateNormals::Flat).unwrap();
+        let smooth = load_obj_model(path.to_str().unwrap(), GenerateNormals::Smooth).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for normal in flat.normals.iter().chain(smooth.normals.iter()) {
+            assert_eq!(*normal, [0.0, 0.0, 1.0]);
+        }
+        check_winding("obj", &flat.positions, &flat.normals, &flat.indicies);
+        check_winding("obj", &smooth.positions, &smooth.normals, &smooth.indicies);
+    }
+}
@@ -3,9 +3,16 @@
 #![feature(const_mut_refs, unsized_locals)]
 #[macro_use]
 pub mod debug;
+pub mod camera;
+pub mod capture;
 pub mod context;
+pub mod cpu_compute;
+pub mod gltf_import;
+pub mod golden;
 pub mod helper;
 pub mod bind;
+pub mod mesh;
+pub mod scene;
 pub mod shared;
 
 pub mod wgpu_compute_header;
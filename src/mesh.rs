@@ -0,0 +1,219 @@
+use zerocopy::AsBytes as _;
+
+use std::sync::Arc;
+
+use crate::bind::{
+    bind_shared_buffer, buffer_usage, Bindable, OutProgramBindings, ProgramBindings, WriteMode,
+};
+use crate::helper::{GeneratedMesh, ObjModel};
+use crate::shared::{Program, GLSLTYPE, QUALIFIER};
+
+// A vertex attribute of a mesh that already lives in a buffer on the gpu
+#[derive(Debug)]
+pub struct MeshAttribute {
+    pub name: String,
+    pub gtype: GLSLTYPE,
+    buffer: Arc<wgpu::Buffer>,
+    length: u64,
+    size: u64,
+}
+
+// Vertex attributes and indicies that are uploaded once and can then be drawn by any program on the same device
+// Binding a mesh shares its buffers with the program instead of copying them
+#[derive(Debug, Default)]
+pub struct Mesh {
+    attributes: Vec<MeshAttribute>,
+    indicies: Option<(Arc<wgpu::Buffer>, u32)>,
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Mesh::default()
+    }
+
+    // The output of load_model or load_cube with the attributes named a_position and a_normal
+    pub fn from_model(
+        program: &dyn Program,
+        (positions, normals, indicies): (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u16>),
+    ) -> Self {
+        let mut mesh = Mesh::new();
+        mesh.add_vec3(program, "a_position", &positions);
+        mesh.add_vec3(program, "a_normal", &normals);
+        mesh.set_indicies(program, &indicies);
+        mesh
+    }
+
+    // An obj model with the attributes named a_position, a_normal and a_TexCoord when it has texture coordinates
+    pub fn from_obj(program: &dyn Program, model: &ObjModel) -> Self {
+        let mut mesh = Mesh::new();
+        mesh.add_vec3(program, "a_position", &model.positions);
+        mesh.add_vec3(program, "a_normal", &model.normals);
+        if !model.uvs.is_empty() {
+            mesh.add_vec2(program, "a_TexCoord", &model.uvs);
+        }
+        mesh.set_indicies(program, &model.indicies);
+        mesh
+    }
+
+    // A shape from one of the generate functions of helper with the attributes named a_position, a_normal and a_TexCoord
+    pub fn from_generated(
+        program: &dyn Program,
+        (positions, normals, uvs, indicies): GeneratedMesh,
+    ) -> Self {
+        let mut mesh = Mesh::new();
+        mesh.add_vec3(program, "a_position", &positions);
+        mesh.add_vec3(program, "a_normal", &normals);
+        mesh.add_vec2(program, "a_TexCoord", &uvs);
+        mesh.set_indicies(program, &indicies);
+        mesh
+    }
+
+    pub fn add_float(&mut self, program: &dyn Program, name: &str, data: &Vec<f32>) {
+        self.add(
+            program,
+            name,
+            data.as_slice().as_bytes(),
+            data.len() as u64,
+            GLSLTYPE::Float,
+        )
+    }
+
+    pub fn add_vec2(&mut self, program: &dyn Program, name: &str, data: &Vec<[f32; 2]>) {
+        let numbers: Vec<f32> = data.iter().flatten().cloned().collect();
+        self.add(
+            program,
+            name,
+            numbers.as_slice().as_bytes(),
+            data.len() as u64,
+            GLSLTYPE::Vec2,
+        )
+    }
+
+    pub fn add_vec3(&mut self, program: &dyn Program, name: &str, data: &Vec<[f32; 3]>) {
+        let numbers: Vec<f32> = data.iter().flatten().cloned().collect();
+        self.add(
+            program,
+            name,
+            numbers.as_slice().as_bytes(),
+            data.len() as u64,
+            GLSLTYPE::Vec3,
+        )
+    }
+
+    pub fn add_vec4(&mut self, program: &dyn Program, name: &str, data: &Vec<[f32; 4]>) {
+        let numbers: Vec<f32> = data.iter().flatten().cloned().collect();
+        self.add(
+            program,
+            name,
+            numbers.as_slice().as_bytes(),
+            data.len() as u64,
+            GLSLTYPE::Vec4,
+        )
+    }
+
+    fn add(
+        &mut self,
+        program: &dyn Program,
+        name: &str,
+        data: &[u8],
+        length: u64,
+        gtype: GLSLTYPE,
+    ) {
+        if self.attributes.iter().any(|a| a.name == name) {
+            panic!("The mesh already has an attribute named {}", name)
+        }
+        if let Some(first) = self.attributes.first() {
+            if first.length != length {
+                panic!(
+                    "{} has {} vertices but {} has {}, every attribute of a mesh needs the same number of vertices",
+                    name, length, first.name, first.length
+                )
+            }
+        }
+
+        let buffer = program
+            .get_device()
+            .create_buffer_with_data(data, buffer_usage(&[QUALIFIER::VERTEX]));
+
+        self.attributes.push(MeshAttribute {
+            name: name.to_string(),
+            gtype,
+            buffer: Arc::new(buffer),
+            length,
+            size: data.len() as u64,
+        })
+    }
+
+    pub fn set_indicies(&mut self, program: &dyn Program, indicies: &Vec<u16>) {
+        let buffer = program
+            .get_device()
+            .create_buffer_with_data(indicies.as_slice().as_bytes(), wgpu::BufferUsage::INDEX);
+
+        self.indicies = Some((Arc::new(buffer), indicies.len() as u32));
+    }
+
+    pub fn indicies(&self) -> Option<(&Arc<wgpu::Buffer>, u32)> {
+        self.indicies.as_ref().map(|(buffer, len)| (buffer, *len))
+    }
+
+    // The number of vertices in each attribute
+    pub fn vertex_count(&self) -> u64 {
+        self.attributes.first().map_or(0, |a| a.length)
+    }
+
+    // Use this to bind an attribute to a parameter with a different name
+    pub fn attribute(&self, name: &str) -> &MeshAttribute {
+        self.attributes
+            .iter()
+            .find(|a| a.name == name)
+            .unwrap_or_else(|| {
+                panic!(
+                    "The mesh has no attribute named {}, it has {:?}",
+                    name,
+                    self.attributes.iter().map(|a| &a.name).collect::<Vec<_>>()
+                )
+            })
+    }
+}
+
+impl Bindable for MeshAttribute {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        _program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        if let WriteMode::Update(_) = mode {
+            panic!(
+                "{} can't be updated with a mesh attribute, bind it again instead",
+                name
+            )
+        }
+        bind_shared_buffer(
+            bindings,
+            out_bindings,
+            &self.buffer,
+            self.length,
+            self.size,
+            vec![self.gtype.clone()],
+            &name,
+        )
+    }
+}
+
+// Binds the attribute of the mesh that has the same name as the parameter
+impl Bindable for Mesh {
+    fn write<R: ProgramBindings, T: OutProgramBindings>(
+        &self,
+        program: &dyn Program,
+        bindings: &mut R,
+        out_bindings: &mut T,
+        name: String,
+        mode: WriteMode,
+    ) {
+        self.attribute(&name)
+            .write(program, bindings, out_bindings, name, mode)
+    }
+}
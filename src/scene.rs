@@ -0,0 +1,234 @@
+use cgmath::{Matrix, Matrix4, One, Quaternion, SquareMatrix, Vector3, Zero};
+
+// A translation, rotation and scale which are applied to a node in the reverse of that order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trs {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Trs {
+    fn default() -> Self {
+        Trs {
+            translation: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Trs {
+    pub fn from_translation(x: f32, y: f32, z: f32) -> Self {
+        Trs {
+            translation: Vector3::new(x, y, z),
+            ..Trs::default()
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug)]
+struct Node<M> {
+    transform: Trs,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    mesh: Option<M>,
+    world: Matrix4<f32>,
+    normal: Matrix4<f32>,
+    // The transform changed since the world matrix was last worked out
+    dirty: bool,
+}
+
+// A node that has a mesh, placed by its own transform and the transforms of all of its parents
+#[derive(Debug)]
+pub struct SceneDraw<'a, M> {
+    pub node: NodeId,
+    pub mesh: &'a M,
+    pub world: Matrix4<f32>,
+    // The inverse transpose of world which keeps normals at right angles to surfaces that are scaled unevenly
+    // Multiply it with a normal that has a w of 0
+    pub normal: Matrix4<f32>,
+}
+
+// Nodes with a transform relative to their parent and optionally something to draw there
+// The mesh can be any type such as a Mesh, an index into a list of meshes or the values to bind for a draw
+#[derive(Debug)]
+pub struct SceneGraph<M> {
+    nodes: Vec<Node<M>>,
+    roots: Vec<NodeId>,
+}
+
+impl<M> Default for SceneGraph<M> {
+    fn default() -> Self {
+        SceneGraph {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+}
+
+impl<M> SceneGraph<M> {
+    pub fn new() -> Self {
+        SceneGraph::default()
+    }
+
+    pub fn add(&mut self, parent: Option<NodeId>, transform: Trs, mesh: Option<M>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            transform,
+            parent: None,
+            children: Vec::new(),
+            mesh,
+            world: Matrix4::identity(),
+            normal: Matrix4::identity(),
+            dirty: true,
+        });
+        self.roots.push(id);
+        self.set_parent(id, parent);
+        id
+    }
+
+    fn node(&self, id: NodeId) -> &Node<M> {
+        self.nodes
+            .get(id.0)
+            .unwrap_or_else(|| panic!("{:?} is not a node of this scene", id))
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node<M> {
+        self.nodes
+            .get_mut(id.0)
+            .unwrap_or_else(|| panic!("{:?} is not a node of this scene", id))
+    }
+
+    // Move a node and everything below it under a new parent, or to the top of the scene with None
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == id {
+                panic!(
+                    "{:?} can't be moved under itself or one of its children",
+                    id
+                )
+            }
+            ancestor = self.node(a).parent;
+        }
+
+        match self.node(id).parent {
+            Some(old) => self.node_mut(old).children.retain(|c| *c != id),
+            None => self.roots.retain(|r| *r != id),
+        }
+        match parent {
+            Some(p) => self.node_mut(p).children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    pub fn transform(&self, id: NodeId) -> &Trs {
+        &self.node(id).transform
+    }
+
+    // The world matrices of this node and its children are worked out again by the next update
+    pub fn transform_mut(&mut self, id: NodeId) -> &mut Trs {
+        let node = self.node_mut(id);
+        node.dirty = true;
+        &mut node.transform
+    }
+
+    pub fn mesh(&self, id: NodeId) -> Option<&M> {
+        self.node(id).mesh.as_ref()
+    }
+
+    pub fn set_mesh(&mut self, id: NodeId, mesh: Option<M>) {
+        self.node_mut(id).mesh = mesh;
+    }
+
+    // Work out the world and normal matrices of every node whose transform, or a parent's transform, changed
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, Matrix4<f32>, bool)> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|r| (*r, Matrix4::identity(), false))
+            .collect();
+
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            let changed = node.dirty || parent_changed;
+            if changed {
+                node.world = parent_world * node.transform.matrix();
+                node.normal = node
+                    .world
+                    .invert()
+                    .map_or_else(Matrix4::zero, |inverse| inverse.transpose());
+                node.dirty = false;
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().rev().map(|c| (*c, world, changed)));
+        }
+    }
+
+    fn check_updated(&self, id: NodeId) {
+        let mut node = Some(id);
+        while let Some(n) = node {
+            if self.node(n).dirty {
+                panic!(
+                    "The transform of {:?} changed since the scene was updated, call update first",
+                    n
+                )
+            }
+            node = self.node(n).parent;
+        }
+    }
+
+    pub fn world_matrix(&self, id: NodeId) -> Matrix4<f32> {
+        self.check_updated(id);
+        self.node(id).world
+    }
+
+    pub fn normal_matrix(&self, id: NodeId) -> Matrix4<f32> {
+        self.check_updated(id);
+        self.node(id).normal
+    }
+
+    // Update the scene and list every node with a mesh, parents before their children
+    // Each draw's world matrix can be given to draw_each as the model matrix
+    pub fn draws(&mut self) -> Vec<SceneDraw<'_, M>> {
+        self.update();
+
+        let mut draws = Vec::new();
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().cloned().collect();
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id.0];
+            if let Some(mesh) = node.mesh.as_ref() {
+                draws.push(SceneDraw {
+                    node: id,
+                    mesh,
+                    world: node.world,
+                    normal: node.normal,
+                });
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        draws
+    }
+}
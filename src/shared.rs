@@ -2,6 +2,10 @@ use glsl_to_spirv::ShaderType;
 use regex::Regex;
 use std::fmt;
 use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use wgpu::ShaderModule;
 
 // Remove spaces between tokens that should be one token
@@ -33,6 +37,99 @@ pub fn compile_shader(contents: String, shader: ShaderType, device: &wgpu::Devic
 
 pub trait Program {
     fn get_device(&self) -> &wgpu::Device;
+    fn get_queue(&self) -> &wgpu::Queue;
+    // Programs run by the cpu executor have no device, data bound to them is kept in host memory instead
+    fn on_cpu(&self) -> bool {
+        false
+    }
+    // The poller of the device, which buffers being read back on it need to keep running
+    fn poller(&self) -> Option<PollHandle> {
+        None
+    }
+}
+
+// How long the poller waits between checks on the device
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// What the thread of a poller shares with everything that is mapping a buffer on its device
+#[derive(Debug, Default)]
+struct PollState {
+    // The poller only polls its device while there are mappings that haven't completed yet
+    pending: AtomicUsize,
+    stop: AtomicBool,
+}
+
+// A handle to the poller of one device, buffers of that device keep one so they can be read back
+#[derive(Debug, Clone)]
+pub struct PollHandle {
+    state: Arc<PollState>,
+    thread: thread::Thread,
+}
+
+impl PollHandle {
+    // Wakes the poller up if it was sleeping
+    pub fn start_mapping(&self) -> PendingMapping {
+        self.state.pending.fetch_add(1, Ordering::SeqCst);
+        self.thread.unpark();
+        PendingMapping(self.clone())
+    }
+}
+
+// Held for as long as a buffer is being mapped so the poller keeps driving its device until it is dropped
+#[derive(Debug)]
+pub struct PendingMapping(PollHandle);
+
+impl Drop for PendingMapping {
+    fn drop(&mut self) {
+        self.0.state.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Drives a device from a background thread so that buffer mappings complete without anyone blocking on device.poll
+// There is one per device, programs compiled onto the device of another share its poller
+// The thread sleeps while no mapping on its device is pending and is stopped when the poller is dropped
+pub struct Poller {
+    handle: PollHandle,
+    join: Option<thread::JoinHandle<()>>,
+}
+
+impl Poller {
+    pub fn new(device: Arc<wgpu::Device>) -> Poller {
+        let state = Arc::new(PollState::default());
+        let thread_state = state.clone();
+        let join = thread::spawn(move || {
+            while !thread_state.stop.load(Ordering::SeqCst) {
+                if thread_state.pending.load(Ordering::SeqCst) == 0 {
+                    // Woken by start_mapping or by dropping the poller
+                    thread::park();
+                    continue;
+                }
+                device.poll(wgpu::Maintain::Poll);
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Poller {
+            handle: PollHandle {
+                state,
+                thread: join.thread().clone(),
+            },
+            join: Some(join),
+        }
+    }
+
+    pub fn handle(&self) -> PollHandle {
+        self.handle.clone()
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.handle.state.stop.store(true, Ordering::SeqCst);
+        self.handle.thread.unpark();
+        if let Some(join) = self.join.take() {
+            join.join().expect("The thread polling the device panicked");
+        }
+    }
 }
 
 // TODO functions to get the rust size and typing
@@ -83,6 +180,43 @@ impl GLSLTYPE {
             GLSLTYPE::Texture2D => panic!("TODO: I haven't checked the size of this yet"),
         }
     }
+
+    // The distance between the elements of an array of this type in a storage buffer
+    // std430 pads a vec3 out to the size of a vec4
+    pub fn stride(&self) -> usize {
+        match self.element_type() {
+            GLSLTYPE::Bool | GLSLTYPE::Float | GLSLTYPE::Int | GLSLTYPE::Uint => 4,
+            GLSLTYPE::Vec2 => 8,
+            GLSLTYPE::Uvec3 | GLSLTYPE::Vec3 | GLSLTYPE::Vec4 => 16,
+            GLSLTYPE::Mat4 => 64,
+            other => panic!("A {} can't be put into a buffer", other),
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        match self {
+            GLSLTYPE::ArrayInt
+            | GLSLTYPE::ArrayUint
+            | GLSLTYPE::ArrayFloat
+            | GLSLTYPE::ArrayVec2
+            | GLSLTYPE::ArrayVec3
+            | GLSLTYPE::ArrayVec4 => true,
+            _ => false,
+        }
+    }
+
+    // The type of a single element of an array, the reverse of array_type
+    pub fn element_type(&self) -> GLSLTYPE {
+        match self {
+            GLSLTYPE::ArrayInt => GLSLTYPE::Int,
+            GLSLTYPE::ArrayUint => GLSLTYPE::Uint,
+            GLSLTYPE::ArrayFloat => GLSLTYPE::Float,
+            GLSLTYPE::ArrayVec2 => GLSLTYPE::Vec2,
+            GLSLTYPE::ArrayVec3 => GLSLTYPE::Vec3,
+            GLSLTYPE::ArrayVec4 => GLSLTYPE::Vec4,
+            x => x.clone(),
+        }
+    }
 }
 
 impl fmt::Display for GLSLTYPE {
@@ -180,6 +314,10 @@ pub enum QUALIFIER {
     IN,
     OUT,
     LOOP,
+    // A uniform with a value for every object that is drawn, see draw_each
+    DYNAMIC,
+    // A vertex attribute that advances once per instance instead of once per vertex
+    INSTANCE,
 }
 
 // I assume there will only be one gl builtin qualifier so find that one and the match should return true
@@ -252,6 +390,12 @@ macro_rules! qualifying {
     (loop) => {
         pipeline::shared::QUALIFIER::LOOP
     };
+    (dynamic) => {
+        pipeline::shared::QUALIFIER::DYNAMIC
+    };
+    (instance) => {
+        pipeline::shared::QUALIFIER::INSTANCE
+    };
 }
 
 #[macro_export]
@@ -315,11 +459,65 @@ pub const fn has_uniform_qual(p: &[QUALIFIER]) -> bool {
     false
 }
 
+pub const fn has_buffer_qual(p: &[QUALIFIER]) -> bool {
+    let mut acc = 0;
+    while acc < p.len() {
+        match p[acc] {
+            QUALIFIER::BUFFER => {
+                return true;
+            }
+            _ => {
+                acc += 1;
+            }
+        }
+    }
+    false
+}
+
 #[derive(Debug)]
 pub struct PARAMETER {
     pub qual: &'static [QUALIFIER],
     pub gtype: GLSLTYPE,
     pub name: &'static str,
+    // The coordinate frames from a declaration like mat4<model, world>, which are empty when none were given
+    pub frames: &'static [&'static str],
+}
+
+// A matrix is declared with the frame it goes from and the frame it goes to, like mat4<model, world>
+// A vector is declared with the one frame it is in, like vec3<view>
+pub const fn valid_frames(params: &[PARAMETER]) {
+    let mut acc = 0;
+    while acc < params.len() {
+        let expected = match params[acc].gtype {
+            GLSLTYPE::Mat4 => 2,
+            GLSLTYPE::Vec2
+            | GLSLTYPE::Vec3
+            | GLSLTYPE::Vec4
+            | GLSLTYPE::ArrayVec2
+            | GLSLTYPE::ArrayVec3
+            | GLSLTYPE::ArrayVec4 => 1,
+            _ => 0,
+        };
+        let count = params[acc].frames.len();
+        if count != 0 && count != expected {
+            panic!("A mat4 takes two frames, the one it goes from and the one it goes to, a vector takes the one frame it is in and other types can't have frames")
+        }
+        acc += 1;
+    }
+}
+
+pub const fn same_frames(a: &[&str], b: &[&str]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut acc = 0;
+    while acc < a.len() {
+        if !string_compare(a[acc], b[acc]) {
+            return false;
+        }
+        acc += 1;
+    }
+    true
 }
 
 // To help view macros
@@ -330,14 +528,17 @@ pub struct PARAMETER {
 // https://doc.rust-lang.org/stable/rust-by-example/macros.html
 #[macro_export]
 macro_rules! shader {
-    ( $([[$($qualifier:tt)*] $type:ident $($brack:tt)*] $param:ident;)*
+    ( $([[$($qualifier:tt)*] $type:ident $(<$($frame:ident),*>)? $([$($dim:tt)*])*] $param:ident;)*
       {$($tt:tt)*}) =>
       {
         {
             const S : &[pipeline::shared::PARAMETER] = &[$(
                 pipeline::shared::PARAMETER{qual:&[$(qualifying!($qualifier)),*],
-                                                      gtype:pipeline::shared::array_type(typing!($type), count_brackets!($($brack)*)),
-                                                      name:stringify!($param)}),*];
+                                                      gtype:pipeline::shared::array_type(typing!($type), count_brackets!($([$($dim)*])*)),
+                                                      name:stringify!($param),
+                                                      frames:&[$($(stringify!($frame)),*)?]}),*];
+
+            const _: () = pipeline::shared::valid_frames(S);
 
 
             const B: &'static str = munch_body!($($tt)*);
@@ -4,15 +4,34 @@ use std::collections::HashMap;
 
 use std::convert::TryInto;
 
+use std::future::Future;
+
+use std::pin::Pin;
+
+use std::sync::Arc;
+
+use std::task::{Context, Poll};
+
+use futures::FutureExt;
+
+use zerocopy::AsBytes as _;
+
 use crate::shared::{
-    check_gl_builtin_type, compile_shader, process_body, Program, PARAMETER, QUALIFIER,
+    check_gl_builtin_type, compile_shader, process_body, PendingMapping, PollHandle, Poller,
+    Program, GLSLTYPE, PARAMETER, QUALIFIER,
 };
 
-use crate::bind::{new_bindings, Bindings, DefaultBinding, OutProgramBindings, ProgramBindings};
+use crate::bind::{
+    new_bindings, pipe_bindings, resource_key, BindGroupCache, Bindings, DefaultBinding,
+    OutProgramBindings, ProgramBindings, ResourceKey,
+};
 
 #[derive(Debug)]
 pub struct ComputeBindings {
     pub bindings: Vec<DefaultBinding>,
+    bind_groups: BindGroupCache,
+    // The buffer holding the base of every pass of a run and its size
+    dispatch_params: Option<(Arc<wgpu::Buffer>, u64)>,
 }
 
 #[derive(Debug)]
@@ -28,6 +47,9 @@ impl OutComputeBindings {
 
             new_binds[bind_pos].data = std::mem::replace(&mut i.data, None);
             new_binds[bind_pos].length = std::mem::replace(&mut i.length, None);
+            new_binds[bind_pos].size = std::mem::replace(&mut i.size, None);
+            new_binds[bind_pos].host = std::mem::replace(&mut i.host, None);
+            new_binds[bind_pos].poller = std::mem::replace(&mut i.poller, None);
         }
         OutComputeBindings {
             bindings: new_binds,
@@ -39,6 +61,8 @@ impl Bindings for ComputeBindings {
     fn clone(&self) -> ComputeBindings {
         ComputeBindings {
             bindings: new_bindings(&self.bindings),
+            bind_groups: BindGroupCache::default(),
+            dispatch_params: None,
         }
     }
 }
@@ -70,22 +94,36 @@ impl OutProgramBindings for OutComputeBindings {
 }
 
 pub struct ComputeProgram {
-    pub device: wgpu::Device,
-    queue: wgpu::Queue,
+    pub device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
     pipeline: wgpu::ComputePipeline,
     bind_group_layout: wgpu::BindGroupLayout,
+    // Keeps the device running so results can be read back, shared by every program on the device
+    poller: Arc<Poller>,
 }
 
 impl Program for ComputeProgram {
     fn get_device(&self) -> &wgpu::Device {
         &self.device
     }
+    fn get_queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+    fn poller(&self) -> Option<PollHandle> {
+        Some(self.poller.handle())
+    }
 }
 
 impl Program for &ComputeProgram {
     fn get_device(&self) -> &wgpu::Device {
         &self.device
     }
+    fn get_queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+    fn poller(&self) -> Option<PollHandle> {
+        Some(self.poller.handle())
+    }
 }
 
 fn stringify_shader(s: &ComputeShader, b: &ComputeBindings, b_out: &OutComputeBindings) -> String {
@@ -120,6 +158,17 @@ fn stringify_shader(s: &ComputeShader, b: &ComputeBindings, b_out: &OutComputeBi
             buffer.push("};\n".to_string());
         }
     }
+    // Runs that are split into several passes start each pass at pipeline_base within the bound window
+    // and pipeline_window_base is where the window starts in the whole run
+    // The macro doesn't expand inside itself so it still refers to the builtin
+    buffer.push(format!(
+        "layout(binding = {}) uniform PIPELINE_DISPATCH {{\nuint pipeline_base;\nuint pipeline_window_base;\n}};\n",
+        b.bindings.len() + b_out.bindings.len()
+    ));
+    buffer.push(
+        "#define gl_GlobalInvocationID (gl_GlobalInvocationID + uvec3(pipeline_base, 0, 0))\n"
+            .to_string(),
+    );
     format!(
         //todo figure out how to use a non-1 local size
         "#version 450\nlayout(local_size_x = 1) in;\n{}\n\n{}\n",
@@ -128,10 +177,8 @@ fn stringify_shader(s: &ComputeShader, b: &ComputeBindings, b_out: &OutComputeBi
     )
 }
 
-fn create_bindings(
-    compute: &ComputeShader,
-    device: &wgpu::Device,
-) -> (wgpu::BindGroupLayout, ComputeBindings, OutComputeBindings) {
+// The bindings of every parameter of a compute shader, which don't need a device until data is bound
+pub fn compute_bindings(compute: &ComputeShader) -> (ComputeBindings, OutComputeBindings) {
     let mut binding_struct: Vec<DefaultBinding> = Vec::new();
     let mut binding_number = 0;
     let mut out_binding_struct: Vec<DefaultBinding> = Vec::new();
@@ -144,6 +191,9 @@ fn create_bindings(
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
@@ -155,6 +205,9 @@ fn create_bindings(
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
@@ -163,12 +216,33 @@ fn create_bindings(
         }
     }
 
+    (
+        ComputeBindings {
+            bindings: binding_struct,
+            bind_groups: BindGroupCache::default(),
+            dispatch_params: None,
+        },
+        OutComputeBindings {
+            bindings: out_binding_struct,
+        },
+    )
+}
+
+fn create_bindings(
+    compute: &ComputeShader,
+    device: &wgpu::Device,
+) -> (wgpu::BindGroupLayout, ComputeBindings, OutComputeBindings) {
+    let (program_bindings, out_program_bindings) = compute_bindings(compute);
+    let binding_struct = &program_bindings.bindings;
+    let out_binding_struct = &out_program_bindings.bindings;
+    let binding_number = (binding_struct.len() + out_binding_struct.len()) as u32;
+
     // Create a layout for our bindings
     // If we had textures we would use this to lay them out
 
     let mut bind_entry = Vec::new();
 
-    for i in &binding_struct {
+    for i in binding_struct {
         bind_entry.push(wgpu::BindGroupLayoutEntry {
             binding: i.binding_number,
             visibility: wgpu::ShaderStage::COMPUTE,
@@ -183,7 +257,7 @@ fn create_bindings(
         });
     }
 
-    for i in &out_binding_struct {
+    for i in out_binding_struct {
         bind_entry.push(wgpu::BindGroupLayoutEntry {
             binding: i.binding_number,
             visibility: wgpu::ShaderStage::COMPUTE,
@@ -198,20 +272,19 @@ fn create_bindings(
         });
     }
 
+    // The base of each pass when a run is split up, see dispatch
+    bind_entry.push(wgpu::BindGroupLayoutEntry {
+        binding: binding_number,
+        visibility: wgpu::ShaderStage::COMPUTE,
+        ty: wgpu::BindingType::UniformBuffer { dynamic: true },
+    });
+
     let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         bindings: &bind_entry,
         label: None,
     });
 
-    (
-        bind_group_layout,
-        ComputeBindings {
-            bindings: binding_struct,
-        },
-        OutComputeBindings {
-            bindings: out_binding_struct,
-        },
-    )
+    (bind_group_layout, program_bindings, out_program_bindings)
 }
 
 pub async fn compile(
@@ -236,6 +309,31 @@ pub async fn compile(
         })
         .await;
 
+    let device = Arc::new(device);
+    let poller = Arc::new(Poller::new(device.clone()));
+
+    build(compute, device, Arc::new(queue), poller)
+}
+
+// Compiles another program onto the device of an existing one so that they can share a ComputeSequence
+pub fn compile_shared(
+    program: &ComputeProgram,
+    compute: &ComputeShader,
+) -> (ComputeProgram, ComputeBindings, OutComputeBindings) {
+    build(
+        compute,
+        program.device.clone(),
+        program.queue.clone(),
+        program.poller.clone(),
+    )
+}
+
+fn build(
+    compute: &ComputeShader,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    poller: Arc<Poller>,
+) -> (ComputeProgram, ComputeBindings, OutComputeBindings) {
     let (bind_group_layout, program_bindings, out_program_bindings) =
         create_bindings(&compute, &device);
 
@@ -264,6 +362,7 @@ pub async fn compile(
             queue,
             pipeline,
             bind_group_layout,
+            poller,
         },
         program_bindings,
         out_program_bindings,
@@ -292,13 +391,37 @@ fn buffer_map_setup<'a>(
 
 pub fn run(
     program: &ComputeProgram,
-    bindings: &ComputeBindings,
-    mut out_bindings: OutComputeBindings,
+    bindings: &mut ComputeBindings,
+    out_bindings: OutComputeBindings,
 ) -> Vec<DefaultBinding> {
     let mut encoder = program
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
+    let results = record(program, bindings, out_bindings, &mut encoder);
+
+    program.queue.submit(&[encoder.finish()]);
+
+    results
+}
+
+// Records the dispatch of a program into an encoder without submitting it
+fn record(
+    program: &ComputeProgram,
+    bindings: &mut ComputeBindings,
+    mut out_bindings: OutComputeBindings,
+    encoder: &mut wgpu::CommandEncoder,
+) -> Vec<DefaultBinding> {
+    let length = loop_length(bindings, &out_bindings);
+
+    create_out_buffers(program, &mut out_bindings, length);
+
+    dispatch(program, bindings, &out_bindings, length, encoder);
+
+    out_bindings.bindings
+}
+
+pub fn loop_length(bindings: &ComputeBindings, out_bindings: &OutComputeBindings) -> u64 {
     // Look for a loop qualifier in bindings, if it isn't there, it must be in out_bindings or we just give 1
     // Use this to get the size that the program should run over
     let mut bind = bindings
@@ -313,158 +436,441 @@ pub fn run(
             .find(|i| i.qual.contains(&QUALIFIER::LOOP));
     }
 
-    let length = if let Some(b) = bind {
+    if let Some(b) = bind {
         b.length.unwrap()
     } else {
         1
-    };
+    }
+}
 
+fn create_out_buffers(
+    program: &ComputeProgram,
+    out_bindings: &mut OutComputeBindings,
+    length: u64,
+) {
     for i in 0..(out_bindings.bindings.len()) {
         if !(out_bindings.bindings[i].qual.contains(&QUALIFIER::IN)) {
-            out_bindings.bindings[i].data =
-                Some(program.device.create_buffer(&wgpu::BufferDescriptor {
-                    label: None,
-                    size: (length * 3 * std::mem::size_of::<u32>() as u64) as u64,
-                    usage: wgpu::BufferUsage::STORAGE
-                        | wgpu::BufferUsage::MAP_READ
-                        | wgpu::BufferUsage::COPY_DST
-                        | wgpu::BufferUsage::COPY_SRC
-                        | wgpu::BufferUsage::VERTEX,
-                }));
+            let size = out_buffer_size(&out_bindings.bindings[i].gtype, length);
+            out_bindings.bindings[i].data = Some(Arc::new(create_out_buffer(program, size)));
             out_bindings.bindings[i].length = Some(length);
+            out_bindings.bindings[i].size = Some(size);
+            out_bindings.bindings[i].poller = Some(program.poller.handle());
         }
     }
+}
 
-    let buffer_map = buffer_map_setup(bindings, &out_bindings);
+// Every invocation gets one element of an out buffer
+pub fn out_buffer_size(gtype: &GLSLTYPE, length: u64) -> u64 {
+    length * gtype.stride() as u64
+}
 
-    let mut empty_vec = Vec::new();
+fn create_out_buffer(program: &ComputeProgram, size: u64) -> wgpu::Buffer {
+    program.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsage::STORAGE
+            | wgpu::BufferUsage::MAP_READ
+            | wgpu::BufferUsage::COPY_DST
+            | wgpu::BufferUsage::COPY_SRC
+            | wgpu::BufferUsage::VERTEX,
+    })
+}
 
-    {
-        for i in 0..(buffer_map.len()) {
-            let b = buffer_map.get(&(i as u32)).expect(
-                "I assumed all bindings would be buffers but I guess that has been invalidated",
-            );
-            empty_vec.push(wgpu::Binding {
-                binding: b.binding_number,
-                resource: wgpu::BindingResource::Buffer {
-                    buffer: &b
-                        .data
-                        .as_ref()
-                        .unwrap_or_else(|| panic!("The binding of {} was not set", &b.name)),
-                    range: 0..b
-                        .length
-                        .unwrap_or_else(|| panic!("The size of {} was not set", &b.name)),
-                },
-            });
+// wgpu 0.5 doesn't report the limits of a device so these are the limits that most hardware has
+pub const MAX_WORKGROUPS_PER_DIMENSION: u64 = 65535;
+pub const MAX_STORAGE_BUFFER_BINDING_SIZE: u64 = 128 << 20;
+
+fn binding_size(b: &DefaultBinding) -> u64 {
+    b.size
+        .unwrap_or_else(|| panic!("The size of {} was not set", &b.name))
+}
+
+// Buffers that are read and written one element per invocation, these can be bound a window at a time
+fn is_windowed(b: &DefaultBinding) -> bool {
+    b.qual.contains(&QUALIFIER::BUFFER)
+        && (b.qual.contains(&QUALIFIER::LOOP)
+            || (b.qual.contains(&QUALIFIER::OUT) && !b.qual.contains(&QUALIFIER::IN)))
+}
+
+// The number of invocations whose elements fit into a single binding of every windowed buffer
+fn window_length(
+    bindings: &ComputeBindings,
+    out_bindings: &OutComputeBindings,
+    length: u64,
+) -> u64 {
+    let mut window = length;
+    for b in bindings.bindings.iter().chain(out_bindings.bindings.iter()) {
+        let size = binding_size(b);
+        if size <= MAX_STORAGE_BUFFER_BINDING_SIZE {
+            continue;
+        }
+        if !is_windowed(b) {
+            panic!(
+                "{} is {} bytes which is more than the {} bytes that can be bound at once, only loop buffers and out buffers can be split up",
+                b.name, size, MAX_STORAGE_BUFFER_BINDING_SIZE
+            )
         }
+        let element = size / b.length.unwrap();
+        // Every window has to start at an aligned offset
+        let fits = MAX_STORAGE_BUFFER_BINDING_SIZE / element / wgpu::BIND_BUFFER_ALIGNMENT
+            * wgpu::BIND_BUFFER_ALIGNMENT;
+        window = std::cmp::min(window, fits);
+    }
+    window
+}
 
-        let bgd = &wgpu::BindGroupDescriptor {
-            layout: &program.bind_group_layout,
-            bindings: empty_vec.as_slice(),
-            label: None,
-        };
+// A run is split into windows which each bind part of the windowed buffers
+// and each window is split into passes that stay under the workgroup limit
+// The start of each pass within its window is given to the shader through pipeline_base
+struct DispatchPass {
+    window: std::ops::Range<u64>,
+    base: u64,
+    count: u64,
+}
+
+fn split_dispatch(length: u64, window_length: u64) -> Vec<DispatchPass> {
+    let mut passes = Vec::new();
+    let mut start = 0;
+    while start < length {
+        let end = std::cmp::min(start + window_length, length);
+        let mut base = 0;
+        while start + base < end {
+            let count = std::cmp::min(MAX_WORKGROUPS_PER_DIMENSION, end - start - base);
+            passes.push(DispatchPass {
+                window: start..end,
+                base,
+                count,
+            });
+            base += count;
+        }
+        start = end;
+    }
+    passes
+}
 
-        let bind_group = program.device.create_bind_group(bgd);
+// Writes the bases of every pass at an aligned offset of the dispatch buffer so each pass can pick its own with a dynamic offset
+// The first pass always starts at 0, so runs that fit into one pass only need the buffer to exist
+// The copy is recorded into the encoder so it happens in order with the passes
+fn write_dispatch_params(
+    program: &ComputeProgram,
+    bindings: &mut ComputeBindings,
+    passes: &[DispatchPass],
+    encoder: &mut wgpu::CommandEncoder,
+) {
+    let stride = (wgpu::BIND_BUFFER_ALIGNMENT / 4) as usize;
+    let mut params = vec![0u32; stride * std::cmp::max(passes.len(), 1)];
+    for (i, pass) in passes.iter().enumerate() {
+        params[i * stride] = pass.base as u32;
+        params[i * stride + 1] = pass.window.start as u32;
+    }
+    let size = params.len() as u64 * 4;
 
-        let mut cpass = encoder.begin_compute_pass();
-        cpass.set_pipeline(&program.pipeline);
-        cpass.set_bind_group(0, &bind_group, &[]);
-        compute(&mut cpass, length as u32);
+    let fits = match &bindings.dispatch_params {
+        Some((_, x)) => *x >= size,
+        None => false,
+    };
+    if !fits {
+        // A new buffer starts out with every base already written
+        let buffer = program.device.create_buffer_with_data(
+            params.as_slice().as_bytes(),
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        );
+        bindings.dispatch_params = Some((Arc::new(buffer), size));
+        return;
+    }
+    if passes.len() <= 1 {
+        return;
     }
-    program.queue.submit(&[encoder.finish()]);
 
-    out_bindings.bindings
+    let staging = program
+        .device
+        .create_buffer_with_data(params.as_slice().as_bytes(), wgpu::BufferUsage::COPY_SRC);
+    encoder.copy_buffer_to_buffer(
+        &staging,
+        0,
+        &bindings.dispatch_params.as_ref().unwrap().0,
+        0,
+        size,
+    );
 }
 
-pub async fn read_uvec(
+fn dispatch(
     program: &ComputeProgram,
-    results: &Vec<DefaultBinding>,
-    name: &str,
-) -> Vec<u32> {
-    for i in results.iter() {
-        if i.name == name {
-            let result_buffer = i.data.as_ref().unwrap();
-            // todo modify by size
-            let size = i.length.unwrap() * std::mem::size_of::<u32>() as u64;
-            let buffer_future = result_buffer.map_read(0, size);
-            program.device.poll(wgpu::Maintain::Wait);
-
-            if let Ok(mapping) = buffer_future.await {
-                let x: Vec<u32> = mapping
-                    .as_slice()
-                    .chunks_exact(4)
-                    .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
-                    .collect();
-                return x;
-            } else {
-                panic!("failed to run compute on gpu!");
-            }
+    bindings: &mut ComputeBindings,
+    out_bindings: &OutComputeBindings,
+    length: u64,
+    encoder: &mut wgpu::CommandEncoder,
+) {
+    let window_length = window_length(bindings, out_bindings, length);
+    let passes = split_dispatch(length, window_length);
+
+    write_dispatch_params(program, bindings, &passes, encoder);
+
+    let mut windows: Vec<std::ops::Range<u64>> = Vec::new();
+    for pass in passes.iter() {
+        if !windows.contains(&pass.window) {
+            windows.push(pass.window.clone());
         }
     }
-    panic!(
-        "We didn't find the binding you were looking to read from: {}",
-        name
-    )
+
+    // Only build a new bind group when one of the bound buffers has changed
+    // Runs over buffers that are split up need a bind group for every window so those aren't cached
+    let windowed = windows.len() > 1;
+    let mut window_bind_groups = Vec::new();
+    let key = bind_group_key(bindings, out_bindings);
+    if windowed {
+        for window in windows.iter() {
+            window_bind_groups.push(create_bind_group(
+                program,
+                bindings,
+                out_bindings,
+                Some(window.clone()),
+            ));
+        }
+    } else if !bindings.bind_groups.contains(&key) {
+        let bind_group = create_bind_group(program, bindings, out_bindings, None);
+        bindings.bind_groups.insert(key.clone(), bind_group);
+    }
+
+    let mut cpass = encoder.begin_compute_pass();
+    cpass.set_pipeline(&program.pipeline);
+    for (i, pass) in passes.iter().enumerate() {
+        let bind_group = if windowed {
+            &window_bind_groups[windows.iter().position(|x| *x == pass.window).unwrap()]
+        } else {
+            bindings.bind_groups.get(&key)
+        };
+        let offset = i as u64 * wgpu::BIND_BUFFER_ALIGNMENT;
+        cpass.set_bind_group(0, bind_group, &[offset as u32]);
+        compute(&mut cpass, pass.count as u32);
+    }
+}
+
+fn bind_group_key(
+    bindings: &ComputeBindings,
+    out_bindings: &OutComputeBindings,
+) -> Vec<ResourceKey> {
+    let buffer_map = buffer_map_setup(bindings, out_bindings);
+
+    let mut key: Vec<ResourceKey> = (0..(buffer_map.len()))
+        .map(|i| {
+            let b = buffer_map.get(&(i as u32)).expect(
+                "I assumed all bindings would be buffers but I guess that has been invalidated",
+            );
+            resource_key(b.binding_number, &b.data, b.size)
+        })
+        .collect();
+    key.push(resource_key(
+        buffer_map.len() as u32,
+        &bindings.dispatch_params.as_ref().map(|x| x.0.clone()),
+        None,
+    ));
+    key
 }
 
-pub async fn read_fvec(
+// Windowed buffers are only bound for the elements of the window when one is given
+fn create_bind_group(
     program: &ComputeProgram,
-    results: &Vec<DefaultBinding>,
-    name: &str,
-) -> Vec<f32> {
-    for i in results.iter() {
-        if i.name == name {
-            let result_buffer = i.data.as_ref().unwrap();
-            let size = i.length.unwrap() * std::mem::size_of::<f32>() as u64;
-            let buffer_future = result_buffer.map_read(0, size);
-            program.device.poll(wgpu::Maintain::Wait);
-
-            if let Ok(mapping) = buffer_future.await {
-                let x: Vec<f32> = mapping
-                    .as_slice()
-                    .chunks_exact(4)
-                    .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
-                    .collect();
-                return x;
-            } else {
-                panic!("failed to run compute on gpu!");
+    bindings: &ComputeBindings,
+    out_bindings: &OutComputeBindings,
+    window: Option<std::ops::Range<u64>>,
+) -> wgpu::BindGroup {
+    let buffer_map = buffer_map_setup(bindings, out_bindings);
+
+    let mut empty_vec = Vec::new();
+
+    for i in 0..(buffer_map.len()) {
+        let b = buffer_map.get(&(i as u32)).expect(
+            "I assumed all bindings would be buffers but I guess that has been invalidated",
+        );
+        let size = binding_size(b);
+        let range = match &window {
+            Some(window) if is_windowed(b) => {
+                let element = size / b.length.unwrap();
+                (window.start * element)..(window.end * element)
             }
+            _ => 0..size,
+        };
+        empty_vec.push(wgpu::Binding {
+            binding: b.binding_number,
+            resource: wgpu::BindingResource::Buffer {
+                buffer: &b
+                    .data
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("The binding of {} was not set", &b.name)),
+                range,
+            },
+        });
+    }
+
+    // Each pass only reads the bases at its own offset
+    empty_vec.push(wgpu::Binding {
+        binding: buffer_map.len() as u32,
+        resource: wgpu::BindingResource::Buffer {
+            buffer: &bindings.dispatch_params.as_ref().unwrap().0,
+            range: 0..16,
+        },
+    });
+
+    let bgd = &wgpu::BindGroupDescriptor {
+        layout: &program.bind_group_layout,
+        bindings: empty_vec.as_slice(),
+        label: None,
+    };
+
+    program.device.create_bind_group(bgd)
+}
+
+// The contents of a result that are being copied back from the gpu
+// The program's poller drives the device so awaiting this never blocks the thread
+// Results of the cpu executor are already in host memory and are ready straight away
+// Awaiting a readback gives its values once, awaiting it again panics
+pub struct Readback<T> {
+    mapping: Option<
+        Pin<Box<dyn Future<Output = Result<wgpu::BufferReadMapping, wgpu::BufferAsyncErr>> + Send>>,
+    >,
+    // Keeps the pollers running until the mapping completes
+    pending: Option<PendingMapping>,
+    ready: Option<Vec<T>>,
+    convert: fn(&[u8]) -> Vec<T>,
+    // The bytes between elements in the buffer and how many of those are read
+    stride: usize,
+    element: usize,
+}
+
+// Nothing in a readback is pinned in place, the mapping is already boxed
+impl<T> Unpin for Readback<T> {}
+
+impl<T> Readback<T> {
+    // Gives the values if they have already been copied and None otherwise
+    // The values are kept in the readback, so awaiting it afterwards still gives them back
+    pub fn try_read(&mut self) -> Option<&[T]> {
+        if self.ready.is_none() {
+            let values = self.now_or_never()?;
+            self.ready = Some(values);
         }
+        self.ready.as_deref()
     }
-    panic!(
-        "We didn't find the binding you were looking to read from: {}",
-        name
-    )
 }
 
-pub async fn read_fvec3(
-    program: &ComputeProgram,
+impl<T> Future for Readback<T> {
+    type Output = Vec<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Vec<T>> {
+        if let Some(values) = self.ready.take() {
+            return Poll::Ready(values);
+        }
+        let convert = self.convert;
+        let mapping = self
+            .mapping
+            .as_mut()
+            .expect("This readback was already read");
+        let result = match mapping.as_mut().poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.mapping = None;
+        self.pending = None;
+        match result {
+            Ok(mapping) => Poll::Ready(convert(&unpad(
+                mapping.as_slice(),
+                self.stride,
+                self.element,
+            ))),
+            Err(_) => panic!("failed to run compute on gpu!"),
+        }
+    }
+}
+
+fn start_read<T>(
     results: &Vec<DefaultBinding>,
     name: &str,
-) -> Vec<f32> {
-    for i in results.iter() {
-        if i.name == name {
-            let result_buffer = i.data.as_ref().unwrap();
-            let size = i.length.unwrap() * 3 * std::mem::size_of::<f32>() as u64;
-            let buffer_future = result_buffer.map_read(0, size);
-            program.device.poll(wgpu::Maintain::Wait);
-
-            if let Ok(mapping) = buffer_future.await {
-                let x: Vec<f32> = mapping
-                    .as_slice()
-                    .chunks_exact(4)
-                    .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
-                    .collect();
-                return x;
-            } else {
-                panic!("failed to run compute on gpu!");
-            }
-        }
+    components: u64,
+    convert: fn(&[u8]) -> Vec<T>,
+) -> Readback<T> {
+    let i = results.iter().find(|i| i.name == name).unwrap_or_else(|| {
+        panic!(
+            "We didn't find the binding you were looking to read from: {}",
+            name
+        )
+    });
+    let length = i.length.unwrap();
+    let size = i.size.unwrap();
+    // Out buffers are laid out like the gpu does while bound buffers are packed, so the stride comes from the buffer
+    let element = components as usize * 4;
+    let stride = if length == 0 {
+        element
+    } else {
+        (size / length) as usize
+    };
+    if stride < element {
+        panic!(
+            "{} has {} bytes per element which is too few to read {} components from",
+            name, stride, components
+        )
     }
-    panic!(
-        "We didn't find the binding you were looking to read from: {}",
-        name
-    )
+    if let Some(host) = i.host.as_ref() {
+        return Readback {
+            mapping: None,
+            pending: None,
+            ready: Some(convert(&unpad(&host[..size as usize], stride, element))),
+            convert,
+            stride,
+            element,
+        };
+    }
+    let result_buffer = i.data.as_ref().unwrap();
+    let poller = i.poller.as_ref().unwrap_or_else(|| {
+        panic!(
+            "{} wasn't created by a program so nothing polls its device",
+            name
+        )
+    });
+    Readback {
+        pending: Some(poller.start_mapping()),
+        mapping: Some(Box::pin(result_buffer.map_read(0, size))),
+        ready: None,
+        convert,
+        stride,
+        element,
+    }
+}
+
+// Drops the padding after each element, such as the fourth float of every vec3 on the gpu
+fn unpad(bytes: &[u8], stride: usize, element: usize) -> Vec<u8> {
+    if stride == element {
+        return bytes.to_vec();
+    }
+    bytes
+        .chunks(stride)
+        .flat_map(|x| x[..element].iter().cloned())
+        .collect()
+}
+
+fn to_uvec(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+        .collect()
+}
+
+fn to_fvec(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+        .collect()
+}
+
+pub fn read_uvec(results: &Vec<DefaultBinding>, name: &str) -> Readback<u32> {
+    start_read(results, name, 1, to_uvec)
+}
+
+pub fn read_fvec(results: &Vec<DefaultBinding>, name: &str) -> Readback<f32> {
+    start_read(results, name, 1, to_fvec)
+}
+
+pub fn read_fvec3(results: &Vec<DefaultBinding>, name: &str) -> Readback<f32> {
+    start_read(results, name, 3, to_fvec)
 }
 
 pub fn pipe(
@@ -472,33 +878,229 @@ pub fn pipe(
     mut in_bindings: ComputeBindings,
     mut out_bindings: OutComputeBindings,
     result_vec: Vec<DefaultBinding>,
+    mapping: &[(&str, &str)],
 ) -> Vec<DefaultBinding> {
-    for i in result_vec {
-        let binding = match in_bindings.bindings.iter().position(|x| x.name == i.name) {
-            Some(x) => &mut in_bindings.bindings[x],
-            None => {
-                let x = out_bindings
-                    .bindings
-                    .iter()
-                    .position(|x| x.name == i.name)
-                    .expect("We couldn't find the binding");
-                &mut out_bindings.bindings[x]
+    pipe_bindings(&mut in_bindings, &mut out_bindings, result_vec, mapping);
+
+    run(program, &mut in_bindings, out_bindings)
+}
+
+// Records several programs into a single encoder which is only submitted once at the end
+// All of the programs need to share a device, see compile_shared
+pub struct ComputeSequence {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    encoder: wgpu::CommandEncoder,
+    // Bindings that were piped into a stage are kept alive until the sequence is submitted
+    retained: Vec<ComputeBindings>,
+    results: Vec<DefaultBinding>,
+}
+
+impl ComputeSequence {
+    pub fn new(program: &ComputeProgram) -> ComputeSequence {
+        ComputeSequence {
+            device: program.device.clone(),
+            queue: program.queue.clone(),
+            encoder: program
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }),
+            retained: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    fn check_device(&self, program: &ComputeProgram) {
+        if !Arc::ptr_eq(&self.device, &program.device) {
+            panic!("All of the programs in a sequence need to be compiled on the same device, use compile_shared")
+        }
+    }
+
+    // Records a program whose bindings were all bound by hand
+    pub fn run(
+        mut self,
+        program: &ComputeProgram,
+        bindings: &mut ComputeBindings,
+        out_bindings: OutComputeBindings,
+    ) -> ComputeSequence {
+        self.check_device(program);
+        self.results = record(program, bindings, out_bindings, &mut self.encoder);
+        self
+    }
+
+    // Records a program that takes the results of the previous stage
+    pub fn pipe(
+        mut self,
+        program: &ComputeProgram,
+        mut in_bindings: ComputeBindings,
+        mut out_bindings: OutComputeBindings,
+        mapping: &[(&str, &str)],
+    ) -> ComputeSequence {
+        self.check_device(program);
+        let result_vec = std::mem::replace(&mut self.results, Vec::new());
+        pipe_bindings(&mut in_bindings, &mut out_bindings, result_vec, mapping);
+        self.results = record(program, &mut in_bindings, out_bindings, &mut self.encoder);
+        self.retained.push(in_bindings);
+        self
+    }
+
+    // Submits everything that was recorded and gives back the results of the last stage
+    pub fn submit(self) -> Vec<DefaultBinding> {
+        self.queue.submit(&[self.encoder.finish()]);
+        self.results
+    }
+}
+
+// Runs a program over and over, feeding each out parameter of a pair back into its in parameter
+// The buffers of a pair are swapped after every step so the data never leaves the gpu
+pub struct PingPong<'a> {
+    program: &'a ComputeProgram,
+    bindings: ComputeBindings,
+    out_bindings: OutComputeBindings,
+    // Positions of the in and out binding of each pair
+    pairs: Vec<(usize, usize)>,
+    length: u64,
+}
+
+impl<'a> PingPong<'a> {
+    pub fn new(
+        program: &'a ComputeProgram,
+        bindings: ComputeBindings,
+        mut out_bindings: OutComputeBindings,
+        pairs: &[(&str, &str)],
+    ) -> PingPong<'a> {
+        let length = loop_length(&bindings, &out_bindings);
+
+        let mut pair_positions = Vec::new();
+        for (in_name, out_name) in pairs.iter() {
+            let in_pos = bindings
+                .bindings
+                .iter()
+                .position(|x| &x.name == in_name)
+                .unwrap_or_else(|| panic!("{} is not an in parameter of the program", in_name));
+            let out_pos = out_bindings
+                .bindings
+                .iter()
+                .position(|x| &x.name == out_name && !x.qual.contains(&QUALIFIER::IN))
+                .unwrap_or_else(|| panic!("{} is not an out parameter of the program", out_name));
+            let (i, o) = (&bindings.bindings[in_pos], &out_bindings.bindings[out_pos]);
+
+            if !i.qual.contains(&QUALIFIER::BUFFER) || !o.qual.contains(&QUALIFIER::BUFFER) {
+                panic!(
+                    "Only buffers can be swapped, {} and {} need to both be buffers",
+                    in_name, out_name
+                )
             }
-        };
+            if i.gtype != o.gtype {
+                panic!(
+                    "{} is a {:?} but {} is a {:?}, the parameters of a pair need to share a type",
+                    in_name, i.gtype, out_name, o.gtype
+                )
+            }
+            if i.data.is_none() || i.length != Some(length) {
+                panic!(
+                    "{} needs to be bound with {} values before it can be swapped with {}",
+                    in_name, length, out_name
+                )
+            }
+            if pair_positions
+                .iter()
+                .any(|(x, y)| *x == in_pos || *y == out_pos)
+            {
+                panic!("{} or {} is already part of a pair", in_name, out_name)
+            }
+            pair_positions.push((in_pos, out_pos));
+        }
 
-        /*          todo Check the types somewhere
-        if !acceptable_types.contains(&binding.gtype) {
-            panic!(
-                "The type of the value you provided is not what was expected, {:?}",
-                &binding.gtype
+        // The out buffers are created once and reused by every step
+        create_out_buffers(program, &mut out_bindings, length);
+
+        PingPong {
+            program,
+            bindings,
+            out_bindings,
+            pairs: pair_positions,
+            length,
+        }
+    }
+
+    pub fn run(&mut self) {
+        self.run_steps(1)
+    }
+
+    // Records n steps into a single encoder and submits them together
+    pub fn run_steps(&mut self, n: u32) {
+        let mut encoder = self
+            .program
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        for _ in 0..n {
+            dispatch(
+                self.program,
+                &mut self.bindings,
+                &self.out_bindings,
+                self.length,
+                &mut encoder,
             );
-        } */
+            for (in_pos, out_pos) in self.pairs.iter() {
+                let (i, o) = (
+                    &mut self.bindings.bindings[*in_pos],
+                    &mut self.out_bindings.bindings[*out_pos],
+                );
+                std::mem::swap(&mut i.data, &mut o.data);
+                std::mem::swap(&mut i.size, &mut o.size);
+                std::mem::swap(&mut i.poller, &mut o.poller);
+            }
+        }
+
+        self.program.queue.submit(&[encoder.finish()]);
+    }
 
-        binding.data = Some(i.data.unwrap());
-        binding.length = Some(i.length.unwrap());
+    // The binding holding the newest values of a parameter
+    // After a step the in parameter of a pair holds what was just written to its out parameter
+    pub fn latest(&self, name: &str) -> &DefaultBinding {
+        let name = match self
+            .pairs
+            .iter()
+            .find(|(_, o)| self.out_bindings.bindings[*o].name == name)
+        {
+            Some((i, _)) => &self.bindings.bindings[*i].name,
+            None => name,
+        };
+        self.bindings
+            .bindings
+            .iter()
+            .chain(self.out_bindings.bindings.iter())
+            .find(|x| x.name == name)
+            .unwrap_or_else(|| panic!("We didn't find the binding you were looking for: {}", name))
     }
 
-    run(program, &in_bindings, out_bindings)
+    // Copies of the newest values of every out parameter, made on the gpu
+    // These can be read or piped like the results of run
+    pub fn results(&self) -> Vec<DefaultBinding> {
+        let mut encoder = self
+            .program
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        let mut results = new_bindings(&self.out_bindings.bindings);
+        for i in results.iter_mut() {
+            let source = self.latest(&i.name);
+            // The copy is as large as the elements of the parameter but never reads past the end of the source
+            let size = out_buffer_size(&i.gtype, self.length);
+            let size = source.size.map_or(size, |x| x.min(size));
+            let copy = create_out_buffer(self.program, size);
+            encoder.copy_buffer_to_buffer(source.data.as_ref().unwrap(), 0, &copy, 0, size);
+            i.data = Some(Arc::new(copy));
+            i.length = Some(self.length);
+            i.size = Some(size);
+            i.poller = Some(self.program.poller.handle());
+        }
+
+        self.program.queue.submit(&[encoder.finish()]);
+
+        results
+    }
 }
 
 #[derive(Debug)]
@@ -514,3 +1116,97 @@ macro_rules! compute_shader {
             (pipeline::wgpu_compute_header::ComputeShader{params:S.0, body:S.1})
         }};
     }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A binding whose size is set without a device
+    fn sized(qual: Vec<QUALIFIER>, gtype: GLSLTYPE, length: u64) -> DefaultBinding {
+        DefaultBinding {
+            binding_number: 0,
+            name: "data".to_string(),
+            data: None,
+            length: Some(length),
+            size: Some(out_buffer_size(&gtype, length)),
+            host: None,
+            poller: None,
+            gtype,
+            qual,
+        }
+    }
+
+    fn split(bindings: Vec<DefaultBinding>, length: u64) -> Vec<DispatchPass> {
+        let bindings = ComputeBindings {
+            bindings,
+            bind_groups: BindGroupCache::default(),
+            dispatch_params: None,
+        };
+        let out_bindings = OutComputeBindings {
+            bindings: Vec::new(),
+        };
+        split_dispatch(length, window_length(&bindings, &out_bindings, length))
+    }
+
+    #[test]
+    fn out_buffers_are_sized_by_their_elements() {
+        assert_eq!(out_buffer_size(&GLSLTYPE::ArrayUint, 10), 40);
+        assert_eq!(out_buffer_size(&GLSLTYPE::ArrayVec2, 10), 80);
+        assert_eq!(out_buffer_size(&GLSLTYPE::ArrayVec3, 10), 160);
+        assert_eq!(out_buffer_size(&GLSLTYPE::ArrayVec4, 10), 160);
+    }
+
+    #[test]
+    fn small_runs_are_one_window() {
+        let length = 3 * MAX_WORKGROUPS_PER_DIMENSION;
+        let passes = split(
+            vec![sized(
+                vec![QUALIFIER::BUFFER, QUALIFIER::LOOP, QUALIFIER::IN],
+                GLSLTYPE::ArrayUint,
+                length,
+            )],
+            length,
+        );
+        assert_eq!(passes.len(), 3);
+        for (i, pass) in passes.iter().enumerate() {
+            assert_eq!(pass.window, 0..length);
+            assert_eq!(pass.base, i as u64 * MAX_WORKGROUPS_PER_DIMENSION);
+        }
+    }
+
+    #[test]
+    fn large_loop_buffers_are_split_into_aligned_windows() {
+        let length = 50_000_000;
+        let passes = split(
+            vec![sized(
+                vec![QUALIFIER::BUFFER, QUALIFIER::LOOP, QUALIFIER::IN],
+                GLSLTYPE::ArrayVec4,
+                length,
+            )],
+            length,
+        );
+        let mut covered = 0;
+        for pass in passes.iter() {
+            let window = &pass.window;
+            assert_eq!(window.start * 16 % wgpu::BIND_BUFFER_ALIGNMENT, 0);
+            assert!((window.end - window.start) * 16 <= MAX_STORAGE_BUFFER_BINDING_SIZE);
+            assert!(pass.count <= MAX_WORKGROUPS_PER_DIMENSION);
+            assert_eq!(window.start + pass.base, covered);
+            covered += pass.count;
+        }
+        assert_eq!(covered, length);
+    }
+
+    #[test]
+    #[should_panic(expected = "only loop buffers and out buffers can be split up")]
+    fn large_buffers_that_arent_windowed_panic() {
+        split(
+            vec![sized(
+                vec![QUALIFIER::BUFFER, QUALIFIER::IN],
+                GLSLTYPE::ArrayVec4,
+                50_000_000,
+            )],
+            1,
+        );
+    }
+}
@@ -3,35 +3,54 @@ use zerocopy::AsBytes as _;
 
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 use winit::window::Window;
 
 use crate::shared::{
-    check_gl_builtin_type, compile_shader, has_in_qual, has_out_qual, has_uniform_qual,
-    process_body, string_compare, Program, GLSLTYPE, PARAMETER, QUALIFIER,
+    check_gl_builtin_type, compile_shader, has_buffer_qual, has_in_qual, has_out_qual,
+    has_uniform_qual, process_body, same_frames, string_compare, PollHandle, Poller, Program,
+    GLSLTYPE, PARAMETER, QUALIFIER,
 };
 
-use crate::bind::{new_bindings, Bindings, DefaultBinding, OutProgramBindings, ProgramBindings};
+use crate::bind::{
+    bind_dynamic, dynamic_stride, is_vertex_attribute, new_bindings, pipe_bindings, resource_key,
+    BindGroupCache, Bindings, DefaultBinding, OutProgramBindings, ProgramBindings, ResourceKey,
+    UniformValue,
+};
+use crate::capture::{read_texture, write_png, CaptureError};
+use crate::mesh::Mesh;
 
 pub struct GraphicsProgram {
-    pub surface: wgpu::Surface,
-    pub device: wgpu::Device,
+    pub surface: Arc<wgpu::Surface>,
+    pub device: Arc<wgpu::Device>,
     bind_group_layout: wgpu::BindGroupLayout,
-    pub queue: wgpu::Queue,
+    pub queue: Arc<wgpu::Queue>,
     pub pipeline: wgpu::RenderPipeline,
+    // Programs that test depth need a depth attachment in their pass, see create_depth_view
+    pub depth_test: bool,
+    // Keeps the device running so the buffers written by a draw can be read back
+    poller: Arc<Poller>,
 }
 
 impl Program for GraphicsProgram {
     fn get_device(&self) -> &wgpu::Device {
         &self.device
     }
+    fn get_queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+    fn poller(&self) -> Option<PollHandle> {
+        Some(self.poller.handle())
+    }
 }
 
 #[derive(Debug)]
 pub struct TextureBinding {
     pub binding_number: u32,
     pub name: String,
-    pub data: Option<wgpu::TextureView>,
+    pub data: Option<Arc<wgpu::TextureView>>,
     pub gtype: GLSLTYPE,
     pub qual: Vec<QUALIFIER>,
 }
@@ -40,7 +59,7 @@ pub struct TextureBinding {
 pub struct SamplerBinding {
     pub binding_number: u32,
     pub name: String,
-    pub data: Option<wgpu::Sampler>,
+    pub data: Option<Arc<wgpu::Sampler>>,
     pub gtype: GLSLTYPE,
     pub qual: Vec<QUALIFIER>,
 }
@@ -48,10 +67,11 @@ pub struct SamplerBinding {
 #[derive(Debug)]
 pub struct GraphicsBindings {
     pub bindings: Vec<DefaultBinding>,
-    pub indicies: Option<wgpu::Buffer>,
+    pub indicies: Option<Arc<wgpu::Buffer>>,
     pub index_len: Option<u32>,
     pub textures: Vec<TextureBinding>,
     pub samplers: Vec<SamplerBinding>,
+    bind_groups: BindGroupCache,
 }
 
 impl ProgramBindings for GraphicsBindings {
@@ -85,6 +105,7 @@ impl Bindings for GraphicsBindings {
             index_len: None,
             textures: new_textures(&self.textures),
             samplers: new_samplers(&self.samplers),
+            bind_groups: BindGroupCache::default(),
         }
     }
 }
@@ -139,6 +160,11 @@ fn stringify_shader(
                 "layout(binding = {}) uniform UNIFORM{} {{\n\t {} {};\n}};\n",
                 i.binding_number, i.binding_number, i.gtype, i.name
             ));
+        } else if i.qual.contains(&QUALIFIER::BUFFER) {
+            buffer.push(format!(
+                "layout(binding = {}) buffer BINDINGS{} {{\n\t {} {};\n}};\n",
+                i.binding_number, i.binding_number, i.gtype, i.name
+            ));
         } else if i.name != "gl_Position" {
             buffer.push(format!(
                 "layout(location={}) {} {} {};\n",
@@ -170,7 +196,13 @@ fn stringify_shader(
         ));
     }
     for i in &b_out.bindings[..] {
-        if i.name != "gl_Position" && !i.qual.contains(&QUALIFIER::UNIFORM) {
+        // in out buffers are read-write storage buffers rather than varyings
+        if i.qual.contains(&QUALIFIER::BUFFER) {
+            buffer.push(format!(
+                "layout(binding = {}) buffer BINDINGS{} {{\n\t {} {};\n}};\n",
+                i.binding_number, i.binding_number, i.gtype, i.name
+            ));
+        } else if i.name != "gl_Position" && !i.qual.contains(&QUALIFIER::UNIFORM) {
             buffer.push(format!(
                 "layout(location={}) {} {} {};\n",
                 i.binding_number,
@@ -204,7 +236,7 @@ pub fn generate_swap_chain(
     // For drawing to window
     let sc_desc = wgpu::SwapChainDescriptor {
         usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        format: COLOR_FORMAT,
         // Window dimensions
         width: size.width,
         height: size.height,
@@ -215,6 +247,15 @@ pub fn generate_swap_chain(
     program.device.create_swap_chain(&program.surface, &sc_desc)
 }
 
+// The number of shader locations a vertex attribute of this type takes up
+fn attribute_locations(gtype: &GLSLTYPE) -> u32 {
+    if *gtype == GLSLTYPE::Mat4 {
+        4
+    } else {
+        1
+    }
+}
+
 fn create_bindings(
     vertex: &GraphicsShader,
     fragment: &GraphicsShader,
@@ -228,26 +269,30 @@ fn create_bindings(
     let mut vertex_out_binding_struct = Vec::new();
     let mut fragment_binding_struct = Vec::new();
     let mut fragment_out_binding_struct = Vec::new();
-    let mut vertex_stage_binding_number = 0;
     let mut vertex_binding_number = 0;
     let mut vertex_to_fragment_binding_number = 0;
     let mut vertex_to_fragment_map = HashMap::new();
     let mut uniform_binding_number = 0;
     let mut uniform_map = HashMap::new();
+    let mut storage_map = HashMap::new();
     let mut fragment_out_binding_number = 0;
     for i in &vertex.params[..] {
         if !check_gl_builtin_type(i.name, &i.gtype) {
             // Bindings that are kept between runs
-            if i.qual.contains(&QUALIFIER::VERTEX) {
+            // Per instance attributes share their locations with the per vertex attributes
+            if i.qual.contains(&QUALIFIER::VERTEX) || i.qual.contains(&QUALIFIER::INSTANCE) {
                 vertex_binding_struct.push(DefaultBinding {
                     binding_number: vertex_binding_number,
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                vertex_binding_number += 1;
+                vertex_binding_number += attribute_locations(&i.gtype);
             // Bindings that are invalidated after a run
             } else if i.qual.contains(&QUALIFIER::UNIFORM) {
                 vertex_binding_struct.push(DefaultBinding {
@@ -255,21 +300,36 @@ fn create_bindings(
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
                 uniform_map.insert(i.name, uniform_binding_number);
                 uniform_binding_number += 1;
+            } else if is_vertex_attribute(&i.qual)
+                && i.qual.contains(&QUALIFIER::IN)
+                && !i.qual.contains(&QUALIFIER::OUT)
+            {
+                panic!(
+                    "{} needs the vertex or instance qualifier to be read from a vertex buffer",
+                    i.name
+                )
+            // Buffers that are only read, these are numbered with the uniforms rather than the attribute locations
             } else if i.qual.contains(&QUALIFIER::IN) && !i.qual.contains(&QUALIFIER::OUT) {
                 vertex_binding_struct.push(DefaultBinding {
-                    binding_number: vertex_stage_binding_number,
+                    binding_number: uniform_binding_number,
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                vertex_stage_binding_number += 1;
+                uniform_binding_number += 1;
             // Bindings that are invalidated after a run
             } else if !i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
                 vertex_out_binding_struct.push(DefaultBinding {
@@ -277,14 +337,32 @@ fn create_bindings(
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
                 vertex_to_fragment_map.insert(i.name, vertex_to_fragment_binding_number);
                 vertex_to_fragment_binding_number += 1;
+            // Read-write storage buffers that are invalidated after a run
+            } else if i.qual.contains(&QUALIFIER::BUFFER) {
+                vertex_out_binding_struct.push(DefaultBinding {
+                    binding_number: uniform_binding_number,
+                    name: i.name.to_string(),
+                    data: None,
+                    length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
+                    gtype: i.gtype.clone(),
+                    qual: i.qual.to_vec(),
+                });
+                storage_map.insert(i.name, uniform_binding_number);
+                uniform_binding_number += 1;
             } else {
                 println!("{:?}", i);
-                panic!("TODO We currently don't support both in and out qualifiers for vertex/fragment shaders")
+                panic!("Both in and out qualifiers are only supported on buffers for vertex/fragment shaders")
             }
         }
     }
@@ -320,6 +398,9 @@ fn create_bindings(
                         name: i.name.to_string(),
                         data: None,
                         length: None,
+                        size: None,
+                        host: None,
+                        poller: None,
                         gtype: i.gtype.clone(),
                         qual: i.qual.to_vec(),
                     });
@@ -333,6 +414,9 @@ fn create_bindings(
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
@@ -343,12 +427,36 @@ fn create_bindings(
                     name: i.name.to_string(),
                     data: None,
                     length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
                 fragment_out_binding_number += 1;
+            // Share the storage buffer with the vertex stage if it was declared there
+            } else if i.qual.contains(&QUALIFIER::BUFFER) {
+                let binding_number = match storage_map.get(i.name) {
+                    Some(x) => *x,
+                    None => {
+                        let x = uniform_binding_number;
+                        uniform_binding_number += 1;
+                        x
+                    }
+                };
+                fragment_out_binding_struct.push(DefaultBinding {
+                    binding_number,
+                    name: i.name.to_string(),
+                    data: None,
+                    length: None,
+                    size: None,
+                    host: None,
+                    poller: None,
+                    gtype: i.gtype.clone(),
+                    qual: i.qual.to_vec(),
+                });
             } else {
-                panic!("TODO We currently don't support both in and out qualifiers for vertex/fragment shaders")
+                panic!("Both in and out qualifiers are only supported on buffers for vertex/fragment shaders")
             }
         }
     }
@@ -360,6 +468,7 @@ fn create_bindings(
             index_len: None,
             textures: Vec::new(),
             samplers: Vec::new(),
+            bind_groups: BindGroupCache::default(),
         },
         OutGraphicsBindings {
             bindings: vertex_out_binding_struct,
@@ -370,6 +479,7 @@ fn create_bindings(
             index_len: None,
             textures: textures_struct,
             samplers: samplers_struct,
+            bind_groups: BindGroupCache::default(),
         },
         OutGraphicsBindings {
             bindings: fragment_out_binding_struct,
@@ -382,6 +492,7 @@ pub async fn graphics_compile(
     window: &Window,
     vertex: &GraphicsShader,
     fragment: &GraphicsShader,
+    depth_test: bool,
 ) -> (GraphicsProgram, GraphicsBindings, OutGraphicsBindings) {
     // the adapter is the handler to the physical graphics unit
 
@@ -409,25 +520,79 @@ pub async fn graphics_compile(
             limits: wgpu::Limits::default(),
         })
         .await;
+    let device = Arc::new(device);
+    let poller = Arc::new(Poller::new(device.clone()));
+
+    build(
+        vec_buffer,
+        vertex,
+        fragment,
+        depth_test,
+        Arc::new(surface),
+        device,
+        Arc::new(queue),
+        poller,
+    )
+}
+
+// Compile another program on the device of one that already exists so they can draw into the same pass
+pub fn graphics_compile_shared(
+    vec_buffer: &mut [wgpu::VertexAttributeDescriptor; 32],
+    program: &GraphicsProgram,
+    vertex: &GraphicsShader,
+    fragment: &GraphicsShader,
+    depth_test: bool,
+) -> (GraphicsProgram, GraphicsBindings, OutGraphicsBindings) {
+    build(
+        vec_buffer,
+        vertex,
+        fragment,
+        depth_test,
+        program.surface.clone(),
+        program.device.clone(),
+        program.queue.clone(),
+        program.poller.clone(),
+    )
+}
 
-    let (mut program_bindings1, out_program_bindings1, program_bindings2, out_program_bindings2) =
-        create_bindings(&vertex, &fragment);
+fn build(
+    vec_buffer: &mut [wgpu::VertexAttributeDescriptor; 32],
+    vertex: &GraphicsShader,
+    fragment: &GraphicsShader,
+    depth_test: bool,
+    surface: Arc<wgpu::Surface>,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    poller: Arc<Poller>,
+) -> (GraphicsProgram, GraphicsBindings, OutGraphicsBindings) {
+    let (
+        mut program_bindings1,
+        mut out_program_bindings1,
+        program_bindings2,
+        out_program_bindings2,
+    ) = create_bindings(&vertex, &fragment);
 
     for i in &program_bindings1.bindings[..] {
-        if i.qual.contains(&QUALIFIER::VERTEX) {
-            vec_buffer[i.binding_number as usize] = wgpu::VertexAttributeDescriptor {
-                offset: 0,
-                // This is our connection to shader.vert
-                // TODO WOW I had an error because I hardcoded the format's below. That should not be a thing
-                shader_location: i.binding_number,
-                format: if i.gtype == GLSLTYPE::Vec3 {
-                    wgpu::VertexFormat::Float3
-                } else if i.gtype == GLSLTYPE::Vec2 {
-                    wgpu::VertexFormat::Float2
-                } else {
-                    wgpu::VertexFormat::Float
-                },
-            };
+        if is_vertex_attribute(&i.qual) {
+            // A mat4 is read as four vec4 columns at consecutive locations
+            for column in 0..attribute_locations(&i.gtype) {
+                vec_buffer[(i.binding_number + column) as usize] =
+                    wgpu::VertexAttributeDescriptor {
+                        offset: (column * 16) as wgpu::BufferAddress,
+                        // This is our connection to shader.vert
+                        // TODO WOW I had an error because I hardcoded the format's below. That should not be a thing
+                        shader_location: i.binding_number + column,
+                        format: if i.gtype == GLSLTYPE::Vec4 || i.gtype == GLSLTYPE::Mat4 {
+                            wgpu::VertexFormat::Float4
+                        } else if i.gtype == GLSLTYPE::Vec3 {
+                            wgpu::VertexFormat::Float3
+                        } else if i.gtype == GLSLTYPE::Vec2 {
+                            wgpu::VertexFormat::Float2
+                        } else {
+                            wgpu::VertexFormat::Float
+                        },
+                    };
+            }
         }
     }
 
@@ -440,7 +605,9 @@ pub async fn graphics_compile(
             bind_entry.push(wgpu::BindGroupLayoutEntry {
                 binding: i.binding_number,
                 visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
-                ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: i.qual.contains(&QUALIFIER::DYNAMIC),
+                },
             });
             are_bind_enties = true;
         } else if i.qual.contains(&QUALIFIER::BUFFER) {
@@ -456,14 +623,14 @@ pub async fn graphics_compile(
         } else {
             vertex_binding_desc.push(wgpu::VertexBufferDescriptor {
                 stride: (i.gtype.size_of()) as wgpu::BufferAddress,
-                step_mode: if i.qual.contains(&QUALIFIER::VERTEX) {
-                    wgpu::InputStepMode::Vertex
-                } else {
+                step_mode: if i.qual.contains(&QUALIFIER::INSTANCE) {
                     wgpu::InputStepMode::Instance
+                } else {
+                    wgpu::InputStepMode::Vertex
                 },
                 // If you have a struct that specifies your vertex, this is a 1 to 1 mapping of that struct
-                attributes: &vec_buffer
-                    [((i.binding_number) as usize)..((i.binding_number + 1) as usize)],
+                attributes: &vec_buffer[((i.binding_number) as usize)
+                    ..((i.binding_number + attribute_locations(&i.gtype)) as usize)],
             });
         }
     }
@@ -473,7 +640,39 @@ pub async fn graphics_compile(
             bind_entry.push(wgpu::BindGroupLayoutEntry {
                 binding: i.binding_number,
                 visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
-                ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: i.qual.contains(&QUALIFIER::DYNAMIC),
+                },
+            });
+            are_bind_enties = true;
+        } else if i.qual.contains(&QUALIFIER::BUFFER) && i.qual.contains(&QUALIFIER::IN) {
+            bind_entry.push(wgpu::BindGroupLayoutEntry {
+                binding: i.binding_number,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::StorageBuffer {
+                    dynamic: false,
+                    readonly: false,
+                },
+            });
+            are_bind_enties = true;
+        }
+    }
+
+    // Storage buffers that only the fragment shader declared still need a layout entry
+    for i in &out_program_bindings2.bindings {
+        if i.qual.contains(&QUALIFIER::BUFFER)
+            && !out_program_bindings1
+                .bindings
+                .iter()
+                .any(|x| x.name == i.name)
+        {
+            bind_entry.push(wgpu::BindGroupLayoutEntry {
+                binding: i.binding_number,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::StorageBuffer {
+                    dynamic: false,
+                    readonly: false,
+                },
             });
             are_bind_enties = true;
         }
@@ -569,7 +768,7 @@ pub async fn graphics_compile(
         color_states: &[wgpu::ColorStateDescriptor {
             // Specify the size of the color data in the buffer
             // Bgra8UnormSrgb is specifically used since it is guaranteed to work on basically all browsers (32bit)
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            format: COLOR_FORMAT,
             // Here is where you can do some fancy stuff for transitioning colors/brightness between frames. Replace defaults to taking all of the current frame and none of the next frame.
             // This can be changed by specifying the modifier for either of the values from src/dest frames or changing the operation used to combine them(instead of addition maybe Max/Min)
             color_blend: wgpu::BlendDescriptor::REPLACE,
@@ -578,7 +777,20 @@ pub async fn graphics_compile(
             write_mask: wgpu::ColorWrite::ALL,
         }],
         // We can add an optional stencil descriptor which allows for effects that you would see in Microsoft Powerpoint like fading/swiping to the next slide
-        depth_stencil_state: None,
+        // Fragments that are further away than what was already drawn are discarded when testing depth
+        depth_stencil_state: if depth_test {
+            Some(wgpu::DepthStencilStateDescriptor {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+                stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+                stencil_read_mask: 0,
+                stencil_write_mask: 0,
+            })
+        } else {
+            None
+        },
         vertex_state: wgpu::VertexStateDescriptor {
             index_format: wgpu::IndexFormat::Uint16,
             vertex_buffers: &vertex_binding_desc[..],
@@ -594,10 +806,22 @@ pub async fn graphics_compile(
     // TODO This is ugly, We should be able to bind across different stages and such
     program_bindings1.samplers = program_bindings2.samplers;
     program_bindings1.textures = program_bindings2.textures;
+    for i in out_program_bindings2.bindings {
+        if i.qual.contains(&QUALIFIER::BUFFER)
+            && !out_program_bindings1
+                .bindings
+                .iter()
+                .any(|x| x.name == i.name)
+        {
+            out_program_bindings1.bindings.push(i);
+        }
+    }
     (
         GraphicsProgram {
             pipeline: render_pipeline,
             bind_group_layout,
+            depth_test,
+            poller,
             device,
             queue,
             surface,
@@ -626,7 +850,7 @@ pub fn bind_sampler(
             out_bindings.samplers[x] */
         }
     };
-    binding.data = Some(sample);
+    binding.data = Some(Arc::new(sample));
 }
 
 pub fn bind_texture(
@@ -648,7 +872,83 @@ pub fn bind_texture(
             out_bindings.samplers[x] */
         }
     };
-    binding.data = Some(texture);
+    binding.data = Some(Arc::new(texture));
+}
+
+// Upload rgba8 pixels, row by row from the top, into a texture that can be bound with bind_texture
+pub fn create_texture_rgba(
+    program: &dyn Program,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> wgpu::TextureView {
+    if width == 0 || height == 0 {
+        panic!(
+            "A texture needs to be at least 1 by 1 but it was {} by {}",
+            width, height
+        )
+    }
+    let bytes = 4 * width as usize * height as usize;
+    if pixels.len() != bytes {
+        panic!(
+            "A {} by {} texture needs {} bytes of rgba pixels but {} were given",
+            width,
+            height,
+            bytes,
+            pixels.len()
+        )
+    }
+
+    let extent = wgpu::Extent3d {
+        width,
+        height,
+        depth: 1,
+    };
+    let texture = program
+        .get_device()
+        .create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            label: None,
+        });
+
+    // Rows of a copy into a texture have to start at an aligned offset
+    let row = 4 * width as usize;
+    let padded_row = (row + 255) / 256 * 256;
+    let mut padded = vec![0u8; padded_row * height as usize];
+    for (y, line) in pixels.chunks(row).enumerate() {
+        padded[y * padded_row..y * padded_row + row].copy_from_slice(line);
+    }
+
+    let staging = program
+        .get_device()
+        .create_buffer_with_data(&padded, wgpu::BufferUsage::COPY_SRC);
+    let mut encoder = program
+        .get_device()
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_texture(
+        wgpu::BufferCopyView {
+            buffer: &staging,
+            offset: 0,
+            bytes_per_row: padded_row as u32,
+            rows_per_image: height,
+        },
+        wgpu::TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        extent,
+    );
+    program.get_queue().submit(&[encoder.finish()]);
+
+    texture.create_default_view()
 }
 
 fn draw(
@@ -662,9 +962,153 @@ fn draw(
 fn draw_indexed(
     rpass: &mut wgpu::RenderPass,
     indexes: core::ops::Range<u32>,
+    base_vertex: i32,
     instances: core::ops::Range<u32>,
 ) {
-    rpass.draw_indexed(indexes, 0, instances);
+    rpass.draw_indexed(indexes, base_vertex, instances);
+}
+
+// The part of the bound vertices, or of the indicies if there are any, that one draw call uses
+// A range that isn't given covers everything that is bound
+#[derive(Debug, Clone)]
+pub struct Draw {
+    pub elements: Option<core::ops::Range<u32>>,
+    // Added to every index before the vertex is read so a sub mesh can use indicies starting from 0
+    pub base_vertex: i32,
+    pub instances: Option<core::ops::Range<u32>>,
+}
+
+impl Draw {
+    pub fn all() -> Draw {
+        Draw {
+            elements: None,
+            base_vertex: 0,
+            instances: None,
+        }
+    }
+
+    pub fn vertices(vertices: core::ops::Range<u32>) -> Draw {
+        Draw {
+            elements: Some(vertices),
+            base_vertex: 0,
+            instances: None,
+        }
+    }
+
+    pub fn indicies(indicies: core::ops::Range<u32>, base_vertex: i32) -> Draw {
+        Draw {
+            elements: Some(indicies),
+            base_vertex,
+            instances: None,
+        }
+    }
+
+    // Three vertices with nothing bound to them, the vertex shader places the corners from gl_VertexID
+    pub fn full_screen_triangle() -> Draw {
+        Draw::vertices(0..3)
+    }
+
+    pub fn instances(self, instances: core::ops::Range<u32>) -> Draw {
+        Draw {
+            instances: Some(instances),
+            ..self
+        }
+    }
+}
+
+// A rectangle of a target in pixels from its top left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn fits(&self, width: u32, height: u32) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.x as u64 + self.width as u64 <= width as u64
+            && self.y as u64 + self.height as u64 <= height as u64
+    }
+}
+
+// Where the draws of a program land on a target of the given size, such as one half of a split screen
+// These stay set for the rest of the pass so later programs without options draw the same way
+#[derive(Debug, Clone)]
+pub struct DrawOptions {
+    pub target_width: u32,
+    pub target_height: u32,
+    // The whole target when there isn't one
+    pub viewport: Option<Rect>,
+    // The depths that 0 to 1 are mapped to
+    pub depth_range: core::ops::Range<f32>,
+    // Nothing outside of the scissor is drawn, the whole target when there isn't one
+    pub scissor: Option<Rect>,
+    pub stencil_reference: u32,
+    // The color used by blend factors that are a constant
+    pub blend_constant: wgpu::Color,
+}
+
+impl DrawOptions {
+    // Draw on all of the target
+    pub fn new(target_width: u32, target_height: u32) -> DrawOptions {
+        DrawOptions {
+            target_width,
+            target_height,
+            viewport: None,
+            depth_range: 0.0..1.0,
+            scissor: None,
+            stencil_reference: 0,
+            blend_constant: wgpu::Color::WHITE,
+        }
+    }
+
+    fn apply(&self, rpass: &mut wgpu::RenderPass) {
+        let whole = Rect {
+            x: 0,
+            y: 0,
+            width: self.target_width,
+            height: self.target_height,
+        };
+
+        let viewport = self.viewport.unwrap_or(whole);
+        if !viewport.fits(self.target_width, self.target_height) {
+            panic!(
+                "The viewport {:?} is not inside of the {}x{} target",
+                viewport, self.target_width, self.target_height
+            )
+        }
+
+        let depth = &self.depth_range;
+        if !(0.0 <= depth.start && depth.start <= depth.end && depth.end <= 1.0) {
+            panic!(
+                "The depth range {:?} needs to be an increasing range between 0 and 1",
+                depth
+            )
+        }
+
+        let scissor = self.scissor.unwrap_or(whole);
+        if !scissor.fits(self.target_width, self.target_height) {
+            panic!(
+                "The scissor {:?} is not inside of the {}x{} target",
+                scissor, self.target_width, self.target_height
+            )
+        }
+
+        rpass.set_viewport(
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+            depth.start,
+            depth.end,
+        );
+        rpass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
+        rpass.set_stencil_reference(self.stencil_reference);
+        rpass.set_blend_color(self.blend_constant);
+    }
 }
 
 fn buffer_map_setup<'a>(
@@ -674,7 +1118,7 @@ fn buffer_map_setup<'a>(
     let mut buffer_map = HashMap::new();
 
     for i in bindings.bindings.iter() {
-        if !i.qual.contains(&QUALIFIER::VERTEX) {
+        if !is_vertex_attribute(&i.qual) {
             buffer_map.insert(i.binding_number, i);
         }
     }
@@ -688,48 +1132,45 @@ fn buffer_map_setup<'a>(
     buffer_map
 }
 
-pub fn graphics_run<'a>(
-    program: &GraphicsProgram,
-    mut rpass: wgpu::RenderPass<'a>,
-    bind_group: &'a mut wgpu::BindGroup,
-    bindings: &'a GraphicsBindings,
-    out_bindings: &'a OutGraphicsBindings,
-) -> wgpu::RenderPass<'a> {
-    /* let mut encoder = program
-    .device
-    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }); */
+fn bind_group_key(
+    bindings: &GraphicsBindings,
+    out_bindings: &OutGraphicsBindings,
+) -> Vec<ResourceKey> {
+    let buffer_map = buffer_map_setup(bindings, out_bindings);
 
-    let bind = bindings
-        .bindings
-        .iter()
-        .find(|i| i.qual.contains(&QUALIFIER::VERTEX));
+    let mut key: Vec<ResourceKey> = buffer_map
+        .values()
+        .map(|b| resource_key(b.binding_number, &b.data, b.size))
+        .collect();
+    // The order of a HashMap isn't stable so sort the keys
+    key.sort_by_key(|k| k.binding_number());
 
-    let verts: u32 = if let Some(b) = bind {
-        b.length.unwrap() as u32
-    } else {
-        3
-    };
+    for i in bindings.samplers.iter() {
+        key.push(resource_key(i.binding_number, &i.data, None));
+    }
 
-    let bind = bindings
-        .bindings
-        .iter()
-        .find(|i| i.qual.contains(&QUALIFIER::LOOP));
+    for i in bindings.textures.iter() {
+        key.push(resource_key(i.binding_number, &i.data, None));
+    }
 
-    let instances: u32 = if let Some(b) = bind {
-        b.length.unwrap() as u32
-    } else {
-        1
-    };
+    key
+}
 
-    let buffer_map = buffer_map_setup(bindings, &out_bindings);
+fn create_bind_group(
+    program: &GraphicsProgram,
+    bindings: &GraphicsBindings,
+    out_bindings: &OutGraphicsBindings,
+) -> wgpu::BindGroup {
+    let buffer_map = buffer_map_setup(bindings, out_bindings);
 
     let mut empty_vec = Vec::new();
 
-    for i in 0..(buffer_map.len()) {
-        let b = buffer_map.get(&(i as u32)).unwrap_or_else(|| {
-            panic!("I assumed all bindings would be buffers but I guess that has been invalidated")
-        });
-
+    // Storage buffers only declared in the fragment shader can be numbered after the samplers and textures
+    // so don't assume the buffer binding numbers are contiguous
+    for b in buffer_map.values() {
+        let size = b
+            .size
+            .unwrap_or_else(|| panic!("The size of {} was not set", &b.name));
         empty_vec.push(wgpu::Binding {
             binding: b.binding_number,
             resource: wgpu::BindingResource::Buffer {
@@ -737,9 +1178,12 @@ pub fn graphics_run<'a>(
                     .data
                     .as_ref()
                     .unwrap_or_else(|| panic!("The binding of {} was not set", &b.name)),
-                range: 0..b
-                    .length
-                    .unwrap_or_else(|| panic!("The size of {} was not set", &b.name)),
+                // A dynamic uniform only binds the value of one object at a time
+                range: if b.qual.contains(&QUALIFIER::DYNAMIC) {
+                    0..b.gtype.size_of() as u64
+                } else {
+                    0..size
+                },
             },
         });
     }
@@ -770,33 +1214,240 @@ pub fn graphics_run<'a>(
         label: None,
     };
 
-    *bind_group = program.device.create_bind_group(bgd);
+    program.device.create_bind_group(bgd)
+}
+
+// Dynamic uniforms are offset to the value of the object being drawn and any others use the first value they were given
+// The offsets are given in the order of the binding numbers
+fn dynamic_offsets(
+    bindings: &GraphicsBindings,
+    out_bindings: &OutGraphicsBindings,
+    object: Option<(&str, u32)>,
+) -> Vec<u32> {
+    let buffer_map = buffer_map_setup(bindings, out_bindings);
+
+    let mut dynamic: Vec<&DefaultBinding> = buffer_map
+        .values()
+        .copied()
+        .filter(|b| b.qual.contains(&QUALIFIER::DYNAMIC))
+        .collect();
+    dynamic.sort_by_key(|b| b.binding_number);
+
+    dynamic
+        .iter()
+        .map(|b| match object {
+            Some((name, i)) if b.name == name => i * dynamic_stride(&b.gtype) as u32,
+            _ => 0,
+        })
+        .collect()
+}
+
+pub fn graphics_run<'a>(
+    program: &'a GraphicsProgram,
+    rpass: RenderPass<'a>,
+    bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a OutGraphicsBindings,
+) -> RenderPass<'a> {
+    graphics_draw(program, rpass, bindings, out_bindings, &[Draw::all()], None)
+}
+
+// Make several draw calls with the same bindings, such as the sub meshes of one vertex and index buffer
+pub fn graphics_run_draws<'a>(
+    program: &'a GraphicsProgram,
+    rpass: RenderPass<'a>,
+    bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a OutGraphicsBindings,
+    draws: &[Draw],
+) -> RenderPass<'a> {
+    graphics_draw(program, rpass, bindings, out_bindings, draws, None)
+}
+
+// Make the draw calls with the viewport, scissor and other state of the options
+pub fn graphics_run_with<'a>(
+    program: &'a GraphicsProgram,
+    mut rpass: RenderPass<'a>,
+    bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a OutGraphicsBindings,
+    draws: &[Draw],
+    options: &DrawOptions,
+) -> RenderPass<'a> {
+    options.apply(&mut rpass);
+    graphics_draw(program, rpass, bindings, out_bindings, draws, None)
+}
+
+// Draw only the given range of instances of the instance parameters
+pub fn graphics_run_instanced<'a>(
+    program: &'a GraphicsProgram,
+    rpass: RenderPass<'a>,
+    bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a OutGraphicsBindings,
+    instances: core::ops::Range<u32>,
+) -> RenderPass<'a> {
+    graphics_draw(
+        program,
+        rpass,
+        bindings,
+        out_bindings,
+        &[Draw::all().instances(instances)],
+        None,
+    )
+}
+
+// Without a range every instance of the instance parameters is drawn, or a single instance if there are none
+fn instance_range(
+    bindings: &GraphicsBindings,
+    instances: Option<core::ops::Range<u32>>,
+) -> core::ops::Range<u32> {
+    let lengths: Vec<(&str, u32)> = bindings
+        .bindings
+        .iter()
+        .filter(|i| i.qual.contains(&QUALIFIER::INSTANCE))
+        .map(|i| {
+            (
+                i.name.as_str(),
+                i.length
+                    .unwrap_or_else(|| panic!("The binding of {} was not set", &i.name))
+                    as u32,
+            )
+        })
+        .collect();
+
+    match instances {
+        Some(range) => {
+            for (name, length) in lengths.iter() {
+                if range.end > *length {
+                    panic!(
+                        "{} only has {} instances but instances up to {} were drawn",
+                        name, length, range.end
+                    )
+                }
+            }
+            range
+        }
+        None => {
+            if lengths.iter().any(|(_, x)| *x != lengths[0].1) {
+                panic!(
+                    "The instance parameters have different lengths: {:?}",
+                    lengths
+                )
+            }
+            match lengths.first() {
+                Some((_, length)) => 0..*length,
+                None => 0..1,
+            }
+        }
+    }
+}
+
+// Draw once for every object with the dynamic uniform name set to the value given for that object
+// All of the values are written into one buffer and each draw selects its own with a dynamic offset
+pub fn draw_each<'a, O, V: UniformValue>(
+    program: &'a GraphicsProgram,
+    rpass: RenderPass<'a>,
+    bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a mut OutGraphicsBindings,
+    name: &str,
+    objects: &[O],
+    value: impl Fn(&O) -> V,
+) -> RenderPass<'a> {
+    let values: Vec<V> = objects.iter().map(value).collect();
+    bind_dynamic(program, bindings, out_bindings, &values, name);
+
+    let out_bindings: &'a OutGraphicsBindings = out_bindings;
+    graphics_draw(
+        program,
+        rpass,
+        bindings,
+        out_bindings,
+        &[Draw::all()],
+        Some((name, objects.len() as u32)),
+    )
+}
+
+fn graphics_draw<'a>(
+    program: &'a GraphicsProgram,
+    mut rpass: RenderPass<'a>,
+    bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a OutGraphicsBindings,
+    draws: &[Draw],
+    each: Option<(&str, u32)>,
+) -> RenderPass<'a> {
+    /* let mut encoder = program
+    .device
+    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }); */
+
+    // The pipeline of a program only works in passes whose depth attachment matches its depth test
+    match (program.depth_test, rpass.has_depth) {
+        (true, false) => panic!(
+            "This program was compiled with a depth test but the pass has no depth attachment, give the pass a view from create_depth_view"
+        ),
+        (false, true) => panic!(
+            "This program was compiled without a depth test but the pass has a depth attachment, draw it in a pass without one"
+        ),
+        _ => {}
+    }
+
+    // Dynamic uniforms are left out of the context, so only draw_each makes sure they have a value
+    if each.is_none() {
+        if let Some(b) = bindings
+            .bindings
+            .iter()
+            .chain(out_bindings.bindings.iter())
+            .find(|b| b.qual.contains(&QUALIFIER::DYNAMIC))
+        {
+            panic!(
+                "{} is a dynamic uniform, programs with one can only be drawn with draw_each",
+                b.name
+            )
+        }
+    }
+
+    let key = bind_group_key(bindings, out_bindings);
+
+    // Only build a new bind group when one of the bound resources has changed
+    if !bindings.bind_groups.contains(&key) {
+        let bind_group = create_bind_group(program, bindings, out_bindings);
+        bindings.bind_groups.insert(key.clone(), bind_group);
+    }
+
+    // The render pass holds on to the bindings from here on
+    let bindings: &'a GraphicsBindings = bindings;
+
+    let bind = bindings
+        .bindings
+        .iter()
+        .find(|i| i.qual.contains(&QUALIFIER::VERTEX));
+
+    let verts: Option<u32> = bind.map(|b| {
+        b.length
+            .unwrap_or_else(|| panic!("The binding of {} was not set", &b.name)) as u32
+    });
+
     {
         // The order must be set_pipeline -> set a bind_group if needed -> set a vertex buffer -> set an index buffer -> do draw
         // Otherwise we crash out
-        /* rpass.set_pipeline(&program.pipeline); */
-
-        rpass.set_bind_group(0, bind_group, &[]);
+        // Another program may have drawn in this pass since its pipeline was set
+        rpass.set_pipeline(&program.pipeline);
 
         if bindings.indicies.is_some() {
             rpass.set_index_buffer(&bindings.indicies.as_ref().unwrap(), 0, 0);
         }
 
-        for i in 0..(bindings.bindings.len()) {
-            let b = bindings.bindings.get(i as usize).expect(
-                "I assumed all bindings would be buffers but I guess that has been invalidated",
+        // Vertex buffers are numbered in the order the vertex attributes were declared
+        // which is not their location since a mat4 takes up four of those
+        let attributes = bindings
+            .bindings
+            .iter()
+            .filter(|b| is_vertex_attribute(&b.qual));
+        for (slot, b) in attributes.enumerate() {
+            rpass.set_vertex_buffer(
+                slot as u32,
+                &b.data
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("The binding of {} was not set", &b.name)),
+                0,
+                0,
             );
-
-            if b.qual.contains(&QUALIFIER::VERTEX) {
-                rpass.set_vertex_buffer(
-                    b.binding_number,
-                    &b.data
-                        .as_ref()
-                        .unwrap_or_else(|| panic!("The binding of {} was not set", &b.name)),
-                    0,
-                    0,
-                );
-            }
         }
 
         for i in 0..(out_bindings.bindings.len()) {
@@ -816,10 +1467,44 @@ pub fn graphics_run<'a>(
             }
         }
 
-        if bindings.indicies.is_some() {
-            draw_indexed(&mut rpass, 0..bindings.index_len.unwrap(), 0..instances)
-        } else {
-            draw(&mut rpass, 0..verts, 0..instances);
+        let objects = match each {
+            Some((_, count)) => count,
+            None => 1,
+        };
+
+        for i in 0..objects {
+            let offsets = dynamic_offsets(bindings, out_bindings, each.map(|(name, _)| (name, i)));
+            rpass.set_bind_group(0, bindings.bind_groups.get(&key), &offsets);
+
+            for d in draws.iter() {
+                let instances = instance_range(bindings, d.instances.clone());
+                if let Some(index_len) = bindings.index_len {
+                    let indexes = d.elements.clone().unwrap_or(0..index_len);
+                    if indexes.end > index_len {
+                        panic!(
+                            "Only {} indicies are bound but indicies up to {} were drawn",
+                            index_len, indexes.end
+                        )
+                    }
+                    draw_indexed(&mut rpass, indexes, d.base_vertex, instances)
+                } else {
+                    if d.base_vertex != 0 {
+                        panic!("A base vertex can only be used when drawing with indicies")
+                    }
+                    let vertices = match (d.elements.clone(), verts) {
+                        (Some(range), Some(length)) if range.end > length => panic!(
+                            "Only {} vertices are bound but vertices up to {} were drawn",
+                            length, range.end
+                        ),
+                        (Some(range), _) => range,
+                        (None, Some(length)) => 0..length,
+                        (None, None) => panic!(
+                            "There are no vertex parameters to count the vertices of, draw a range of vertices instead"
+                        ),
+                    };
+                    draw(&mut rpass, vertices, instances);
+                }
+            }
         }
     }
     rpass
@@ -829,98 +1514,251 @@ pub fn graphics_run<'a>(
 
 pub fn graphics_run_indicies<'a>(
     program: &'a GraphicsProgram,
-    pass: wgpu::RenderPass<'a>,
-    bind_group: &'a mut wgpu::BindGroup,
+    pass: RenderPass<'a>,
     bindings: &'a mut GraphicsBindings,
     out_bindings: &'a OutGraphicsBindings,
     indicies: &Vec<u16>,
-) -> wgpu::RenderPass<'a> {
-    bindings.indicies = Some(
-        program
-            .get_device()
-            .create_buffer_with_data(indicies.as_slice().as_bytes(), wgpu::BufferUsage::INDEX),
-    );
-    bindings.index_len = Some(indicies.len() as u32);
-    graphics_run(program, pass, bind_group, bindings, out_bindings)
+) -> RenderPass<'a> {
+    bind_indicies(program, bindings, indicies);
+    graphics_run(program, pass, bindings, out_bindings)
 }
 
-pub fn graphics_pipe(
+// Draws with these bindings use the indicies until they are replaced
+pub fn bind_indicies(
     program: &GraphicsProgram,
-    rpass: wgpu::RenderPass,
-    bind_group: &mut wgpu::BindGroup,
-    mut in_bindings: GraphicsBindings,
-    mut out_bindings: &mut OutGraphicsBindings,
-    result_vec: Vec<DefaultBinding>,
+    bindings: &mut GraphicsBindings,
+    indicies: &Vec<u16>,
 ) {
-    for i in result_vec {
-        let binding = match in_bindings.bindings.iter().position(|x| x.name == i.name) {
-            Some(x) => &mut in_bindings.bindings[x],
-            None => {
-                let x = out_bindings
-                    .bindings
-                    .iter()
-                    .position(|x| x.name == i.name)
-                    .expect("We couldn't find the binding");
-                &mut out_bindings.bindings[x]
-            }
-        };
-
-        /*          todo Check the types somewhere
-        if !acceptable_types.contains(&binding.gtype) {
-            panic!(
-                "The type of the value you provided is not what was expected, {:?}",
-                &binding.gtype
-            );
-        } */
-
-        binding.data = Some(i.data.unwrap());
-        binding.length = Some(i.length.unwrap());
-    }
+    bindings.indicies = Some(Arc::new(program.get_device().create_buffer_with_data(
+        indicies.as_slice().as_bytes(),
+        wgpu::BufferUsage::INDEX,
+    )));
+    bindings.index_len = Some(indicies.len() as u32);
+}
 
-    graphics_run(program, rpass, bind_group, &in_bindings, out_bindings);
+// Draws with these bindings use the indicies of the mesh, which are shared instead of uploaded again
+pub fn bind_mesh_indicies(bindings: &mut GraphicsBindings, mesh: &Mesh) {
+    let (buffer, len) = mesh
+        .indicies()
+        .expect("The mesh has no indicies to bind, use set_indicies first");
+    bindings.indicies = Some(Arc::clone(buffer));
+    bindings.index_len = Some(len);
 }
 
-pub fn default_bind_group(program: &GraphicsProgram) -> wgpu::BindGroup {
-    let bind_group_layout =
-        program
-            .device
-            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                // The layout of for each binding specify a number to connect with the bind_group, a visibility to specify for which stage it's for and a type
-                bindings: &[],
-                label: None,
-            });
+// Pipe the results of a program into this one and draw with them
+// Array results of a compute program can be used directly as vertex buffers for vertex or per instance parameters
+pub fn graphics_pipe<'a>(
+    program: &'a GraphicsProgram,
+    rpass: RenderPass<'a>,
+    in_bindings: &'a mut GraphicsBindings,
+    out_bindings: &'a mut OutGraphicsBindings,
+    result_vec: Vec<DefaultBinding>,
+    mapping: &[(&str, &str)],
+) -> RenderPass<'a> {
+    pipe_bindings(in_bindings, out_bindings, result_vec, mapping);
 
-    let bgd = &wgpu::BindGroupDescriptor {
-        layout: &bind_group_layout,
-        bindings: &[],
-        label: None,
-    };
+    graphics_run(program, rpass, in_bindings, out_bindings)
+}
 
-    program.device.create_bind_group(bgd)
+// Collect the in out storage buffers written by a draw so they can be read like the results of a compute run
+// The render pass using these bindings needs to have been submitted first
+pub fn graphics_results(out_bindings: OutGraphicsBindings) -> Vec<DefaultBinding> {
+    out_bindings
+        .bindings
+        .into_iter()
+        .filter(|i| i.qual.contains(&QUALIFIER::BUFFER) && i.data.is_some())
+        .collect()
 }
 
 pub fn setup_render_pass<'a>(
     program: &'a GraphicsProgram,
     encoder: &'a mut wgpu::CommandEncoder,
     frame: &'a wgpu::SwapChainOutput,
-) -> wgpu::RenderPass<'a> {
-    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+) -> RenderPass<'a> {
+    let mut rpass = begin_pass(encoder, PassDescriptor::new(&frame.view));
+
+    rpass.set_pipeline(&program.pipeline);
+    rpass
+}
+
+// The format of everything a program draws colors to, the swap chain and render targets
+pub const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// A depth attachment for programs compiled with a depth test, it needs to be the size of the color attachment
+pub fn create_depth_view(program: &GraphicsProgram, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = program.device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        label: None,
+    });
+    texture.create_default_view()
+}
+
+// A texture to draw to instead of the window, which can be saved or sampled by another program
+pub struct RenderTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RenderTarget {
+    // Copy what was drawn back from the gpu as rgba8 pixels, row by row from the top
+    pub fn read_pixels(&self, program: &dyn Program) -> Vec<u8> {
+        read_texture(
+            program,
+            &self.texture,
+            self.width,
+            self.height,
+            COLOR_FORMAT,
+        )
+    }
+
+    pub fn save_png(&self, program: &dyn Program, path: &str) -> Result<(), CaptureError> {
+        write_png(path, self.width, self.height, &self.read_pixels(program))
+    }
+}
+
+// Draw to it by giving its view to PassDescriptor::new
+pub fn create_render_target(program: &dyn Program, width: u32, height: u32) -> RenderTarget {
+    if width == 0 || height == 0 {
+        panic!(
+            "A render target needs to be at least 1 by 1 but it was {} by {}",
+            width, height
+        )
+    }
+    let texture = program
+        .get_device()
+        .create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: COLOR_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT
+                | wgpu::TextureUsage::COPY_SRC
+                | wgpu::TextureUsage::SAMPLED,
+            label: None,
+        });
+    let view = texture.create_default_view();
+    RenderTarget {
+        texture,
+        view,
+        width,
+        height,
+    }
+}
+
+// A pass from begin_pass, which remembers whether it has a depth attachment so each draw can check it against its program
+// Everything else is done through the wgpu render pass it derefs to
+pub struct RenderPass<'a> {
+    pass: wgpu::RenderPass<'a>,
+    has_depth: bool,
+}
+
+impl<'a> Deref for RenderPass<'a> {
+    type Target = wgpu::RenderPass<'a>;
+
+    fn deref(&self) -> &wgpu::RenderPass<'a> {
+        &self.pass
+    }
+}
+
+impl<'a> DerefMut for RenderPass<'a> {
+    fn deref_mut(&mut self) -> &mut wgpu::RenderPass<'a> {
+        &mut self.pass
+    }
+}
+
+// What an attachment holds when a pass begins
+// Keep draws on top of what earlier passes left behind, such as for an overlay
+#[derive(Debug, Clone, Copy)]
+pub enum Load<T> {
+    Clear(T),
+    Keep,
+}
+
+// The attachments that every program drawing in a pass shares
+pub struct PassDescriptor<'a> {
+    pub color: &'a wgpu::TextureView,
+    pub color_load: Load<wgpu::Color>,
+    pub color_store: wgpu::StoreOp,
+    pub depth: Option<&'a wgpu::TextureView>,
+    pub depth_load: Load<f32>,
+    pub depth_store: wgpu::StoreOp,
+}
+
+impl<'a> PassDescriptor<'a> {
+    // Clear the color to transparent and keep what is drawn
+    pub fn new(color: &'a wgpu::TextureView) -> PassDescriptor<'a> {
+        PassDescriptor {
+            color,
+            color_load: Load::Clear(wgpu::Color::TRANSPARENT),
+            color_store: wgpu::StoreOp::Store,
+            depth: None,
+            depth_load: Load::Clear(1.0),
+            depth_store: wgpu::StoreOp::Store,
+        }
+    }
+}
+
+fn load_op<T: Default>(load: Load<T>) -> (wgpu::LoadOp, T) {
+    match load {
+        Load::Clear(x) => (wgpu::LoadOp::Clear, x),
+        Load::Keep => (wgpu::LoadOp::Load, T::default()),
+    }
+}
+
+// Start a pass that any number of programs can draw into, each draw switches to the pipeline of its program
+pub fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    pass: PassDescriptor<'a>,
+) -> RenderPass<'a> {
+    let (color_load, clear_color) = load_op(pass.color_load);
+    let (depth_load, clear_depth) = load_op(pass.depth_load);
+
+    let has_depth = pass.depth.is_some();
+    let pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         // color_attachments is literally where we draw the colors to
         color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
             // The texture we are saving the colors to
-            attachment: &frame.view,
+            attachment: pass.color,
             resolve_target: None,
-            load_op: wgpu::LoadOp::Clear,
-            store_op: wgpu::StoreOp::Store,
+            load_op: color_load,
+            store_op: pass.color_store,
             // Default color for all pixels
             // Use Color to specify a specific rgba value
-            clear_color: wgpu::Color::TRANSPARENT,
+            clear_color,
         }],
-        depth_stencil_attachment: None,
+        depth_stencil_attachment: pass.depth.map(|attachment| {
+            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment,
+                depth_load_op: depth_load,
+                depth_store_op: pass.depth_store,
+                clear_depth,
+                stencil_load_op: wgpu::LoadOp::Clear,
+                stencil_store_op: wgpu::StoreOp::Store,
+                clear_stencil: 0,
+            }
+        }),
     });
-
-    rpass.set_pipeline(&program.pipeline);
-    rpass
+    RenderPass { pass, has_depth }
 }
 
 #[derive(Debug)]
@@ -957,6 +1795,35 @@ pub const fn valid_fragment_shader(frag: &GraphicsShader) {
     panic!("This is not a valid fragment shader! Remember you need 'color' as an out of a fragment shader")
 }
 
+// A fragment in is written by the vertex out with the same name so when both have frames they must agree
+// Uniforms and buffers declared in both stages are one binding so their frames must agree as well
+pub const fn valid_frames_between(vert: &GraphicsShader, frag: &GraphicsShader) {
+    let mut i = 0;
+    while i < frag.params.len() {
+        let input = &frag.params[i];
+        let shared = has_uniform_qual(input.qual) || has_buffer_qual(input.qual);
+        if has_in_qual(input.qual) && input.frames.len() != 0 {
+            let mut j = 0;
+            while j < vert.params.len() {
+                let output = &vert.params[j];
+                if string_compare(output.name, input.name)
+                    && output.frames.len() != 0
+                    && !same_frames(output.frames, input.frames)
+                {
+                    if shared && (has_uniform_qual(output.qual) || has_buffer_qual(output.qual)) {
+                        panic!("A uniform or buffer is declared in different frames by the vertex and fragment shaders")
+                    }
+                    if !shared && has_out_qual(output.qual) {
+                        panic!("A fragment in is declared in a different frame than the vertex out that writes it")
+                    }
+                }
+                j += 1;
+            }
+        }
+        i += 1;
+    }
+}
+
 #[macro_export]
 macro_rules! graphics_shader {
     ($($body:tt)*) => {{
@@ -968,22 +1835,51 @@ macro_rules! graphics_shader {
 #[macro_export]
 macro_rules! compile_valid_graphics_program {
     ($window:tt, $vertex:tt, $fragment:tt) => {{
+        compile_valid_graphics_program!($window, $vertex, $fragment, false)
+    }};
+    ($window:tt, $vertex:tt, $fragment:tt, $depth_test:tt) => {{
         let mut compile_buffer: [wgpu::VertexAttributeDescriptor; 32] =
             pipeline::wgpu_graphics_header::compile_buffer();
 
         const _: () = pipeline::wgpu_graphics_header::valid_vertex_shader(&$vertex);
         const _: () = pipeline::wgpu_graphics_header::valid_fragment_shader(&$fragment);
+        const _: () = pipeline::wgpu_graphics_header::valid_frames_between(&$vertex, &$fragment);
         let (x, y, z) = pipeline::wgpu_graphics_header::graphics_compile(
             &mut compile_buffer,
             &$window,
             &$vertex,
             &$fragment,
+            $depth_test,
         )
         .await;
         (x, y, z, compile_buffer)
     }};
 }
 
+// Compile on the device of another program so both can draw into the same pass
+#[macro_export]
+macro_rules! compile_valid_graphics_program_shared {
+    ($program:tt, $vertex:tt, $fragment:tt) => {{
+        compile_valid_graphics_program_shared!($program, $vertex, $fragment, false)
+    }};
+    ($program:tt, $vertex:tt, $fragment:tt, $depth_test:tt) => {{
+        let mut compile_buffer: [wgpu::VertexAttributeDescriptor; 32] =
+            pipeline::wgpu_graphics_header::compile_buffer();
+
+        const _: () = pipeline::wgpu_graphics_header::valid_vertex_shader(&$vertex);
+        const _: () = pipeline::wgpu_graphics_header::valid_fragment_shader(&$fragment);
+        const _: () = pipeline::wgpu_graphics_header::valid_frames_between(&$vertex, &$fragment);
+        let (x, y, z) = pipeline::wgpu_graphics_header::graphics_compile_shared(
+            &mut compile_buffer,
+            &$program,
+            &$vertex,
+            &$fragment,
+            $depth_test,
+        );
+        (x, y, z, compile_buffer)
+    }};
+}
+
 pub const fn graphics_starting_context(
     vertex: [&'static str; 32],
     fragment: GraphicsShader,
@@ -995,7 +1891,10 @@ pub const fn graphics_starting_context(
     let mut acc = 0;
 
     while acc < fragment.params.len() {
-        if has_in_qual(fragment.params[acc].qual) && has_uniform_qual(fragment.params[acc].qual) {
+        if has_in_qual(fragment.params[acc].qual)
+            && (has_uniform_qual(fragment.params[acc].qual)
+                || has_buffer_qual(fragment.params[acc].qual))
+        {
             uniforms_to_bind[uniform_acc] = fragment.params[acc].name;
             uniform_acc += 1;
         }
@@ -1029,3 +1928,61 @@ pub fn compile_buffer() -> [wgpu::VertexAttributeDescriptor; 32] {
     let y: Box<[wgpu::VertexAttributeDescriptor; 32]> = x.try_into().unwrap();
     *y
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTEX: GraphicsShader = GraphicsShader {
+        params: &[
+            PARAMETER {
+                qual: &[QUALIFIER::VERTEX, QUALIFIER::IN],
+                gtype: GLSLTYPE::Vec3,
+                name: "a_position",
+                frames: &[],
+            },
+            PARAMETER {
+                qual: &[QUALIFIER::UNIFORM, QUALIFIER::IN],
+                gtype: GLSLTYPE::Mat4,
+                name: "u_model",
+                frames: &[],
+            },
+            PARAMETER {
+                qual: &[QUALIFIER::BUFFER, QUALIFIER::IN],
+                gtype: GLSLTYPE::ArrayVec4,
+                name: "offsets",
+                frames: &[],
+            },
+            PARAMETER {
+                qual: &[QUALIFIER::VERTEX, QUALIFIER::IN],
+                gtype: GLSLTYPE::Vec3,
+                name: "a_normal",
+                frames: &[],
+            },
+        ],
+        body: "{ void main() {} }",
+    };
+
+    const FRAGMENT: GraphicsShader = GraphicsShader {
+        params: &[],
+        body: "{ void main() {} }",
+    };
+
+    fn binding_number(bindings: &GraphicsBindings, name: &str) -> u32 {
+        bindings
+            .bindings
+            .iter()
+            .find(|x| x.name == name)
+            .unwrap()
+            .binding_number
+    }
+
+    #[test]
+    fn read_only_buffers_are_numbered_with_the_uniforms() {
+        let (bindings, _, _, _) = create_bindings(&VERTEX, &FRAGMENT);
+        assert_eq!(binding_number(&bindings, "a_position"), 0);
+        assert_eq!(binding_number(&bindings, "a_normal"), 1);
+        assert_eq!(binding_number(&bindings, "u_model"), 0);
+        assert_eq!(binding_number(&bindings, "offsets"), 1);
+    }
+}
@@ -6,7 +6,7 @@ use syn::parse::{Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{braced, bracketed, parenthesized, parse_macro_input, Error, Ident, Token};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter;
 
 use rand::Rng;
@@ -14,9 +14,13 @@ use rand::Rng;
 struct Parameters {
     quals: Vec<Ident>,
     //glsl_type : Ident,
+    // The coordinate frames the parameter was declared with like mat4<model, world>
+    frames: Vec<Ident>,
     name: Ident,
 }
 
+const FRAMES: [&str; 4] = ["model", "world", "view", "clip"];
+
 impl Parse for Parameters {
     fn parse(input: ParseStream) -> Result<Self> {
         let qual_and_type;
@@ -38,6 +42,22 @@ impl Parse for Parameters {
         }
 
         qual_and_type.parse::<Ident>();
+        let mut frames = Vec::new();
+        if qual_and_type.peek(Token![<]) {
+            qual_and_type.parse::<Token![<]>()?;
+            let frame_lst =
+                Punctuated::<Ident, Token![,]>::parse_separated_nonempty(&qual_and_type)?;
+            qual_and_type.parse::<Token![>]>()?;
+            for frame in frame_lst.into_iter() {
+                if !FRAMES.contains(&frame.to_string().as_str()) {
+                    return Err(Error::new(
+                        frame.span(),
+                        format!("{} is not a frame, use one of {:?}", frame, FRAMES),
+                    ));
+                }
+                frames.push(frame);
+            }
+        }
         while !qual_and_type.is_empty() {
             let x;
             bracketed!(x in qual_and_type);
@@ -46,6 +66,7 @@ impl Parse for Parameters {
         let name = input.parse::<Ident>()?;
         Ok(Parameters {
             quals: quals.into_iter().collect(),
+            frames,
             name,
         })
     }
@@ -121,10 +142,16 @@ impl Parse for Shader {
     }
 }
 
+fn frame_list(frames: &[Ident]) -> String {
+    let names: Vec<String> = frames.iter().map(|f| f.to_string()).collect();
+    format!("<{}>", names.join(", "))
+}
+
 struct Context {
     context: Ident,
     ins: HashSet<Ident>,
     outs: HashSet<Ident>,
+    frames: HashMap<Ident, Vec<Ident>>,
 }
 
 impl Parse for Context {
@@ -135,22 +162,54 @@ impl Parse for Context {
 
         let mut ins = HashSet::new();
         let mut outs = HashSet::new();
+        let mut frames: HashMap<Ident, Vec<Ident>> = HashMap::new();
 
-        shaders.into_iter().for_each(|s| {
-            s.params.into_iter().for_each(|p| {
+        for s in shaders.into_iter() {
+            for p in s.params.into_iter() {
+                // Dynamic uniforms are given their values by draw_each rather than being bound, and it is the only way to draw their programs
+                if p.quals.contains(&format_ident!("dynamic")) {
+                    continue;
+                }
                 if p.quals.contains(&format_ident!("in")) {
                     if outs.contains(&p.name) {
                         outs.remove(&p.name);
                     } else {
+                        // A parameter in several stages takes the frames of whichever stage gives them
+                        match frames.get(&p.name) {
+                            Some(existing)
+                                if !existing.is_empty()
+                                    && !p.frames.is_empty()
+                                    && *existing != p.frames =>
+                            {
+                                return Err(Error::new(
+                                    p.name.span(),
+                                    format!(
+                                        "{} is declared with frames {} in one stage and {} in another",
+                                        p.name,
+                                        frame_list(existing),
+                                        frame_list(&p.frames)
+                                    ),
+                                ));
+                            }
+                            Some(existing) if !existing.is_empty() => {}
+                            _ => {
+                                frames.insert(p.name.clone(), p.frames);
+                            }
+                        }
                         ins.insert(p.name);
                     }
                 } else if p.quals.contains(&format_ident!("out")) {
                     outs.insert(p.name);
                 }
-            })
-        });
+            }
+        }
 
-        Ok(Context { context, ins, outs })
+        Ok(Context {
+            context,
+            ins,
+            outs,
+            frames,
+        })
     }
 }
 
@@ -162,6 +221,7 @@ pub fn init(_: TokenStream) -> TokenStream {
 
         trait AbstractBind {
             fn new() -> Self;
+            fn is_bound() -> bool;
         }
 
         struct Bound {}
@@ -172,14 +232,21 @@ pub fn init(_: TokenStream) -> TokenStream {
             fn new() -> Self {
                 Bound {}
             }
+            fn is_bound() -> bool {
+                true
+            }
         }
 
         impl AbstractBind for Unbound{
             fn new() -> Self {
                 Unbound {}
             }
+            fn is_bound() -> bool {
+                false
+            }
         }
 
+        // The inputs of a context that still need to be bound
         trait ContextInputs {
             fn inputs(&self) -> Vec<String>;
         }
@@ -230,9 +297,13 @@ pub fn generic_bindings(input: TokenStream) -> TokenStream {
             }
         }
 
-        impl ContextInputs for #context<#(#init),*> {
+        impl<#(#variables: AbstractBind),*> ContextInputs for #context<#(#variables),*> {
             fn inputs(&self) -> Vec<String> {
-                vec![#(stringify!(#input_vec).to_string()),*]
+                let mut unbound = Vec::new();
+                #(if !#variables::is_bound() {
+                    unbound.push(stringify!(#input_vec).to_string());
+                })*
+                unbound
             }
         }
 
@@ -241,7 +312,28 @@ pub fn generic_bindings(input: TokenStream) -> TokenStream {
                 f()
             }
             fn can_pipe(&self, b : &dyn ContextInputs) {
-                let a = vec![#(stringify!(#out_vec).to_string()),*];
+                self.can_pipe_mapped(b, &[]);
+            }
+            // Each (out, in) pair renames an out of this context to an in of the next
+            // Outs that aren't renamed flow onward by name, outs renamed to "" are left behind
+            // and the ins of the next context that were already bound are left alone
+            fn can_pipe_mapped(&self, b : &dyn ContextInputs, mapping : &[(&str, &str)]) {
+                let outs = vec![#(stringify!(#out_vec).to_string()),*];
+                for (from, to) in mapping.iter() {
+                    assert!(outs.iter().any(|x| x == from), "{} is not an out of this context", from);
+                    assert!(to.is_empty() || b.inputs().iter().any(|x| x == to), "{} is not an unbound in of the next context", to);
+                }
+                let a: Vec<String> = outs
+                    .into_iter()
+                    .map(|x| match mapping.iter().find(|(from, _)| *from == x) {
+                        Some((_, to)) => to.to_string(),
+                        None => x,
+                    })
+                    .filter(|x| !x.is_empty())
+                    .collect();
+                for x in a.iter() {
+                    assert!(b.inputs().contains(x), "{} is not an in of the next context, map it to \"\" to leave it behind", x);
+                }
                 assert!(b.inputs().iter().all(|item| a.contains(item)));
             }
         }
@@ -256,6 +348,18 @@ pub fn generic_bindings(input: TokenStream) -> TokenStream {
         let trait_name = format_ident!("BindField{}{}", i + 1, n1);
         let name = format_ident!("{}", input_vec[i]);
         let bind_name = format_ident!("bind_{}", input_vec[i]);
+
+        // Parameters declared with frames only take data tagged with the same frames
+        let frame_bound = match shader_params.frames.get(&input_vec[i]) {
+            Some(frames) if !frames.is_empty() => {
+                let frame_types = frames.iter().map(|f| {
+                    let f = f.to_string();
+                    format_ident!("{}{}", f[..1].to_uppercase(), f[1..])
+                });
+                quote! { + pipeline::helper::HasFrames<(#(pipeline::helper::#frame_types,)*)> }
+            }
+            _ => quote! {},
+        };
         let mut type_params = variables.clone();
         type_params.remove(i);
         type_params.insert(i, bound.clone());
@@ -300,11 +404,11 @@ pub fn generic_bindings(input: TokenStream) -> TokenStream {
         restricted_type.insert(i, bound.clone());
 
         all_expanded.push(quote!{
-            trait #trait_name<#(#trait_params: AbstractBind,)* B: Bindable, R: ProgramBindings, T: OutProgramBindings>{
+            trait #trait_name<#(#trait_params: AbstractBind,)* B: Bindable #frame_bound, R: ProgramBindings, T: OutProgramBindings>{
                 fn #bind_name(self, data : &B, program: &dyn Program, bindings: &mut R, out_bindings: &mut T) -> #context<#(#type_params),*>;
             }
 
-            impl<#(#restricted_abstract: AbstractBind,)* B: Bindable, R: ProgramBindings, T: OutProgramBindings> #trait_name<#(#restricted_trait,)* B, R, T> for &#context<#(#restricted_impl),*> {
+            impl<#(#restricted_abstract: AbstractBind,)* B: Bindable #frame_bound, R: ProgramBindings, T: OutProgramBindings> #trait_name<#(#restricted_trait,)* B, R, T> for &#context<#(#restricted_impl),*> {
                 fn #bind_name(self, data : &B, program: &dyn Program, bindings: &mut R, out_bindings: &mut T) -> #context<#(#restricted_type),*> {
                     Bindable::bind(
                         data,
@@ -319,7 +423,7 @@ pub fn generic_bindings(input: TokenStream) -> TokenStream {
                 }
             }
 
-            impl<#(#trait_params: AbstractBind,)* B: Bindable, R: ProgramBindings, T: OutProgramBindings> #trait_name<#(#trait_params,)* B, R, T> for #context<#(#impl_params),*> {
+            impl<#(#trait_params: AbstractBind,)* B: Bindable #frame_bound, R: ProgramBindings, T: OutProgramBindings> #trait_name<#(#trait_params,)* B, R, T> for #context<#(#impl_params),*> {
                 fn #bind_name(self, data : &B, program: &dyn Program, bindings: &mut R, out_bindings: &mut T) -> #context<#(#type_params),*>{
                     Bindable::bind(
                         data,